@@ -1,7 +1,7 @@
+mod middleware;
 mod models;
 mod utils;
-mod middleware;
 
+pub use middleware::*;
 pub use models::*;
 pub use utils::*;
-pub use middleware::*;
\ No newline at end of file
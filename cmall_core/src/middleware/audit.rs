@@ -0,0 +1,250 @@
+use std::net::SocketAddr;
+
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, Method},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use tracing::warn;
+
+use super::AuditRecorder;
+use crate::{AuditEntry, User};
+
+// 审计时读取的响应体上限，超过时不记录变更后的数据
+const MAX_AUDIT_BODY_SIZE: u64 = 1024 * 1024;
+// 写入审计日志前需要脱敏的字段
+const REDACTED_FIELDS: &[&str] = &[
+    "password",
+    "newPassword",
+    "oldPassword",
+    "tempPassword",
+    "token",
+    "refreshToken",
+    "mfaToken",
+    "secret",
+    "otpauthUri",
+    "recoveryCodes",
+];
+const REDACTED: &str = "******";
+
+// 记录已登录用户的 POST/DELETE 请求，需要在 verify_token 之后执行
+pub async fn audit<T>(State(state): State<T>, req: Request, next: Next) -> Response
+where
+    T: AuditRecorder + Clone + Send + Sync + 'static,
+{
+    let method = req.method().clone();
+    if method != Method::POST && method != Method::DELETE {
+        return next.run(req).await;
+    }
+    let Some(user) = req.extensions().get::<User>().cloned() else {
+        return next.run(req).await;
+    };
+    let path = req.uri().path().to_string();
+    let action = match req.extensions().get::<MatchedPath>() {
+        Some(matched) => format!("{} {}", method, matched.as_str()),
+        None => format!("{} {}", method, path),
+    };
+    let (target_type, mut target_id) = parse_target(&path, user.id);
+    let addr = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let ip = state.client_ip(req.headers(), addr);
+
+    let before = match target_id {
+        Some(id) => match state.snapshot(&target_type, id).await {
+            Ok(before) => before,
+            Err(e) => {
+                warn!("load audit snapshot error: {:?}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let res = next.run(req).await;
+    let status = res.status();
+    let (res, after) = if status.is_success() && method != Method::DELETE {
+        read_json_body(res).await
+    } else {
+        (res, None)
+    };
+    // 新建资源时从响应中获取 id
+    if target_id.is_none() {
+        target_id = after
+            .as_ref()
+            .and_then(|v| v.get("id"))
+            .and_then(Value::as_i64);
+    }
+
+    let entry = AuditEntry {
+        actor_id: user.id,
+        actor_name: user.username,
+        action,
+        target_type,
+        target_id,
+        before_data: before.map(redact),
+        after_data: after.map(redact),
+        status_code: status.as_u16() as i32,
+        ip,
+    };
+    if let Err(e) = state.record(entry).await {
+        warn!("write audit log error: {:?}", e);
+    }
+    res
+}
+
+// 从 /api/v1/user/13/roles/2 中解析出 ("user", Some(13))，me 表示当前用户
+fn parse_target(path: &str, actor_id: i64) -> (String, Option<i64>) {
+    let path = path.strip_prefix("/api/v1").unwrap_or(path);
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    let target_type = segments.next().unwrap_or_default().to_string();
+    let target_id = match segments.next() {
+        Some("me") => Some(actor_id),
+        Some(id) => id.parse().ok(),
+        None => None,
+    };
+    (target_type, target_id)
+}
+
+// 只读取 JSON 响应，文件下载等响应原样返回
+async fn read_json_body(res: Response) -> (Response, Option<Value>) {
+    let is_json = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let size = res.body().size_hint().upper();
+    if !is_json || size.is_none_or(|size| size > MAX_AUDIT_BODY_SIZE) {
+        return (res, None);
+    }
+    let (parts, body) = res.into_parts();
+    match to_bytes(body, MAX_AUDIT_BODY_SIZE as usize).await {
+        Ok(bytes) => {
+            let after = serde_json::from_slice(&bytes).ok();
+            (Response::from_parts(parts, Body::from(bytes)), after)
+        }
+        Err(e) => {
+            warn!("read response body for audit error: {:?}", e);
+            (Response::from_parts(parts, Body::empty()), None)
+        }
+    }
+}
+
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    if REDACTED_FIELDS.contains(&k.as_str()) {
+                        (k, Value::String(REDACTED.to_string()))
+                    } else {
+                        (k, redact(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(redact).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod test_audit {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        middleware::from_fn_with_state,
+        routing::post,
+        Extension, Json, Router,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct MockState {
+        entries: Arc<Mutex<Vec<AuditEntry>>>,
+    }
+
+    impl AuditRecorder for MockState {
+        type Error = Infallible;
+
+        fn client_ip(&self, _headers: &HeaderMap, _addr: Option<SocketAddr>) -> String {
+            "127.0.0.1".to_string()
+        }
+
+        async fn snapshot(&self, target_type: &str, id: i64) -> Result<Option<Value>, Self::Error> {
+            Ok(Some(
+                json!({ "type": target_type, "id": id, "status": "active" }),
+            ))
+        }
+
+        async fn record(&self, entry: AuditEntry) -> Result<(), Self::Error> {
+            self.entries.lock().unwrap().push(entry);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_target_should_work() {
+        assert_eq!(
+            parse_target("/api/v1/user/13", 1),
+            ("user".into(), Some(13))
+        );
+        assert_eq!(
+            parse_target("/user/13/roles/2", 1),
+            ("user".into(), Some(13))
+        );
+        assert_eq!(
+            parse_target("/user/me/password", 7),
+            ("user".into(), Some(7))
+        );
+        assert_eq!(parse_target("/role/", 1), ("role".into(), None));
+    }
+
+    #[tokio::test]
+    async fn test_audit_should_record_mutations() -> Result<()> {
+        let state = MockState::default();
+        let user = User::new(1, "Eli Shi", "elixy@qq.com", "138");
+        let app = Router::new()
+            .route(
+                "/user/:id/reset-password",
+                post(|| async { Json(json!({ "tempPassword": "abc" })) }),
+            )
+            .layer(from_fn_with_state(state.clone(), audit::<MockState>))
+            .layer(Extension(user));
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/user/13/reset-password")
+            .body(Body::empty())?;
+        let res = app.clone().oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(body, r#"{"tempPassword":"abc"}"#);
+
+        let req = Request::builder()
+            .uri("/user/13/reset-password")
+            .body(Body::empty())?;
+        app.oneshot(req).await?;
+
+        let entries = state.entries.lock().unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.action, "POST /user/:id/reset-password");
+        assert_eq!(entry.target_type, "user");
+        assert_eq!(entry.target_id, Some(13));
+        assert_eq!(entry.after_data, Some(json!({ "tempPassword": REDACTED })));
+        assert_eq!(entry.before_data.as_ref().unwrap()["status"], "active");
+        Ok(())
+    }
+}
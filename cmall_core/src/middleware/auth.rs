@@ -1,4 +1,3 @@
-
 use axum::{
     extract::{FromRequestParts, Query, Request, State},
     http::StatusCode,
@@ -44,10 +43,11 @@ where
                 }
             }
         };
-    let req = match state.verify(&token) {
-        Ok(user) => {
+    let req = match state.verify(&token).await {
+        Ok(verified) => {
             let mut req = Request::from_parts(parts, body);
-            req.extensions_mut().insert(user);
+            req.extensions_mut().insert(verified.user);
+            req.extensions_mut().insert(verified.session_id);
             req
         }
         Err(e) => {
@@ -1,7 +1,10 @@
+mod audit;
 mod auth;
-use std::fmt;
+mod permission;
+use std::{fmt, future::Future, net::SocketAddr};
 
-use axum::Router;
+use axum::{http::HeaderMap, Router};
+use serde_json::Value;
 use tower::ServiceBuilder;
 use tower_http::{
     compression::CompressionLayer,
@@ -10,14 +13,54 @@ use tower_http::{
 };
 use tracing::Level;
 
+pub use audit::audit;
 pub use auth::verify_token;
+pub use permission::{verify_permission, RequirePermission, RequirePermissionExt};
 
-use crate::User;
+use crate::{AuditEntry, User};
+
+// 当前请求所属的登录会话，由 verify_token 写入请求扩展
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionId(pub String);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedToken {
+    pub user: User,
+    pub session_id: SessionId,
+}
 
 pub trait TokenVerify {
     type Error: fmt::Debug;
 
-    fn verify(&self, token: &str) -> Result<User, Self::Error>;
+    fn verify(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = Result<VerifiedToken, Self::Error>> + Send;
+}
+
+pub trait PermissionVerify {
+    type Error: fmt::Debug;
+
+    fn permissions(
+        &self,
+        user: &User,
+    ) -> impl Future<Output = Result<Vec<String>, Self::Error>> + Send;
+}
+
+pub trait AuditRecorder {
+    type Error: fmt::Debug;
+
+    // 获取客户端地址
+    fn client_ip(&self, headers: &HeaderMap, addr: Option<SocketAddr>) -> String;
+
+    // 加载操作对象变更前的数据
+    fn snapshot(
+        &self,
+        target_type: &str,
+        target_id: i64,
+    ) -> impl Future<Output = Result<Option<Value>, Self::Error>> + Send;
+
+    fn record(&self, entry: AuditEntry) -> impl Future<Output = Result<(), Self::Error>> + Send;
 }
 
 pub fn setup_layer(app: Router) -> Router {
@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+    Json,
+};
+use tracing::warn;
+
+use super::PermissionVerify;
+use crate::{ErrorOutput, User};
+
+#[derive(Debug, Clone)]
+pub struct RequirePermission<T> {
+    state: T,
+    code: &'static str,
+}
+
+impl<T> RequirePermission<T> {
+    pub fn new(state: T, code: &'static str) -> Self {
+        Self { state, code }
+    }
+}
+
+pub async fn verify_permission<T>(
+    State(required): State<RequirePermission<T>>,
+    req: Request,
+    next: Next,
+) -> Response
+where
+    T: PermissionVerify + Clone + Send + Sync + 'static,
+{
+    let Some(user) = req.extensions().get::<User>().cloned() else {
+        let msg = "missing signed in user, verify_token should run first";
+        warn!(msg);
+        return (StatusCode::UNAUTHORIZED, Json(ErrorOutput::new(msg))).into_response();
+    };
+    let permissions = match required.state.permissions(&user).await {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            let msg = format!("load permissions error: {:?}", e);
+            warn!(msg);
+            return (StatusCode::FORBIDDEN, Json(ErrorOutput::new(msg))).into_response();
+        }
+    };
+    if !permissions.iter().any(|p| p == required.code) {
+        let msg = format!("permission denied: {}", required.code);
+        warn!("{} for user {}", msg, user.id);
+        return (StatusCode::FORBIDDEN, Json(ErrorOutput::new(msg))).into_response();
+    }
+    next.run(req).await
+}
+
+pub trait RequirePermissionExt {
+    // 为路由挂载权限校验，权限码对应按钮类型菜单的 menu_id，例如 user:delete
+    fn require_permission<T>(self, state: &T, code: &'static str) -> Self
+    where
+        T: PermissionVerify + Clone + Send + Sync + 'static;
+}
+
+impl<S> RequirePermissionExt for MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn require_permission<T>(self, state: &T, code: &'static str) -> Self
+    where
+        T: PermissionVerify + Clone + Send + Sync + 'static,
+    {
+        self.route_layer(from_fn_with_state(
+            RequirePermission::new(state.clone(), code),
+            verify_permission::<T>,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test_permission {
+    use std::convert::Infallible;
+
+    use super::*;
+    use anyhow::Result;
+    use axum::{body::Body, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct MockState;
+
+    impl PermissionVerify for MockState {
+        type Error = Infallible;
+
+        async fn permissions(&self, user: &User) -> Result<Vec<String>, Self::Error> {
+            match user.id {
+                1 => Ok(vec!["user:delete".to_string()]),
+                _ => Ok(vec![]),
+            }
+        }
+    }
+
+    fn app(user: User) -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|| async { "ok" }).require_permission(&MockState, "user:delete"),
+            )
+            .layer(Extension(user))
+    }
+
+    #[tokio::test]
+    async fn test_require_permission_should_work() -> Result<()> {
+        let user = User::new(1, "Eli Shi", "elixy@qq.com", "138");
+        let req = Request::builder().uri("/").body(Body::empty())?;
+        let res = app(user).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let user = User::new(2, "Alice Shi", "alice@acme.org", "139");
+        let req = Request::builder().uri("/").body(Body::empty())?;
+        let res = app(user).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+// 审计中间件生成的待写入记录
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub actor_id: i64,
+    pub actor_name: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub before_data: Option<Value>,
+    pub after_data: Option<Value>,
+    pub status_code: i32,
+    pub ip: String,
+}
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLog {
+    pub id: i64,
+    pub actor_id: i64,
+    pub actor_name: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub before_data: Option<Value>,
+    pub after_data: Option<Value>,
+    pub status_code: i32,
+    pub ip: String,
+    pub create_time: DateTime<Utc>,
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+// 购物车中的一行商品，price 和 available 为读取时的最新值，金额以分为单位
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CartItem {
+    pub sku_id: i64,
+    pub sku_code: String,
+    pub product_id: i64,
+    pub product_name: String,
+    pub attributes: Json<BTreeMap<String, String>>,
+    pub quantity: i64,
+    // 加入购物车时的价格
+    pub added_price: i64,
+    pub price: i64,
+    // SKU 启用且商品在售
+    pub on_sale: bool,
+    pub available: i64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CartIssue {
+    // 商品已下架或 SKU 已停用
+    Unavailable,
+    InsufficientStock,
+    PriceChanged,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CartLine {
+    #[serde(flatten)]
+    pub item: CartItem,
+    pub line_total: i64,
+    pub issues: Vec<CartIssue>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Cart {
+    pub lines: Vec<CartLine>,
+    // 只统计可以下单的商品
+    pub total_quantity: i64,
+    pub subtotal: i64,
+}
+
+impl CartLine {
+    // 价格变动只做提示，不影响下单
+    pub fn purchasable(&self) -> bool {
+        !self
+            .issues
+            .iter()
+            .any(|i| matches!(i, CartIssue::Unavailable | CartIssue::InsufficientStock))
+    }
+}
+
+impl Cart {
+    // 按最新价格计算金额，溢出时返回 None
+    pub fn build(items: Vec<CartItem>) -> Option<Self> {
+        let mut cart = Cart::default();
+        for item in items {
+            let mut issues = Vec::new();
+            if !item.on_sale {
+                issues.push(CartIssue::Unavailable);
+            } else if item.available < item.quantity {
+                issues.push(CartIssue::InsufficientStock);
+            }
+            if item.price != item.added_price {
+                issues.push(CartIssue::PriceChanged);
+            }
+            let line = CartLine {
+                line_total: item.price.checked_mul(item.quantity)?,
+                item,
+                issues,
+            };
+            if line.purchasable() {
+                cart.total_quantity = cart.total_quantity.checked_add(line.item.quantity)?;
+                cart.subtotal = cart.subtotal.checked_add(line.line_total)?;
+            }
+            cart.lines.push(line);
+        }
+        Some(cart)
+    }
+}
+
+#[cfg(test)]
+mod test_cart {
+    use super::*;
+
+    fn item(sku_id: i64, quantity: i64, price: i64, available: i64) -> CartItem {
+        CartItem {
+            sku_id,
+            sku_code: format!("SKU-{}", sku_id),
+            product_id: 1,
+            product_name: "cup".to_string(),
+            attributes: Json(BTreeMap::new()),
+            quantity,
+            added_price: price,
+            price,
+            on_sale: true,
+            available,
+        }
+    }
+
+    #[test]
+    fn test_cart_build_should_skip_unpurchasable_lines() {
+        let mut off_shelf = item(3, 1, 1000, 10);
+        off_shelf.on_sale = false;
+        let mut repriced = item(4, 3, 199, 10);
+        repriced.added_price = 299;
+        let items = vec![item(1, 2, 1999, 5), item(2, 6, 500, 5), off_shelf, repriced];
+        let cart = Cart::build(items).unwrap();
+
+        let issues: Vec<Vec<CartIssue>> = cart.lines.iter().map(|l| l.issues.clone()).collect();
+        assert_eq!(
+            issues,
+            vec![
+                vec![],
+                vec![CartIssue::InsufficientStock],
+                vec![CartIssue::Unavailable],
+                vec![CartIssue::PriceChanged],
+            ]
+        );
+        assert_eq!(cart.lines[1].line_total, 3000);
+        assert_eq!(cart.total_quantity, 5);
+        assert_eq!(cart.subtotal, 1999 * 2 + 199 * 3);
+
+        assert!(Cart::build(vec![item(1, 2, i64::MAX, 5)]).is_none());
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{build_tree, EffectStatus};
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub name: String,
+    pub icon: String,
+    pub order_num: i32,
+    pub status: EffectStatus,
+    pub description: String,
+    pub create_time: DateTime<Utc>,
+    pub create_by: String,
+    pub update_time: DateTime<Utc>,
+    pub update_by: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub category: Category,
+    pub children: Vec<CategoryNode>,
+}
+
+impl CategoryNode {
+    // 根据 parent_id 组装分类树，父节点不存在的分类作为根节点
+    pub fn build_tree(categories: Vec<Category>) -> Vec<CategoryNode> {
+        build_tree(
+            categories,
+            |c| c.id,
+            |c| c.parent_id,
+            |c| c.order_num,
+            |category, children| CategoryNode { category, children },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_category {
+    use super::*;
+
+    fn category(id: i64, parent_id: Option<i64>, order_num: i32) -> Category {
+        Category {
+            id,
+            parent_id,
+            name: format!("category {}", id),
+            icon: "".to_string(),
+            order_num,
+            status: EffectStatus::Enable,
+            description: "".to_string(),
+            create_time: Utc::now(),
+            create_by: "admin".to_string(),
+            update_time: Utc::now(),
+            update_by: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_tree_should_order_siblings() {
+        let categories = vec![
+            category(1, None, 2),
+            category(2, None, 1),
+            category(3, Some(1), 2),
+            category(4, Some(1), 1),
+        ];
+        let tree = CategoryNode::build_tree(categories);
+
+        let roots: Vec<i64> = tree.iter().map(|n| n.category.id).collect();
+        assert_eq!(roots, vec![2, 1]);
+        let children: Vec<i64> = tree[1].children.iter().map(|n| n.category.id).collect();
+        assert_eq!(children, vec![4, 3]);
+    }
+}
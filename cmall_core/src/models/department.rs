@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{build_tree, EffectStatus};
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Department {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub identifier: String,
+    pub name: String,
+    pub order_num: i32,
+    pub status: EffectStatus,
+    pub description: String,
+    pub create_time: DateTime<Utc>,
+    pub create_by: String,
+    pub update_time: DateTime<Utc>,
+    pub update_by: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DepartmentNode {
+    #[serde(flatten)]
+    pub department: Department,
+    pub children: Vec<DepartmentNode>,
+}
+
+impl DepartmentNode {
+    // 根据 parent_id 组装部门树，父节点不存在的部门作为根节点
+    pub fn build_tree(departments: Vec<Department>) -> Vec<DepartmentNode> {
+        build_tree(
+            departments,
+            |d| d.id,
+            |d| d.parent_id,
+            |d| d.order_num,
+            |department, children| DepartmentNode {
+                department,
+                children,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_department {
+    use super::*;
+
+    fn dept(id: i64, parent_id: Option<i64>, order_num: i32) -> Department {
+        Department {
+            id,
+            parent_id,
+            identifier: format!("dept-{}", id),
+            name: format!("dept {}", id),
+            order_num,
+            status: EffectStatus::Enable,
+            description: "".to_string(),
+            create_time: Utc::now(),
+            create_by: "admin".to_string(),
+            update_time: Utc::now(),
+            update_by: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_tree_should_work() {
+        let depts = vec![
+            dept(1, None, 0),
+            dept(2, Some(1), 2),
+            dept(3, Some(1), 1),
+            dept(4, Some(3), 0),
+            // parent filtered out of the result set, promoted to root
+            dept(5, Some(99), 0),
+        ];
+        let tree = DepartmentNode::build_tree(depts);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].department.id, 1);
+        assert_eq!(tree[1].department.id, 5);
+        let children: Vec<i64> = tree[0].children.iter().map(|n| n.department.id).collect();
+        assert_eq!(children, vec![3, 2]);
+        assert_eq!(tree[0].children[0].children[0].department.id, 4);
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "export_status", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+// 数据量较大时在后台生成导出文件，完成后通过下载链接获取
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub id: String,
+    pub user_id: i64,
+    pub resource: String,
+    pub status: ExportStatus,
+    pub file_name: String,
+    #[serde(skip)]
+    pub file_path: String,
+    pub row_count: i64,
+    pub error: Option<String>,
+    pub create_time: DateTime<Utc>,
+    pub finish_time: Option<DateTime<Utc>>,
+}
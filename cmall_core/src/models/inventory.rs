@@ -0,0 +1,135 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "stock_movement_kind", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+pub enum StockMovementKind {
+    Inbound,
+    Sale,
+    Return,
+    Adjustment,
+    Reserve,
+    Release,
+    TransferOut,
+    TransferIn,
+}
+
+impl fmt::Display for StockMovementKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StockMovementKind::Inbound => write!(f, "inbound"),
+            StockMovementKind::Sale => write!(f, "sale"),
+            StockMovementKind::Return => write!(f, "return"),
+            StockMovementKind::Adjustment => write!(f, "adjustment"),
+            StockMovementKind::Reserve => write!(f, "reserve"),
+            StockMovementKind::Release => write!(f, "release"),
+            StockMovementKind::TransferOut => write!(f, "transfer_out"),
+            StockMovementKind::TransferIn => write!(f, "transfer_in"),
+        }
+    }
+}
+
+// 已预留的库存仍在库中，可售数量为 on_hand - reserved
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Inventory {
+    pub warehouse_id: i64,
+    pub sku_id: i64,
+    pub on_hand: i64,
+    pub reserved: i64,
+    pub update_time: DateTime<Utc>,
+}
+
+// 库存流水只追加不修改，quantity 和 reserved 为本次变动量
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StockMovement {
+    pub id: i64,
+    pub warehouse_id: i64,
+    pub sku_id: i64,
+    pub kind: StockMovementKind,
+    pub quantity: i64,
+    pub reserved: i64,
+    // 变动后的在库数量
+    pub balance: i64,
+    pub reason: String,
+    pub reference: String,
+    pub create_by: String,
+    pub create_time: DateTime<Utc>,
+}
+
+// 按 SKU 请求的数量，例如订单中的一行
+#[derive(Debug, Clone, Copy, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StockLine {
+    pub sku_id: i64,
+    pub quantity: i64,
+}
+
+// 分配到具体仓库的数量
+#[derive(Debug, Clone, Copy, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StockAllocation {
+    pub warehouse_id: i64,
+    pub sku_id: i64,
+    pub quantity: i64,
+}
+
+impl StockLine {
+    pub fn new(sku_id: i64, quantity: i64) -> Self {
+        Self { sku_id, quantity }
+    }
+}
+
+impl StockAllocation {
+    pub fn new(warehouse_id: i64, sku_id: i64, quantity: i64) -> Self {
+        Self {
+            warehouse_id,
+            sku_id,
+            quantity,
+        }
+    }
+}
+
+impl StockMovementKind {
+    // 按流水类型计算在库数量和预留数量的变动，quantity 为本次数量
+    pub fn deltas(&self, quantity: i64) -> (i64, i64) {
+        match self {
+            StockMovementKind::Inbound
+            | StockMovementKind::Return
+            | StockMovementKind::TransferIn => (quantity, 0),
+            // 盘点调整的数量可以为负
+            StockMovementKind::Adjustment => (quantity, 0),
+            StockMovementKind::Reserve => (0, quantity),
+            StockMovementKind::Release => (0, -quantity),
+            // 出库和调出时同时扣减预留
+            StockMovementKind::Sale | StockMovementKind::TransferOut => (-quantity, -quantity),
+        }
+    }
+}
+
+impl Inventory {
+    pub fn available(&self) -> i64 {
+        self.on_hand - self.reserved
+    }
+}
+
+#[cfg(test)]
+mod test_inventory {
+    use super::*;
+
+    #[test]
+    fn test_movement_deltas_should_keep_available() {
+        assert_eq!(StockMovementKind::Inbound.deltas(5), (5, 0));
+        assert_eq!(StockMovementKind::Adjustment.deltas(-2), (-2, 0));
+        assert_eq!(StockMovementKind::Reserve.deltas(3), (0, 3));
+        assert_eq!(StockMovementKind::Release.deltas(3), (0, -3));
+        // 出库不影响可售数量
+        let (on_hand, reserved) = StockMovementKind::Sale.deltas(3);
+        assert_eq!(on_hand - reserved, 0);
+    }
+}
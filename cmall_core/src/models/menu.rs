@@ -0,0 +1,134 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::EffectStatus;
+
+pub const ROOT_MENU_ID: &str = "root";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, PartialOrd, sqlx::Type)]
+#[sqlx(type_name = "menu_type", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum MenuType {
+    Menu,
+    Link,
+    Button,
+}
+
+impl fmt::Display for MenuType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MenuType::Menu => write!(f, "menu"),
+            MenuType::Link => write!(f, "link"),
+            MenuType::Button => write!(f, "button"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Menu {
+    pub id: i64,
+    pub menu_id: String,
+    pub path: String,
+    pub chinese_name: String,
+    pub english_name: String,
+    pub icon: String,
+    pub order_num: i32,
+    #[sqlx(rename = "type")]
+    #[serde(rename = "type")]
+    pub menu_type: MenuType,
+    pub parent_menu_id: String,
+    pub status: EffectStatus,
+    pub description: String,
+    pub create_time: DateTime<Utc>,
+    pub create_by: String,
+    pub update_time: DateTime<Utc>,
+    pub update_by: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuNode {
+    #[serde(flatten)]
+    pub menu: Menu,
+    pub children: Vec<MenuNode>,
+}
+
+impl MenuNode {
+    // 根据 parent_menu_id 组装菜单树，同级按 order_num 排序
+    pub fn build_tree(menus: Vec<Menu>) -> Vec<MenuNode> {
+        let ids: Vec<String> = menus.iter().map(|m| m.menu_id.clone()).collect();
+        let mut children: HashMap<String, Vec<Menu>> = HashMap::new();
+        for menu in menus {
+            let parent = if ids.contains(&menu.parent_menu_id) {
+                menu.parent_menu_id.clone()
+            } else {
+                ROOT_MENU_ID.to_string()
+            };
+            children.entry(parent).or_default().push(menu);
+        }
+        Self::attach(ROOT_MENU_ID, &mut children)
+    }
+
+    fn attach(parent: &str, children: &mut HashMap<String, Vec<Menu>>) -> Vec<MenuNode> {
+        let mut menus = children.remove(parent).unwrap_or_default();
+        menus.sort_by_key(|m| (m.order_num, m.id));
+        menus
+            .into_iter()
+            .map(|menu| {
+                let children = Self::attach(&menu.menu_id, children);
+                MenuNode { menu, children }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_menu {
+    use super::*;
+
+    fn menu(id: i64, menu_id: &str, parent_menu_id: &str, order_num: i32) -> Menu {
+        Menu {
+            id,
+            menu_id: menu_id.to_string(),
+            path: format!("/{}", menu_id),
+            chinese_name: menu_id.to_string(),
+            english_name: menu_id.to_string(),
+            icon: "".to_string(),
+            order_num,
+            menu_type: MenuType::Menu,
+            parent_menu_id: parent_menu_id.to_string(),
+            status: EffectStatus::Enable,
+            description: "".to_string(),
+            create_time: Utc::now(),
+            create_by: "admin".to_string(),
+            update_time: Utc::now(),
+            update_by: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_tree_should_work() {
+        let menus = vec![
+            menu(1, "system", ROOT_MENU_ID, 2),
+            menu(2, "dashboard", ROOT_MENU_ID, 1),
+            menu(3, "role", "system", 2),
+            menu(4, "user", "system", 1),
+            menu(5, "user:delete", "user", 0),
+        ];
+        let tree = MenuNode::build_tree(menus);
+
+        let roots: Vec<&str> = tree.iter().map(|n| n.menu.menu_id.as_str()).collect();
+        assert_eq!(roots, vec!["dashboard", "system"]);
+        let children: Vec<&str> = tree[1]
+            .children
+            .iter()
+            .map(|n| n.menu.menu_id.as_str())
+            .collect();
+        assert_eq!(children, vec!["user", "role"]);
+        assert_eq!(tree[1].children[0].children[0].menu.menu_id, "user:delete");
+    }
+}
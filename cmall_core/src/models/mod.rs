@@ -2,6 +2,41 @@ mod user;
 
 pub use user::*;
 
-
 mod role;
-pub use role::*;
\ No newline at end of file
+pub use role::*;
+
+mod department;
+pub use department::*;
+
+mod menu;
+pub use menu::*;
+
+mod audit;
+pub use audit::*;
+
+mod export;
+pub use export::*;
+
+mod product;
+pub use product::*;
+
+mod category;
+pub use category::*;
+
+mod inventory;
+pub use inventory::*;
+
+mod warehouse;
+pub use warehouse::*;
+
+mod cart;
+pub use cart::*;
+
+mod order;
+pub use order::*;
+
+mod payment;
+pub use payment::*;
+
+mod error;
+pub use error::*;
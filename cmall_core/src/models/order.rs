@@ -0,0 +1,120 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+use crate::StockMovementKind;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "order_status", rename_all = "snake_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum OrderStatus {
+    PendingPayment,
+    Paid,
+    Shipped,
+    Delivered,
+    Completed,
+    // 付款前由用户或管理员取消
+    Cancelled,
+    // 超时未付款或付款后未发货即退款关闭
+    Closed,
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderStatus::PendingPayment => write!(f, "pending-payment"),
+            OrderStatus::Paid => write!(f, "paid"),
+            OrderStatus::Shipped => write!(f, "shipped"),
+            OrderStatus::Delivered => write!(f, "delivered"),
+            OrderStatus::Completed => write!(f, "completed"),
+            OrderStatus::Cancelled => write!(f, "cancelled"),
+            OrderStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl OrderStatus {
+    // 订单状态机，返回 None 表示不允许该状态变更，
+    // 否则返回变更时需要对预留库存执行的操作
+    pub fn transition(&self, to: OrderStatus) -> Option<Option<StockMovementKind>> {
+        use OrderStatus::*;
+        match (self, to) {
+            (PendingPayment, Paid) => Some(None),
+            (PendingPayment, Cancelled) | (PendingPayment, Closed) | (Paid, Closed) => {
+                Some(Some(StockMovementKind::Release))
+            }
+            // 发货时扣减在库数量和预留数量
+            (Paid, Shipped) => Some(Some(StockMovementKind::Sale)),
+            (Shipped, Delivered) | (Delivered, Completed) => Some(None),
+            _ => None,
+        }
+    }
+}
+
+// 金额均以分为单位
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+    pub id: i64,
+    pub user_id: i64,
+    pub status: OrderStatus,
+    pub total_amount: i64,
+    pub remark: String,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+impl Order {
+    // 订单在库存流水中的业务单号
+    pub fn reference(&self) -> String {
+        format!("SO-{}", self.id)
+    }
+}
+
+// 下单时的商品快照，之后商品改价或改名不影响订单
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderItem {
+    pub sku_id: i64,
+    pub sku_code: String,
+    pub product_id: i64,
+    pub product_name: String,
+    pub attributes: Json<BTreeMap<String, String>>,
+    pub price: i64,
+    pub quantity: i64,
+    pub line_total: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderDetail {
+    #[serde(flatten)]
+    pub order: Order,
+    pub items: Vec<OrderItem>,
+}
+
+#[cfg(test)]
+mod test_order {
+    use super::*;
+
+    #[test]
+    fn test_order_status_transition() {
+        use OrderStatus::*;
+        assert_eq!(PendingPayment.transition(Paid), Some(None));
+        assert_eq!(
+            Paid.transition(Shipped),
+            Some(Some(StockMovementKind::Sale))
+        );
+        assert_eq!(
+            PendingPayment.transition(Cancelled),
+            Some(Some(StockMovementKind::Release))
+        );
+        // 发货后不能取消或关闭，也不能跳过中间状态
+        assert_eq!(Shipped.transition(Cancelled), None);
+        assert_eq!(Shipped.transition(Closed), None);
+        assert_eq!(Paid.transition(Completed), None);
+        assert_eq!(Completed.transition(PendingPayment), None);
+    }
+}
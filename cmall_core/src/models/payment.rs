@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "payment_status", rename_all = "snake_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum PaymentStatus {
+    Pending,
+    Succeeded,
+    Failed,
+    // 已决定退款、等待渠道确认，失败时由后台任务重试
+    Refunding,
+    Refunded,
+}
+
+// 每次发起支付都会新增一条记录，external_id 为支付渠道的交易号
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Payment {
+    pub id: i64,
+    pub order_id: i64,
+    pub gateway: String,
+    pub external_id: Option<String>,
+    pub amount: i64,
+    pub status: PaymentStatus,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+use crate::EffectStatus;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "product_status", rename_all = "snake_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum ProductStatus {
+    Draft,
+    OnSale,
+    OffShelf,
+}
+
+impl fmt::Display for ProductStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProductStatus::Draft => write!(f, "draft"),
+            ProductStatus::OnSale => write!(f, "on-sale"),
+            ProductStatus::OffShelf => write!(f, "off-shelf"),
+        }
+    }
+}
+
+// 价格均以分为单位保存
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Product {
+    pub id: i64,
+    pub category_id: Option<i64>,
+    pub name: String,
+    pub description: String,
+    pub status: ProductStatus,
+    pub price: i64,
+    pub create_time: DateTime<Utc>,
+    pub create_by: String,
+    pub update_time: DateTime<Utc>,
+    pub update_by: String,
+}
+
+// 商品的规格定义，例如 颜色: [红, 蓝]
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductAttribute {
+    pub id: i64,
+    pub product_id: i64,
+    pub name: String,
+    pub options: Vec<String>,
+    pub order_num: i32,
+}
+
+// SKU 的 attributes 为规格名到规格值的映射，例如 {"颜色": "红", "尺码": "M"}
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Sku {
+    pub id: i64,
+    pub product_id: i64,
+    pub sku_code: String,
+    pub price: i64,
+    pub attributes: Json<BTreeMap<String, String>>,
+    pub status: EffectStatus,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductDetail {
+    #[serde(flatten)]
+    pub product: Product,
+    pub attributes: Vec<ProductAttribute>,
+    pub skus: Vec<Sku>,
+}
+
+// 检查 SKU 的规格组合是否与商品的规格定义一致，每个规格都必须取一个已定义的值
+pub fn check_sku_attributes(
+    attributes: &[ProductAttribute],
+    combination: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    for (name, value) in combination {
+        let attribute = attributes
+            .iter()
+            .find(|a| &a.name == name)
+            .ok_or_else(|| format!("unknown attribute: {}", name))?;
+        if !attribute.options.contains(value) {
+            return Err(format!("invalid value {} for attribute {}", value, name));
+        }
+    }
+    if let Some(missing) = attributes
+        .iter()
+        .find(|a| !combination.contains_key(&a.name))
+    {
+        return Err(format!("missing attribute: {}", missing.name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_product {
+    use super::*;
+
+    fn attribute(name: &str, values: &[&str]) -> ProductAttribute {
+        ProductAttribute {
+            id: 0,
+            product_id: 1,
+            name: name.to_string(),
+            options: values.iter().map(|v| v.to_string()).collect(),
+            order_num: 0,
+        }
+    }
+
+    fn combination(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_sku_attributes_should_work() {
+        let attributes = vec![
+            attribute("color", &["red", "blue"]),
+            attribute("size", &["S", "M"]),
+        ];
+        assert!(check_sku_attributes(
+            &attributes,
+            &combination(&[("color", "red"), ("size", "M")])
+        )
+        .is_ok());
+        assert!(check_sku_attributes(&attributes, &combination(&[("color", "red")])).is_err());
+        assert!(check_sku_attributes(
+            &attributes,
+            &combination(&[("color", "green"), ("size", "M")])
+        )
+        .is_err());
+        assert!(check_sku_attributes(
+            &attributes,
+            &combination(&[("color", "red"), ("size", "M"), ("fit", "slim")])
+        )
+        .is_err());
+        // 没有规格的商品只能有一个空组合的 SKU
+        assert!(check_sku_attributes(&[], &BTreeMap::new()).is_ok());
+    }
+}
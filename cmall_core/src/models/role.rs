@@ -4,8 +4,6 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-
-
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, PartialOrd, sqlx::Type)]
 #[sqlx(type_name = "effect_status", rename_all = "snake_case")]
 #[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
@@ -14,7 +12,6 @@ pub enum EffectStatus {
     Disable,
 }
 
-
 impl fmt::Display for EffectStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -24,7 +21,6 @@ impl fmt::Display for EffectStatus {
     }
 }
 
-
 #[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Role {
@@ -33,8 +29,10 @@ pub struct Role {
     pub name: String,
     pub status: EffectStatus,
     pub description: String,
+    #[sqlx(default)]
+    pub mfa_required: bool,
     pub create_time: DateTime<Utc>,
     pub create_by: String,
     pub update_time: DateTime<Utc>,
     pub update_by: String,
-}
\ No newline at end of file
+}
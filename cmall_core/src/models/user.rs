@@ -25,6 +25,13 @@ impl fmt::Display for UserStatus {
     }
 }
 
+impl UserStatus {
+    // 停用的用户不能登录，已签发的 token 也随之失效
+    pub fn can_sign_in(&self) -> bool {
+        !matches!(self, UserStatus::Off)
+    }
+}
+
 #[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
@@ -39,11 +46,27 @@ pub struct User {
     pub avatar: String,
     pub status: UserStatus,
     pub roles: Vec<i64>,
+    #[sqlx(default)]
+    pub must_change_password: bool,
+    #[sqlx(default)]
+    pub failed_attempts: i32,
+    #[sqlx(default)]
+    pub locked_until: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    pub mfa_enabled: bool,
+    // 用户拥有的已启用角色中是否有要求两步验证的角色
+    #[sqlx(default)]
+    pub mfa_required: bool,
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
 }
 
 impl User {
+    // 连续登录失败达到阈值后账号被临时锁定
+    pub fn is_locked(&self) -> bool {
+        self.locked_until.is_some_and(|t| t > Utc::now())
+    }
+
     pub fn new(id: i64, username: &str, email: &str, phone: &str) -> Self {
         Self {
             id,
@@ -57,6 +80,11 @@ impl User {
             create_time: chrono::Utc::now(),
             update_time: chrono::Utc::now(),
             roles: [1, 2].to_vec(),
+            must_change_password: false,
+            failed_attempts: 0,
+            locked_until: None,
+            mfa_enabled: false,
+            mfa_required: false,
         }
     }
 }
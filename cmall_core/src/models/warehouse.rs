@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{EffectStatus, StockAllocation, StockLine};
+
+// priority 越小越优先发货，停用的仓库不参与分配
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Warehouse {
+    pub id: i64,
+    pub code: String,
+    pub name: String,
+    pub address: String,
+    pub priority: i32,
+    pub status: EffectStatus,
+    pub description: String,
+    pub create_time: DateTime<Utc>,
+    pub create_by: String,
+    pub update_time: DateTime<Utc>,
+    pub update_by: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AllocationStrategy {
+    // 按仓库优先级依次分配
+    #[default]
+    Priority,
+    // 使用尽可能少的仓库发货
+    FewestWarehouses,
+}
+
+// 某个仓库中某个 SKU 的可售数量
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WarehouseStock {
+    pub warehouse_id: i64,
+    pub priority: i32,
+    pub sku_id: i64,
+    pub available: i64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "transfer_status", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+pub enum TransferStatus {
+    Pending,
+    Completed,
+    Cancelled,
+}
+
+// 调拨单创建时预留调出仓库存，完成时同时写入调出和调入流水
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StockTransfer {
+    pub id: i64,
+    pub from_warehouse_id: i64,
+    pub to_warehouse_id: i64,
+    pub status: TransferStatus,
+    pub remark: String,
+    pub create_time: DateTime<Utc>,
+    pub create_by: String,
+    pub update_time: DateTime<Utc>,
+    pub update_by: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StockTransferDetail {
+    #[serde(flatten)]
+    pub transfer: StockTransfer,
+    pub items: Vec<StockLine>,
+}
+
+impl StockTransfer {
+    // 调拨单在流水中的业务单号
+    pub fn reference(&self) -> String {
+        format!("TR-{}", self.id)
+    }
+}
+
+// 根据各仓库的可售数量计算分配方案，库存不足时返回缺货的 sku_id
+pub fn plan_allocation(
+    strategy: AllocationStrategy,
+    stock: &[WarehouseStock],
+    lines: &[StockLine],
+) -> Result<Vec<StockAllocation>, i64> {
+    let mut demand: BTreeMap<i64, i64> = BTreeMap::new();
+    for line in lines {
+        *demand.entry(line.sku_id).or_default() += line.quantity;
+    }
+    let mut available: HashMap<(i64, i64), i64> = HashMap::new();
+    let mut warehouses: Vec<(i32, i64)> = Vec::new();
+    for s in stock {
+        *available.entry((s.warehouse_id, s.sku_id)).or_default() += s.available.max(0);
+        warehouses.push((s.priority, s.warehouse_id));
+    }
+    warehouses.sort();
+    warehouses.dedup();
+    let mut order: Vec<i64> = warehouses.into_iter().map(|(_, id)| id).collect();
+    if strategy == AllocationStrategy::FewestWarehouses {
+        order = choose_fewest_warehouses(&order, &available, &demand);
+    }
+
+    let mut allocations = Vec::new();
+    for (sku_id, quantity) in demand {
+        let mut remaining = quantity;
+        for warehouse_id in &order {
+            if remaining == 0 {
+                break;
+            }
+            let take = available
+                .get(&(*warehouse_id, sku_id))
+                .copied()
+                .unwrap_or_default()
+                .min(remaining);
+            if take > 0 {
+                allocations.push(StockAllocation::new(*warehouse_id, sku_id, take));
+                remaining -= take;
+            }
+        }
+        if remaining > 0 {
+            return Err(sku_id);
+        }
+    }
+    Ok(allocations)
+}
+
+// 贪心地每次选择能满足最多剩余数量的仓库，相同时按优先级
+fn choose_fewest_warehouses(
+    order: &[i64],
+    available: &HashMap<(i64, i64), i64>,
+    demand: &BTreeMap<i64, i64>,
+) -> Vec<i64> {
+    let mut remaining = demand.clone();
+    let mut chosen: Vec<i64> = Vec::new();
+    loop {
+        let covered = |warehouse_id: i64| -> i64 {
+            remaining
+                .iter()
+                .map(|(sku_id, quantity)| {
+                    let stock = available.get(&(warehouse_id, *sku_id)).copied();
+                    stock.unwrap_or_default().min(*quantity)
+                })
+                .sum()
+        };
+        let mut best: Option<(i64, i64)> = None;
+        for warehouse_id in order.iter().filter(|w| !chosen.contains(w)) {
+            let value = covered(*warehouse_id);
+            if value > best.map(|(_, v)| v).unwrap_or_default() {
+                best = Some((*warehouse_id, value));
+            }
+        }
+        let Some((warehouse_id, _)) = best else {
+            break;
+        };
+        for (sku_id, quantity) in remaining.iter_mut() {
+            let stock = available.get(&(warehouse_id, *sku_id)).copied();
+            *quantity -= stock.unwrap_or_default().min(*quantity);
+        }
+        chosen.push(warehouse_id);
+    }
+    chosen
+}
+
+#[cfg(test)]
+mod test_warehouse {
+    use super::*;
+
+    fn stock(warehouse_id: i64, priority: i32, sku_id: i64, available: i64) -> WarehouseStock {
+        WarehouseStock {
+            warehouse_id,
+            priority,
+            sku_id,
+            available,
+        }
+    }
+
+    #[test]
+    fn test_plan_allocation_by_priority() {
+        let stock = vec![stock(1, 2, 10, 5), stock(2, 1, 10, 3), stock(1, 2, 11, 1)];
+        let lines = vec![StockLine::new(10, 4), StockLine::new(11, 1)];
+        let plan = plan_allocation(AllocationStrategy::Priority, &stock, &lines).unwrap();
+        assert_eq!(
+            plan,
+            vec![
+                StockAllocation::new(2, 10, 3),
+                StockAllocation::new(1, 10, 1),
+                StockAllocation::new(1, 11, 1),
+            ]
+        );
+        let lines = vec![StockLine::new(11, 2)];
+        assert_eq!(
+            plan_allocation(AllocationStrategy::Priority, &stock, &lines),
+            Err(11)
+        );
+    }
+
+    #[test]
+    fn test_plan_allocation_with_fewest_warehouses() {
+        // 优先级最高的仓库只有部分商品，仓库 3 可以单独发货
+        let stock = vec![
+            stock(1, 1, 10, 2),
+            stock(2, 2, 11, 2),
+            stock(3, 3, 10, 2),
+            stock(3, 3, 11, 2),
+        ];
+        let lines = vec![StockLine::new(10, 2), StockLine::new(11, 2)];
+        let plan = plan_allocation(AllocationStrategy::FewestWarehouses, &stock, &lines).unwrap();
+        assert_eq!(
+            plan,
+            vec![
+                StockAllocation::new(3, 10, 2),
+                StockAllocation::new(3, 11, 2)
+            ]
+        );
+        let plan = plan_allocation(AllocationStrategy::Priority, &stock, &lines).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].warehouse_id, 1);
+        assert_eq!(plan[1].warehouse_id, 2);
+    }
+}
@@ -1,13 +1,17 @@
 use jwt_simple::prelude::*;
 
-use crate::User;
-
 type JwtError = jwt_simple::Error;
 
-const JWT_DURATION: u64 = 7 * 24 * 60 * 60;
 const JWT_ISSUER: &str = "cmall_server";
 const JWT_AUDIENCE: &str = "cmall_frontend";
 
+// token 只携带用户 id 和会话 id，用户信息在校验时实时加载
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenClaims {
+    pub user_id: i64,
+    pub session_id: String,
+}
+
 #[derive(Clone)]
 pub struct EncodingKeyPair(Ed25519KeyPair);
 #[derive(Debug, Clone)]
@@ -19,9 +23,18 @@ impl EncodingKeyPair {
         Ok(Self(key_pair))
     }
 
-    pub fn sign(&self, user: impl Into<User>) -> Result<String, JwtError> {
-        let claims = Claims::with_custom_claims(user.into(), Duration::from_secs(JWT_DURATION));
-        let claims = claims.with_issuer(JWT_ISSUER).with_audience(JWT_AUDIENCE);
+    pub fn sign(
+        &self,
+        user_id: i64,
+        session_id: &str,
+        expires_in: u64,
+    ) -> Result<String, JwtError> {
+        let claims = Claims::create(Duration::from_secs(expires_in));
+        let claims = claims
+            .with_subject(user_id)
+            .with_issuer(JWT_ISSUER)
+            .with_audience(JWT_AUDIENCE)
+            .with_jwt_id(session_id);
         self.0.sign(claims)
     }
 }
@@ -32,20 +45,33 @@ impl DecodingKeyPair {
         Ok(Self(key_pair))
     }
 
-    pub fn verify(&self, token: &str) -> Result<User, JwtError> {
+    pub fn verify(&self, token: &str) -> Result<TokenClaims, JwtError> {
         let options = VerificationOptions {
             allowed_issuers: Some(HashSet::from_strings(&[JWT_ISSUER])),
             allowed_audiences: Some(HashSet::from_strings(&[JWT_AUDIENCE])),
             ..Default::default()
         };
-        let claims = self.0.verify_token::<User>(token, Some(options))?;
-        Ok(claims.custom)
+        let claims = self
+            .0
+            .verify_token::<NoCustomClaims>(token, Some(options))?;
+        let user_id = claims
+            .subject
+            .and_then(|sub| sub.parse().ok())
+            .ok_or_else(|| JwtError::msg("missing or invalid subject"))?;
+        let session_id = claims
+            .jwt_id
+            .ok_or_else(|| JwtError::msg("missing session id"))?;
+        Ok(TokenClaims {
+            user_id,
+            session_id,
+        })
     }
 }
 
 #[cfg(test)]
 mod test_jwt {
     use super::*;
+    use crate::User;
     use anyhow::Result;
 
     #[tokio::test]
@@ -58,12 +84,13 @@ mod test_jwt {
 
         let user = User::new(1, "Eli Shi", "elixy@qq.com", "138");
 
-        let token = encoding_key_pair.sign(user.clone()).unwrap();
+        let token = encoding_key_pair.sign(user.id, "1", 60).unwrap();
         // assert_eq!(token, "eyJhbGciOiJFUzI1NiIsInR5cCI6IkpXVCJ9.eyJpZCI6MSwibmFtZSI6IkVsaSBTaGkiLCJlbWFpbCI6ImVsaXh5QHFxLmNvbSIsInBob25lIjoiMTM4IiwiaXN");
 
-        let decoded_user = decoding_key_pair.verify(&token)?;
+        let claims = decoding_key_pair.verify(&token)?;
 
-        assert_eq!(decoded_user.username, user.username);
+        assert_eq!(claims.user_id, user.id);
+        assert_eq!(claims.session_id, "1");
         Ok(())
     }
 }
@@ -1,2 +1,4 @@
 mod jwt;
+mod tree;
 pub use jwt::*;
+pub(crate) use tree::*;
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+// 根据 parent_id 组装树，父节点不存在的记录作为根节点，同级按 (order_num, id) 排序
+pub(crate) fn build_tree<T, N>(
+    items: Vec<T>,
+    id: impl Fn(&T) -> i64 + Copy,
+    parent_id: impl Fn(&T) -> Option<i64>,
+    order_num: impl Fn(&T) -> i32 + Copy,
+    node: impl Fn(T, Vec<N>) -> N + Copy,
+) -> Vec<N> {
+    let ids: Vec<i64> = items.iter().map(id).collect();
+    let mut children: HashMap<Option<i64>, Vec<T>> = HashMap::new();
+    for item in items {
+        let parent = parent_id(&item).filter(|p| ids.contains(p));
+        children.entry(parent).or_default().push(item);
+    }
+    attach(None, &mut children, id, order_num, node)
+}
+
+fn attach<T, N>(
+    parent: Option<i64>,
+    children: &mut HashMap<Option<i64>, Vec<T>>,
+    id: impl Fn(&T) -> i64 + Copy,
+    order_num: impl Fn(&T) -> i32 + Copy,
+    node: impl Fn(T, Vec<N>) -> N + Copy,
+) -> Vec<N> {
+    let mut items = children.remove(&parent).unwrap_or_default();
+    items.sort_by_key(|item| (order_num(item), id(item)));
+    items
+        .into_iter()
+        .map(|item| {
+            let nodes = attach(Some(id(&item)), children, id, order_num, node);
+            node(item, nodes)
+        })
+        .collect()
+}
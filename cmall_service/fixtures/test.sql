@@ -6,10 +6,32 @@
 -- ('hr', 0),
 -- ('dev', 0);
 
+-- insert 3 departments
+INSERT INTO departments(parent_id, identifier, name, order_num, status, description, create_by, update_by)
+  VALUES (NULL, 'headquarters', 'Headquarters', 0, 'enable', 'root department', 'system', 'system'),
+(1, 'develop', 'Develop', 1, 'enable', 'develop department', 'system', 'system'),
+(1, 'operation', 'Operation', 2, 'enable', 'operation department', 'system', 'system');
+
+-- insert 3 roles
+INSERT INTO roles(code, name, status, description, create_by, update_by)
+  VALUES ('admin', 'Administrator', 'enable', 'administrator', 'system', 'system'),
+('operator', 'Operator', 'enable', 'shop operator', 'system', 'system'),
+('viewer', 'Viewer', 'disable', 'read only', 'system', 'system');
+
+-- grant all seeded menus to admin, user menus to operator
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT 1, id FROM menus;
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT 2, id FROM menus WHERE menu_id IN ('system', 'user', 'user:query', 'user:create');
+
 -- insert 5 users, all with hashed password '123456'
-INSERT INTO users(dept_id, email, username, password_hash, phone, status, avatar, roles)
-  VALUES (1, 'elixy@qq.com', 'Eli Shi', '$argon2id$v=19$m=19456,t=2,p=1$MxGhY+ib/kplwBPLa7u2ug$c5h9u7Sc8Px8J5+qgNdOjSY7ZJO2QN4rugKpapGW4XU','123123','active','default','{1}'),
-(1, 'alice@acme.org', 'Alice Shi', '$argon2id$v=19$m=19456,t=2,p=1$MxGhY+ib/kplwBPLa7u2ug$c5h9u7Sc8Px8J5+qgNdOjSY7ZJO2QN4rugKpapGW4XU','234234','off','def','{1,2,3}');
+INSERT INTO users(dept_id, email, username, password_hash, phone, status, avatar)
+  VALUES (1, 'elixy@qq.com', 'Eli Shi', '$argon2id$v=19$m=19456,t=2,p=1$MxGhY+ib/kplwBPLa7u2ug$c5h9u7Sc8Px8J5+qgNdOjSY7ZJO2QN4rugKpapGW4XU','123123','active','default'),
+(1, 'alice@acme.org', 'Alice Shi', '$argon2id$v=19$m=19456,t=2,p=1$MxGhY+ib/kplwBPLa7u2ug$c5h9u7Sc8Px8J5+qgNdOjSY7ZJO2QN4rugKpapGW4XU','234234','off','def');
+
+-- assign roles to users
+INSERT INTO user_roles(user_id, role_id)
+  VALUES (1, 1), (2, 1), (2, 2), (2, 3);
 
 
 -- -- insert 4 chats
@@ -0,0 +1,80 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+// 进程内的简单 TTL 缓存，超过容量时先清理过期项，仍然不足则整体清空
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: RwLock<HashMap<K, (V, Instant)>>,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .filter(|(_, created)| created.elapsed() < self.ttl)
+            .map(|(value, _)| value.clone())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.capacity {
+            let ttl = self.ttl;
+            entries.retain(|_, (_, created)| created.elapsed() < ttl);
+            if entries.len() >= self.capacity {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (value, Instant::now()));
+    }
+
+    pub fn remove(&self, key: &K) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod test_cache {
+    use super::*;
+
+    #[test]
+    fn test_ttl_cache_should_expire() {
+        let cache = TtlCache::new(Duration::from_millis(20), 2);
+        cache.insert(1, "a");
+        assert_eq!(cache.get(&1), Some("a"));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn test_ttl_cache_should_respect_capacity() {
+        let cache = TtlCache::new(Duration::from_secs(60), 2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&3), Some("c"));
+        assert_eq!(cache.get(&1), None);
+
+        cache.remove(&3);
+        assert_eq!(cache.get(&3), None);
+    }
+}
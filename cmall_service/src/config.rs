@@ -1,12 +1,27 @@
 use anyhow::{bail, Result};
 use std::{fs::File, path::PathBuf};
 
+use cmall_core::AllocationStrategy;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub auth: AuthConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+    #[serde(default)]
+    pub recycle: RecycleConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+    #[serde(default)]
+    pub inventory: InventoryConfig,
+    #[serde(default)]
+    pub cart: CartConfig,
+    #[serde(default)]
+    pub order: OrderConfig,
+    // 没有默认值，部署时必须显式配置支付渠道
+    pub payment: PaymentConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,8 +38,172 @@ pub struct AuthConfig {
     pub secret_key: String,
     // 解密
     pub public_key: String,
-    // 有效期
+    // access token 有效期（秒）
     pub expires_in: u64,
+    // refresh token 有效期（秒）
+    #[serde(default = "default_refresh_expires_in")]
+    pub refresh_expires_in: u64,
+    // 重置密码 token 有效期（秒）
+    #[serde(default = "default_reset_expires_in")]
+    pub reset_expires_in: u64,
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockoutConfig {
+    // 同一账号连续失败次数达到该值后锁定
+    pub max_attempts: i32,
+    // 同一客户端地址在统计窗口内的最大失败次数
+    pub ip_max_attempts: i64,
+    // 客户端地址失败次数的统计窗口（秒）
+    pub window: u64,
+    // 账号锁定时长（秒）
+    pub lock_duration: u64,
+    // 部署在反向代理之后时，从 X-Forwarded-For 读取客户端地址
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailConfig {
+    // 发件人，例如 Cmall <noreply@cmall.com>
+    pub from: String,
+    // 前端重置密码页面地址，token 作为查询参数附加
+    pub reset_url: String,
+    pub sender: MailSenderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MailSenderConfig {
+    Smtp {
+        host: String,
+        port: u16,
+        username: String,
+        password: String,
+        // false 时使用 SMTPS（465），true 时使用 STARTTLS（587）
+        #[serde(default)]
+        starttls: bool,
+    },
+    // 本地开发和测试使用，邮件写入目录并打印日志
+    File {
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecycleConfig {
+    // 软删除数据的保留时长（秒），超过后才能被彻底清理
+    pub retention: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportConfig {
+    // 后台导出任务及其文件的保留时长（秒）
+    pub retention: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InventoryConfig {
+    // 下单时从各仓库分配库存的策略：priority 或 fewest-warehouses
+    #[serde(default)]
+    pub allocation: AllocationStrategy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CartConfig {
+    // 游客购物车的有效期（秒），超过该时长未修改的购物车会被清理
+    pub guest_expires_in: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderConfig {
+    // 下单后的付款时限（秒），超时未付款的订单自动关闭并释放预留库存
+    pub payment_timeout: u64,
+    // 检查超时订单的间隔（秒）
+    pub close_interval: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentConfig {
+    pub gateway: PaymentGatewayConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PaymentGatewayConfig {
+    // 只能在 debug 构建的本地开发和测试中使用，不调用外部服务。
+    // 回调使用 secret 签名，未配置时每次启动随机生成，只能通过 simulate_callback 模拟回调
+    Mock {
+        #[serde(default)]
+        secret: Option<String>,
+    },
+}
+
+fn default_refresh_expires_in() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_reset_expires_in() -> u64 {
+    30 * 60
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            ip_max_attempts: 20,
+            window: 15 * 60,
+            lock_duration: 15 * 60,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl Default for RecycleConfig {
+    fn default() -> Self {
+        Self {
+            retention: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl Default for CartConfig {
+    fn default() -> Self {
+        Self {
+            guest_expires_in: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl Default for OrderConfig {
+    fn default() -> Self {
+        Self {
+            payment_timeout: 30 * 60,
+            close_interval: 60,
+        }
+    }
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            retention: 24 * 60 * 60,
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            from: "Cmall <noreply@localhost>".to_string(),
+            reset_url: "http://localhost:5173/reset-password".to_string(),
+            sender: MailSenderConfig::File {
+                dir: std::env::temp_dir().join("cmall_mail"),
+            },
+        }
+    }
 }
 
 impl AppConfig {
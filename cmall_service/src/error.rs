@@ -1,24 +1,14 @@
 use axum::{
-    http::StatusCode,
+    extract::multipart::MultipartError,
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
     response::{IntoResponse, Json, Response},
 };
 use rust_xlsxwriter::XlsxError;
 use thiserror::Error;
 
-use serde::{Deserialize, Serialize};
+use cmall_core::OrderStatus;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorOutput {
-    pub message: String,
-}
-
-impl ErrorOutput {
-    pub fn new(error: impl Into<String>) -> Self {
-        Self {
-            message: error.into(),
-        }
-    }
-}
+pub use cmall_core::ErrorOutput;
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -35,21 +25,163 @@ pub enum AppError {
     #[error("http header parse error: {0}")]
     HttpHeaderError(#[from] axum::http::header::InvalidHeaderValue),
 
+    // auth error
+    #[error("invalid or expired refresh token")]
+    InvalidRefreshToken,
+
+    #[error("session revoked or expired: {0}")]
+    SessionRevoked(String),
+
+    #[error("too many failed sign-in attempts, retry after {0} seconds")]
+    TooManyAttempts(u64),
+
+    #[error("invalid mfa code")]
+    InvalidMfaCode,
+
+    #[error("invalid or expired mfa token")]
+    InvalidMfaToken,
+
+    #[error("user {0} must enable two-factor authentication")]
+    MfaRequired(i64),
+
+    #[error("user {0} has not set up two-factor authentication")]
+    MfaNotEnrolled(i64),
+
+    #[error("user {0} has already enabled two-factor authentication")]
+    MfaAlreadyEnabled(i64),
+
+    #[error("totp error: {0}")]
+    TotpError(String),
+
+    #[error("invalid or expired password reset token")]
+    InvalidResetToken,
+
+    #[error("mail error: {0}")]
+    MailError(String),
+
     // user error
     #[error("user alredy existed: {0}")]
     UserAlreadyExisted(String),
 
+    #[error("user is disabled: {0}")]
+    UserDisabled(i64),
+
+    #[error("invalid password: {0}")]
+    InvalidPassword(String),
+
+    #[error("user {0} must change password before continuing")]
+    PasswordChangeRequired(i64),
+
     #[error("password hash error: {0}")]
     PasswordHashError(#[from] argon2::password_hash::Error),
 
-    #[error("export user error: {0}")]
-    ExportUserError(#[from] XlsxError),
+    #[error("xlsx error: {0}")]
+    XlsxError(#[from] XlsxError),
+
+    #[error("csv error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("import user error: {0}")]
+    ImportError(String),
+
+    #[error("multipart error: {0}")]
+    MultipartError(#[from] MultipartError),
 
     // role error
     #[error("role already existed: {0}")]
     RoleAlreadyExisted(String),
 
+    #[error("role {0} is still assigned to users")]
+    RoleHasUsers(i64),
+
+    // department error
+    #[error("department already existed: {0}")]
+    DepartmentAlreadyExisted(String),
+
+    #[error("department {0} still has users")]
+    DepartmentHasUsers(i64),
+
+    #[error("department {0} still has sub departments")]
+    DepartmentHasChildren(i64),
+
+    #[error("invalid parent department: {0}")]
+    InvalidDepartmentParent(i64),
+
+    // menu error
+    #[error("menu already existed: {0}")]
+    MenuAlreadyExisted(String),
+
+    #[error("menu {0} still has sub menus")]
+    MenuHasChildren(String),
+
+    #[error("invalid parent menu: {0}")]
+    InvalidMenuParent(String),
+
+    // category error
+    #[error("category {0} still has sub categories")]
+    CategoryHasChildren(i64),
+
+    #[error("category {0} still has products")]
+    CategoryHasProducts(i64),
+
+    #[error("invalid parent category: {0}")]
+    InvalidCategoryParent(i64),
+
+    // product error
+    #[error("invalid product: {0}")]
+    InvalidProduct(String),
+
+    #[error("sku already existed: {0}")]
+    SkuAlreadyExisted(String),
+
+    #[error("sku {0} still has stock")]
+    SkuHasStock(i64),
+
+    #[error("sku {0} has transfer or order history")]
+    SkuInUse(i64),
+
+    // inventory error
+    #[error("insufficient stock for sku {0}")]
+    InsufficientStock(i64),
+
+    #[error("invalid stock change: {0}")]
+    InvalidStock(String),
+
+    // warehouse error
+    #[error("warehouse already existed: {0}")]
+    WarehouseAlreadyExisted(String),
+
+    #[error("warehouse {0} still has stock, transfers or orders")]
+    WarehouseInUse(i64),
+
+    #[error("invalid transfer: {0}")]
+    InvalidTransfer(String),
+
+    // cart error
+    #[error("invalid cart: {0}")]
+    InvalidCart(String),
+
+    // order error
+    #[error("cannot change order status from {0} to {1}")]
+    InvalidOrderTransition(OrderStatus, OrderStatus),
+
+    // payment error
+    #[error("invalid payment signature")]
+    InvalidPaymentSignature,
+
+    #[error("invalid payment: {0}")]
+    InvalidPayment(String),
+
+    #[error("payment gateway error: {0}")]
+    PaymentError(String),
+
     // common error
+    #[error("invalid pagination: {0}")]
+    InvalidPagination(String),
+
+    #[error("export has {0} rows, at most {1} rows can be exported")]
+    ExportTooLarge(i64, i64),
+
     #[error("general error: {0}")]
     AnyError(#[from] anyhow::Error),
 }
@@ -62,13 +194,73 @@ impl IntoResponse for AppError {
             Self::NotFound(_) => StatusCode::NOT_FOUND,
             Self::AnyError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::HttpHeaderError(_) => StatusCode::BAD_REQUEST,
+            // auth error
+            Self::InvalidRefreshToken => StatusCode::UNAUTHORIZED,
+            Self::SessionRevoked(_) => StatusCode::UNAUTHORIZED,
+            Self::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::InvalidMfaCode => StatusCode::UNAUTHORIZED,
+            Self::InvalidMfaToken => StatusCode::UNAUTHORIZED,
+            Self::MfaRequired(_) => StatusCode::FORBIDDEN,
+            Self::MfaNotEnrolled(_) => StatusCode::BAD_REQUEST,
+            Self::MfaAlreadyEnabled(_) => StatusCode::CONFLICT,
+            Self::TotpError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidResetToken => StatusCode::BAD_REQUEST,
+            Self::MailError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             // user error
             Self::UserAlreadyExisted(_) => StatusCode::CONFLICT,
+            Self::UserDisabled(_) => StatusCode::FORBIDDEN,
+            Self::InvalidPassword(_) => StatusCode::BAD_REQUEST,
+            Self::PasswordChangeRequired(_) => StatusCode::FORBIDDEN,
             Self::PasswordHashError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            Self::ExportUserError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::XlsxError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::CsvError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::ImportError(_) => StatusCode::BAD_REQUEST,
+            Self::MultipartError(_) => StatusCode::BAD_REQUEST,
+            // role error
+            Self::RoleHasUsers(_) => StatusCode::CONFLICT,
+            // department error
+            Self::DepartmentAlreadyExisted(_) => StatusCode::CONFLICT,
+            Self::DepartmentHasUsers(_) => StatusCode::CONFLICT,
+            Self::DepartmentHasChildren(_) => StatusCode::CONFLICT,
+            Self::InvalidDepartmentParent(_) => StatusCode::BAD_REQUEST,
+            // menu error
+            Self::MenuAlreadyExisted(_) => StatusCode::CONFLICT,
+            Self::MenuHasChildren(_) => StatusCode::CONFLICT,
+            Self::InvalidMenuParent(_) => StatusCode::BAD_REQUEST,
+            // category error
+            Self::CategoryHasChildren(_) => StatusCode::CONFLICT,
+            Self::CategoryHasProducts(_) => StatusCode::CONFLICT,
+            Self::InvalidCategoryParent(_) => StatusCode::BAD_REQUEST,
+            // product error
+            Self::InvalidProduct(_) => StatusCode::BAD_REQUEST,
+            Self::SkuAlreadyExisted(_) => StatusCode::CONFLICT,
+            Self::SkuHasStock(_) => StatusCode::CONFLICT,
+            Self::SkuInUse(_) => StatusCode::CONFLICT,
+            // inventory error
+            Self::InsufficientStock(_) => StatusCode::CONFLICT,
+            Self::InvalidStock(_) => StatusCode::BAD_REQUEST,
+            // warehouse error
+            Self::WarehouseAlreadyExisted(_) => StatusCode::CONFLICT,
+            Self::WarehouseInUse(_) => StatusCode::CONFLICT,
+            Self::InvalidTransfer(_) => StatusCode::BAD_REQUEST,
+            // cart error
+            Self::InvalidCart(_) => StatusCode::BAD_REQUEST,
+            // order error
+            Self::InvalidOrderTransition(_, _) => StatusCode::CONFLICT,
+            // payment error
+            Self::InvalidPaymentSignature => StatusCode::UNAUTHORIZED,
+            Self::InvalidPayment(_) => StatusCode::BAD_REQUEST,
+            Self::PaymentError(_) => StatusCode::BAD_GATEWAY,
             // common error
+            Self::InvalidPagination(_) => StatusCode::BAD_REQUEST,
+            Self::ExportTooLarge(_, _) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
-        (status, Json(ErrorOutput::new(self.to_string()))).into_response()
+        let mut res = (status, Json(ErrorOutput::new(self.to_string()))).into_response();
+        if let Self::TooManyAttempts(secs) = &self {
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(*secs));
+        }
+        res
     }
 }
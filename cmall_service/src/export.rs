@@ -0,0 +1,388 @@
+use axum::http::{
+    header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    HeaderMap, HeaderValue,
+};
+use chrono::{DateTime, Utc};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::AppError;
+
+// 单次导出的最大行数
+pub const MAX_EXPORT_ROWS: i64 = 100_000;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// 带 BOM 的 CSV 才能被 Excel 正确识别为 UTF-8
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    #[default]
+    Xlsx,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportLocale {
+    #[default]
+    Zh,
+    En,
+}
+
+// 各资源的导出条件中都包含 format 和 locale 参数
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub locale: ExportLocale,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Empty,
+    Int(i64),
+    Bool(bool),
+    Text(String),
+    Time(DateTime<Utc>),
+    Json(Value),
+}
+
+pub struct ExportColumn<T> {
+    // JSON Lines 中的字段名
+    pub key: &'static str,
+    pub zh: &'static str,
+    pub en: &'static str,
+    // XLSX 列宽，为空时使用默认宽度
+    pub width: Option<f64>,
+    pub value: fn(&T) -> ExportValue,
+}
+
+// 可导出的资源声明导出的列、表头和取值方式
+pub trait Exportable: Sized {
+    // 导出文件名前缀
+    const NAME: &'static str;
+
+    fn columns() -> Vec<ExportColumn<Self>>;
+}
+
+impl ExportOptions {
+    pub fn new(format: ExportFormat, locale: ExportLocale) -> Self {
+        Self { format, locale }
+    }
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, ext) = file_name.rsplit_once('.')?;
+        match ext.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "xlsx" => Some(Self::Xlsx),
+            "jsonl" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+
+    // 例如 users-20250121093000.xlsx
+    pub fn file_name(&self, prefix: &str) -> String {
+        format!(
+            "{}-{}.{}",
+            prefix,
+            Utc::now().format("%Y%m%d%H%M%S"),
+            self.extension()
+        )
+    }
+}
+
+impl<T> ExportColumn<T> {
+    pub fn new(
+        key: &'static str,
+        zh: &'static str,
+        en: &'static str,
+        value: fn(&T) -> ExportValue,
+    ) -> Self {
+        Self {
+            key,
+            zh,
+            en,
+            width: None,
+            value,
+        }
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    fn header(&self, locale: ExportLocale) -> &'static str {
+        match locale {
+            ExportLocale::Zh => self.zh,
+            ExportLocale::En => self.en,
+        }
+    }
+}
+
+impl ExportValue {
+    fn to_text(&self) -> String {
+        match self {
+            Self::Empty => String::new(),
+            Self::Int(v) => v.to_string(),
+            Self::Bool(v) => v.to_string(),
+            Self::Text(v) => v.clone(),
+            Self::Time(v) => v.format(TIME_FORMAT).to_string(),
+            Self::Json(v) => v.to_string(),
+        }
+    }
+
+    fn into_json(self) -> Value {
+        match self {
+            Self::Empty => Value::Null,
+            Self::Int(v) => v.into(),
+            Self::Bool(v) => v.into(),
+            Self::Text(v) => v.into(),
+            Self::Time(v) => v.to_rfc3339().into(),
+            Self::Json(v) => v,
+        }
+    }
+}
+
+impl From<i64> for ExportValue {
+    fn from(v: i64) -> Self {
+        Self::Int(v)
+    }
+}
+
+impl From<i32> for ExportValue {
+    fn from(v: i32) -> Self {
+        Self::Int(v.into())
+    }
+}
+
+impl From<bool> for ExportValue {
+    fn from(v: bool) -> Self {
+        Self::Bool(v)
+    }
+}
+
+impl From<String> for ExportValue {
+    fn from(v: String) -> Self {
+        Self::Text(v)
+    }
+}
+
+impl From<&str> for ExportValue {
+    fn from(v: &str) -> Self {
+        Self::Text(v.to_string())
+    }
+}
+
+impl From<DateTime<Utc>> for ExportValue {
+    fn from(v: DateTime<Utc>) -> Self {
+        Self::Time(v)
+    }
+}
+
+impl From<Value> for ExportValue {
+    fn from(v: Value) -> Self {
+        Self::Json(v)
+    }
+}
+
+impl<T: Into<ExportValue>> From<Option<T>> for ExportValue {
+    fn from(v: Option<T>) -> Self {
+        v.map(Into::into).unwrap_or(Self::Empty)
+    }
+}
+
+pub fn export_to_bytes<T: Exportable>(
+    items: &[T],
+    options: ExportOptions,
+) -> Result<Vec<u8>, AppError> {
+    let columns = T::columns();
+    match options.format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(UTF8_BOM.to_vec());
+            writer.write_record(columns.iter().map(|c| c.header(options.locale)))?;
+            for item in items {
+                writer.write_record(columns.iter().map(|c| (c.value)(item).to_text()))?;
+            }
+            writer
+                .into_inner()
+                .map_err(|e| AppError::AnyError(anyhow::anyhow!(e.to_string())))
+        }
+        ExportFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            let worksheet = workbook.add_worksheet();
+            let bold = Format::new().set_bold();
+            for (col, column) in (0..).zip(columns.iter()) {
+                worksheet.write_with_format(0, col, column.header(options.locale), &bold)?;
+                if let Some(width) = column.width {
+                    worksheet.set_column_width(col, width)?;
+                }
+            }
+            for (row, item) in (1..).zip(items.iter()) {
+                for (col, column) in (0..).zip(columns.iter()) {
+                    match (column.value)(item) {
+                        ExportValue::Empty => {}
+                        ExportValue::Int(v) => {
+                            worksheet.write(row, col, v)?;
+                        }
+                        ExportValue::Bool(v) => {
+                            worksheet.write(row, col, v)?;
+                        }
+                        value => {
+                            worksheet.write(row, col, value.to_text())?;
+                        }
+                    }
+                }
+            }
+            Ok(workbook.save_to_buffer()?)
+        }
+        ExportFormat::Jsonl => {
+            let mut data = Vec::new();
+            for item in items {
+                let line: Map<String, Value> = columns
+                    .iter()
+                    .map(|c| (c.key.to_string(), (c.value)(item).into_json()))
+                    .collect();
+                serde_json::to_writer(&mut data, &line)
+                    .map_err(|e| AppError::AnyError(e.into()))?;
+                data.push(b'\n');
+            }
+            Ok(data)
+        }
+    }
+}
+
+// 导出文件作为附件下载
+pub fn attachment_headers(file_name: &str) -> Result<HeaderMap, AppError> {
+    let content_type = ExportFormat::from_file_name(file_name)
+        .map(|f| f.content_type())
+        .unwrap_or("application/octet-stream");
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(
+        CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", file_name).parse()?,
+    );
+    Ok(headers)
+}
+
+// 符合条件的行数超过上限时拒绝导出，而不是返回被截断的文件
+pub fn check_export_total(total: i64, limit: i64) -> Result<(), AppError> {
+    if total > limit {
+        return Err(AppError::ExportTooLarge(total, limit));
+    }
+    Ok(())
+}
+
+pub fn export_response<T: Exportable>(
+    items: &[T],
+    options: ExportOptions,
+) -> Result<(HeaderMap, Vec<u8>), AppError> {
+    let data = export_to_bytes(items, options)?;
+    let headers = attachment_headers(&options.format.file_name(T::NAME))?;
+    Ok((headers, data))
+}
+
+#[cfg(test)]
+mod test_export {
+    use super::*;
+    use anyhow::Result;
+
+    struct Item {
+        id: i64,
+        name: String,
+        note: Option<String>,
+    }
+
+    impl Exportable for Item {
+        const NAME: &'static str = "items";
+
+        fn columns() -> Vec<ExportColumn<Self>> {
+            vec![
+                ExportColumn::new("id", "编号", "ID", |i: &Item| i.id.into()),
+                ExportColumn::new("name", "名称", "Name", |i: &Item| i.name.as_str().into())
+                    .width(20.0),
+                ExportColumn::new("note", "备注", "Note", |i: &Item| i.note.clone().into()),
+            ]
+        }
+    }
+
+    fn items() -> Vec<Item> {
+        vec![
+            Item {
+                id: 1,
+                name: "a,b".to_string(),
+                note: None,
+            },
+            Item {
+                id: 2,
+                name: "c".to_string(),
+                note: Some("n".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_export_csv_should_use_locale_headers() -> Result<()> {
+        let options = ExportOptions::new(ExportFormat::Csv, ExportLocale::En);
+        let data = export_to_bytes(&items(), options)?;
+        assert_eq!(&data[..3], UTF8_BOM);
+        let text = String::from_utf8(data[3..].to_vec())?;
+        assert_eq!(text, "ID,Name,Note\n1,\"a,b\",\n2,c,n\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_jsonl_should_use_keys() -> Result<()> {
+        let options = ExportOptions::new(ExportFormat::Jsonl, ExportLocale::Zh);
+        let data = export_to_bytes(&items(), options)?;
+        let lines: Vec<Value> = String::from_utf8(data)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["name"], "a,b");
+        assert_eq!(lines[0]["note"], Value::Null);
+        assert_eq!(lines[1]["id"], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_response_should_set_headers() -> Result<()> {
+        let (headers, data) = export_response(&items(), ExportOptions::default())?;
+        assert_eq!(&data[..2], b"PK");
+        assert_eq!(headers[CONTENT_TYPE], ExportFormat::Xlsx.content_type());
+        let disposition = headers[CONTENT_DISPOSITION].to_str()?;
+        assert!(disposition.starts_with("attachment; filename=\"items-"));
+        assert!(disposition.ends_with(".xlsx\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_export_total_should_reject_over_limit() {
+        assert!(check_export_total(10, 10).is_ok());
+        let ret = check_export_total(11, 10);
+        assert!(matches!(ret, Err(AppError::ExportTooLarge(11, 10))));
+    }
+}
@@ -0,0 +1,39 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use tracing::info;
+
+use crate::{
+    error::AppError, export_response, AppState, ExportAudit, ExportOptions, Keyset, Page,
+    RecordOutput, SearchAudit,
+};
+
+pub async fn list_audit_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchAudit>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_audit_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    if input.cursor.is_some() {
+        let (logs, info) = state
+            .find_audit_logs_after(&input, input.cursor, page.page_size)
+            .await?;
+        return Ok(Json(RecordOutput::with_page(logs, None, info)));
+    }
+    let (logs, total_count) = state.find_audit_log_by_condition(&input, page).await?;
+    // 按页码查询时也返回游标，之后可以改用游标翻页
+    let mut info = page.info(total_count);
+    info.next_cursor = logs.last().filter(|_| info.has_more).map(Keyset::key);
+    Ok(Json(RecordOutput::with_page(logs, Some(total_count), info)))
+}
+
+pub async fn export_audit_handler(
+    State(state): State<AppState>,
+    Query(input): Query<ExportAudit>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("export_audit_handler {:?}", input);
+    let logs = state.find_audit_logs_for_export(&input).await?;
+    export_response(&logs, ExportOptions::new(input.format, input.locale))
+}
@@ -1,42 +1,141 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use cmall_core::User;
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::{SessionId, User};
 use serde::{Deserialize, Serialize};
 
+use super::cart::cart_token;
 use crate::{
-    error::{ErrorOutput, AppError},
-    AppState, CreateUser, LoginUser,
+    error::{AppError, ErrorOutput},
+    AppState, AuthTokens, ConfirmPasswordReset, ForgotPassword, LoginUser, MfaSignin, RefreshToken,
+    SignupUser,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AuthOutput {
-    token: String,
+    #[serde(flatten)]
+    tokens: AuthTokens,
     user: User,
 }
 
 pub async fn signup_handler(
     State(state): State<AppState>,
-    Json(input): Json<CreateUser>,
+    headers: HeaderMap,
+    Json(input): Json<SignupUser>,
 ) -> Result<impl IntoResponse, AppError> {
-    let user = state.create_user(&input).await?;
-    let token = state.secret_key.sign(user.clone())?;
-    let body = Json(AuthOutput { token, user });
+    let user = state.signup_user(&input).await?;
+    if let Some(token) = cart_token(&headers) {
+        state.merge_guest_cart(&token, user.id).await?;
+    }
+    let tokens = state.create_session(&user).await?;
+    let body = Json(AuthOutput { tokens, user });
     Ok((StatusCode::CREATED, body))
 }
 
 pub async fn signin_handler(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(input): Json<LoginUser>,
 ) -> Result<impl IntoResponse, AppError> {
+    let ip = state.client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    state.check_signin_allowed(&input.email, &ip).await?;
     let user = state.verify_user(&input).await?;
 
     match user {
-        Some(user) => {
-            let token = state.secret_key.sign(user.clone())?;
-            Ok((StatusCode::OK, Json(AuthOutput { token, user })).into_response())
+        Some(user) if !user.status.can_sign_in() => Err(AppError::UserDisabled(user.id)),
+        // 开启两步验证的用户需要再提交验证码，失败次数在第二步完成后才清空
+        Some(user) if user.mfa_enabled => {
+            let challenge = state.create_mfa_challenge(user.id).await?;
+            Ok((StatusCode::OK, Json(challenge)).into_response())
+        }
+        Some(mut user) => {
+            state.reset_signin_failures(user.id).await?;
+            user.failed_attempts = 0;
+            user.locked_until = None;
+            if let Some(token) = cart_token(&headers) {
+                state.merge_guest_cart(&token, user.id).await?;
+            }
+            let tokens = state.create_session(&user).await?;
+            Ok((StatusCode::OK, Json(AuthOutput { tokens, user })).into_response())
         }
         None => {
+            if let Some(secs) = state.record_signin_failure(&input.email, &ip).await? {
+                return Err(AppError::TooManyAttempts(secs));
+            }
             let body = Json(ErrorOutput::new("Invalid Credentials"));
             Ok((StatusCode::FORBIDDEN, body).into_response())
         }
     }
 }
+
+pub async fn mfa_signin_handler(
+    State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(input): Json<MfaSignin>,
+) -> Result<impl IntoResponse, AppError> {
+    let ip = state.client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    let mut user = state.find_mfa_challenge_user(&input.mfa_token).await?;
+    state.check_signin_allowed(&user.email, &ip).await?;
+    if !user.status.can_sign_in() {
+        return Err(AppError::UserDisabled(user.id));
+    }
+    if !state.verify_mfa_code(user.id, &input.code).await? {
+        if let Some(secs) = state.record_signin_failure(&user.email, &ip).await? {
+            return Err(AppError::TooManyAttempts(secs));
+        }
+        return Err(AppError::InvalidMfaCode);
+    }
+    state.consume_mfa_challenge(&input.mfa_token).await?;
+    state.reset_signin_failures(user.id).await?;
+    user.failed_attempts = 0;
+    user.locked_until = None;
+    if let Some(token) = cart_token(&headers) {
+        state.merge_guest_cart(&token, user.id).await?;
+    }
+    let tokens = state.create_session(&user).await?;
+    Ok((StatusCode::OK, Json(AuthOutput { tokens, user })))
+}
+
+pub async fn refresh_handler(
+    State(state): State<AppState>,
+    Json(input): Json<RefreshToken>,
+) -> Result<impl IntoResponse, AppError> {
+    let (user, tokens) = state.refresh_session(&input.refresh_token).await?;
+    Ok((StatusCode::OK, Json(AuthOutput { tokens, user })))
+}
+
+pub async fn signout_handler(
+    Extension(session_id): Extension<SessionId>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let id: i64 = session_id
+        .0
+        .parse()
+        .map_err(|_| AppError::SessionRevoked(session_id.0.clone()))?;
+    state.revoke_session(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn forgot_password_handler(
+    State(state): State<AppState>,
+    Json(input): Json<ForgotPassword>,
+) -> Result<impl IntoResponse, AppError> {
+    state.request_password_reset(&input.email).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn confirm_password_reset_handler(
+    State(state): State<AppState>,
+    Json(input): Json<ConfirmPasswordReset>,
+) -> Result<impl IntoResponse, AppError> {
+    state.confirm_password_reset(&input).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,116 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::User;
+
+use crate::{error::AppError, AddCartItem, AppState, CartOwner, UpdateCartItem};
+
+// 游客购物车令牌通过该请求头传递，首次加购时由响应头返回
+pub const CART_TOKEN_HEADER: HeaderName = HeaderName::from_static("x-cart-token");
+
+pub(crate) fn cart_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CART_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+fn guest_owner(headers: &HeaderMap) -> Result<CartOwner, AppError> {
+    cart_token(headers)
+        .map(CartOwner::Guest)
+        .ok_or_else(|| AppError::NotFound("cart".to_string()))
+}
+
+pub async fn get_my_cart_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = state.find_cart(&CartOwner::User(user.id)).await?;
+    Ok(Json(cart))
+}
+
+pub async fn add_my_cart_item_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<AddCartItem>,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = state
+        .add_cart_item(&CartOwner::User(user.id), &input)
+        .await?;
+    Ok(Json(cart))
+}
+
+pub async fn update_my_cart_item_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+    Json(input): Json<UpdateCartItem>,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = state
+        .update_cart_item(&CartOwner::User(user.id), sku_id, &input)
+        .await?;
+    Ok(Json(cart))
+}
+
+pub async fn remove_my_cart_item_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = state
+        .remove_cart_item(&CartOwner::User(user.id), sku_id)
+        .await?;
+    Ok(Json(cart))
+}
+
+// 没有令牌时返回空购物车
+pub async fn get_guest_cart_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = match cart_token(&headers) {
+        Some(token) => state.find_cart(&CartOwner::Guest(token)).await?,
+        None => Default::default(),
+    };
+    Ok(Json(cart))
+}
+
+pub async fn add_guest_cart_item_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(input): Json<AddCartItem>,
+) -> Result<impl IntoResponse, AppError> {
+    let (token, cart) = state
+        .add_guest_cart_item(cart_token(&headers), &input)
+        .await?;
+    let mut res_headers = HeaderMap::new();
+    res_headers.insert(CART_TOKEN_HEADER, HeaderValue::from_str(&token)?);
+    Ok((res_headers, Json(cart)))
+}
+
+pub async fn update_guest_cart_item_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(sku_id): Path<i64>,
+    Json(input): Json<UpdateCartItem>,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = state
+        .update_cart_item(&guest_owner(&headers)?, sku_id, &input)
+        .await?;
+    Ok(Json(cart))
+}
+
+pub async fn remove_guest_cart_item_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(sku_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let cart = state
+        .remove_cart_item(&guest_owner(&headers)?, sku_id)
+        .await?;
+    Ok(Json(cart))
+}
@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use cmall_core::{EffectStatus, User};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::{
+    error::AppError, AppState, MoveCategory, OperateCategory, Page, RecordOutput, SearchCategory,
+};
+
+// 前台分类树的缓存时间
+const CATEGORY_TREE_MAX_AGE: &str = "public, max-age=300";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CategoryTreeQuery {
+    pub status: Option<EffectStatus>,
+}
+
+pub async fn create_category_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<OperateCategory>,
+) -> Result<impl IntoResponse, AppError> {
+    let category = state.create_category(&input, user.username).await?;
+    Ok((StatusCode::CREATED, Json(category)))
+}
+
+pub async fn list_category_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchCategory>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_category_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (categories, total_count) = state.find_category_by_condition(&input, page).await?;
+    Ok(Json(RecordOutput::paged(categories, total_count, page)))
+}
+
+pub async fn category_tree_handler(
+    State(state): State<AppState>,
+    Query(input): Query<CategoryTreeQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let tree = state.find_category_tree(input.status).await?;
+    Ok(Json(tree))
+}
+
+// 无需登录，带 ETag 以便前台和 CDN 缓存
+pub async fn public_category_tree_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let tree = state.find_public_category_tree().await?;
+    let body = serde_json::to_vec(&tree).map_err(|e| AppError::AnyError(e.into()))?;
+    let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+
+    let mut res_headers = HeaderMap::new();
+    res_headers.insert(
+        CACHE_CONTROL,
+        HeaderValue::from_static(CATEGORY_TREE_MAX_AGE),
+    );
+    res_headers.insert(ETAG, etag.parse()?);
+    let matched = headers
+        .get(IF_NONE_MATCH)
+        .is_some_and(|v| v.as_bytes() == etag.as_bytes());
+    if matched {
+        return Ok((StatusCode::NOT_MODIFIED, res_headers).into_response());
+    }
+    res_headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok((res_headers, body).into_response())
+}
+
+pub async fn get_category_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_category_by_id(id).await? {
+        Some(category) => Ok(Json(category)),
+        None => Err(AppError::NotFound(format!("category id {}", id))),
+    }
+}
+
+pub async fn update_category_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OperateCategory>,
+) -> Result<impl IntoResponse, AppError> {
+    let category = state.update_category(id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(category)))
+}
+
+pub async fn move_category_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<MoveCategory>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("move_category_handler {:?} {:?}", id, input);
+    let category = state.move_category(id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(category)))
+}
+
+pub async fn delete_category_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("delete_category_handler {:?}", id);
+    let result = state.delete_category(id).await?;
+    Ok((StatusCode::OK, Json(result)))
+}
@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::{EffectStatus, User};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    check_export_total, error::AppError, export_response, AppState, ExportFormat, ExportLocale,
+    ExportOptions, OperateDepartment, Page, RecordOutput, MAX_EXPORT_ROWS,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchDepartment {
+    pub name: Option<String>,
+    pub parent_id: Option<i64>,
+    pub status: Option<EffectStatus>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportDepartment {
+    pub name: Option<String>,
+    pub parent_id: Option<i64>,
+    pub status: Option<EffectStatus>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DepartmentTreeQuery {
+    pub status: Option<EffectStatus>,
+}
+
+pub async fn create_department_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<OperateDepartment>,
+) -> Result<impl IntoResponse, AppError> {
+    let dept = state.create_department(&input, user.username).await?;
+    Ok((StatusCode::CREATED, Json(dept)))
+}
+
+pub async fn list_department_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchDepartment>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_department_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (depts, total_count) = state
+        .find_department_by_condition(input.name.as_deref(), input.parent_id, input.status, page)
+        .await?;
+
+    Ok(Json(RecordOutput::paged(depts, total_count, page)))
+}
+
+pub async fn export_departments_handler(
+    State(state): State<AppState>,
+    Query(input): Query<ExportDepartment>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("export_departments_handler {:?}", input);
+    let (depts, total) = state
+        .find_department_by_condition(
+            input.name.as_deref(),
+            input.parent_id,
+            input.status,
+            Page::first(MAX_EXPORT_ROWS),
+        )
+        .await?;
+    check_export_total(total, MAX_EXPORT_ROWS)?;
+    export_response(&depts, ExportOptions::new(input.format, input.locale))
+}
+
+pub async fn department_tree_handler(
+    State(state): State<AppState>,
+    Query(input): Query<DepartmentTreeQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let tree = state.find_department_tree(input.status).await?;
+    Ok(Json(tree))
+}
+
+pub async fn get_department_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_department_by_id(id).await? {
+        Some(dept) => Ok(Json(dept)),
+        None => Err(AppError::NotFound(format!("department id {}", id))),
+    }
+}
+
+pub async fn update_department_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OperateDepartment>,
+) -> Result<impl IntoResponse, AppError> {
+    let dept = state.update_department(id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(dept)))
+}
+
+pub async fn delete_department_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("delete_department_handler {:?}", id);
+    let result = state.delete_department(id).await?;
+    Ok((StatusCode::OK, Json(result)))
+}
+
+pub async fn restore_department_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("restore_department_handler {:?}", id);
+    let department = state.restore_department(id, user.username).await?;
+    Ok((StatusCode::OK, Json(department)))
+}
@@ -0,0 +1,113 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::User;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    check_export_total, error::AppError, export_response, AdjustStock, AppState, ExportFormat,
+    ExportLocale, ExportOptions, Page, ReceiveStock, RecordOutput, ReturnStock, SearchMovement,
+    DEFAULT_LOW_STOCK_THRESHOLD, MAX_EXPORT_ROWS,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchLowStock {
+    pub threshold: Option<i64>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLowStock {
+    pub threshold: Option<i64>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
+pub async fn get_inventory_handler(
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_sku_inventories(sku_id).await? {
+        Some(inventories) => Ok(Json(inventories)),
+        None => Err(AppError::NotFound(format!("sku id {}", sku_id))),
+    }
+}
+
+pub async fn receive_stock_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+    Json(input): Json<ReceiveStock>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("receive_stock_handler {:?} {:?}", sku_id, input);
+    let inventory = state.receive_stock(sku_id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(inventory)))
+}
+
+pub async fn return_stock_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+    Json(input): Json<ReturnStock>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("return_stock_handler {:?} {:?}", sku_id, input);
+    let inventory = state.return_stock(sku_id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(inventory)))
+}
+
+pub async fn adjust_stock_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+    Json(input): Json<AdjustStock>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("adjust_stock_handler {:?} {:?}", sku_id, input);
+    let inventory = state.adjust_stock(sku_id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(inventory)))
+}
+
+pub async fn list_stock_movements_handler(
+    State(state): State<AppState>,
+    Path(sku_id): Path<i64>,
+    Query(input): Query<SearchMovement>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (movements, total_count) = state.find_stock_movements(sku_id, &input, page).await?;
+    Ok(Json(RecordOutput::paged(movements, total_count, page)))
+}
+
+pub async fn list_low_stock_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchLowStock>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_low_stock_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let threshold = input.threshold.unwrap_or(DEFAULT_LOW_STOCK_THRESHOLD);
+    let (items, total_count) = state.find_low_stock(threshold, page).await?;
+    Ok(Json(RecordOutput::paged(items, total_count, page)))
+}
+
+pub async fn export_low_stock_handler(
+    State(state): State<AppState>,
+    Query(input): Query<ExportLowStock>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("export_low_stock_handler {:?}", input);
+    let threshold = input.threshold.unwrap_or(DEFAULT_LOW_STOCK_THRESHOLD);
+    let (items, total) = state
+        .find_low_stock(threshold, Page::first(MAX_EXPORT_ROWS))
+        .await?;
+    check_export_total(total, MAX_EXPORT_ROWS)?;
+    export_response(&items, ExportOptions::new(input.format, input.locale))
+}
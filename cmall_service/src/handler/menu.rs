@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::{EffectStatus, MenuType, User};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    check_export_total, error::AppError, export_response, AppState, ExportFormat, ExportLocale,
+    ExportOptions, OperateMenu, Page, RecordOutput, MAX_EXPORT_ROWS,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMenu {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub menu_type: Option<MenuType>,
+    pub status: Option<EffectStatus>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportMenu {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub menu_type: Option<MenuType>,
+    pub status: Option<EffectStatus>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuTreeQuery {
+    pub status: Option<EffectStatus>,
+}
+
+pub async fn create_menu_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<OperateMenu>,
+) -> Result<impl IntoResponse, AppError> {
+    let menu = state.create_menu(&input, user.username).await?;
+    Ok((StatusCode::CREATED, Json(menu)))
+}
+
+pub async fn list_menu_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchMenu>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_menu_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (menus, total_count) = state
+        .find_menu_by_condition(input.name.as_deref(), input.menu_type, input.status, page)
+        .await?;
+
+    Ok(Json(RecordOutput::paged(menus, total_count, page)))
+}
+
+pub async fn export_menus_handler(
+    State(state): State<AppState>,
+    Query(input): Query<ExportMenu>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("export_menus_handler {:?}", input);
+    let (menus, total) = state
+        .find_menu_by_condition(
+            input.name.as_deref(),
+            input.menu_type,
+            input.status,
+            Page::first(MAX_EXPORT_ROWS),
+        )
+        .await?;
+    check_export_total(total, MAX_EXPORT_ROWS)?;
+    export_response(&menus, ExportOptions::new(input.format, input.locale))
+}
+
+pub async fn menu_tree_handler(
+    State(state): State<AppState>,
+    Query(input): Query<MenuTreeQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let tree = state.find_menu_tree(input.status).await?;
+    Ok(Json(tree))
+}
+
+pub async fn get_menu_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_menu_by_id(id).await? {
+        Some(menu) => Ok(Json(menu)),
+        None => Err(AppError::NotFound(format!("menu id {}", id))),
+    }
+}
+
+pub async fn update_menu_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OperateMenu>,
+) -> Result<impl IntoResponse, AppError> {
+    let menu = state.update_menu(id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(menu)))
+}
+
+pub async fn delete_menu_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("delete_menu_handler {:?}", id);
+    let result = state.delete_menu(id).await?;
+    Ok((StatusCode::OK, Json(result)))
+}
+
+pub async fn restore_menu_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("restore_menu_handler {:?}", id);
+    let menu = state.restore_menu(id, user.username).await?;
+    Ok((StatusCode::OK, Json(menu)))
+}
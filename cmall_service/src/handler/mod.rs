@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{Page, PageInfo};
+
 mod user;
 pub use user::*;
 
@@ -9,13 +11,73 @@ pub use auth::*;
 mod role;
 pub use role::*;
 
+mod department;
+pub use department::*;
+
+mod menu;
+pub use menu::*;
+
+mod audit;
+pub use audit::*;
+
+mod recycle;
+pub use recycle::*;
+
+mod product;
+pub use product::*;
+
+mod category;
+pub use category::*;
+
+mod inventory;
+pub use inventory::*;
+
+mod warehouse;
+pub use warehouse::*;
+
+mod transfer;
+pub use transfer::*;
+
+mod cart;
+pub use cart::*;
+
+mod order;
+pub use order::*;
+
+mod payment;
+pub use payment::*;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RecordOutput<T> {
     body: Vec<T>,
-    total: i64,
+    // 游标分页不统计总数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<PageInfo>,
 }
 impl<T> RecordOutput<T> {
     pub fn new(body: Vec<T>, total: i64) -> Self {
-        Self { body, total }
+        Self {
+            body,
+            total: Some(total),
+            page: None,
+        }
+    }
+
+    pub fn paged(body: Vec<T>, total: i64, page: Page) -> Self {
+        Self {
+            body,
+            total: Some(total),
+            page: Some(page.info(total)),
+        }
+    }
+
+    pub fn with_page(body: Vec<T>, total: Option<i64>, page: PageInfo) -> Self {
+        Self {
+            body,
+            total,
+            page: Some(page),
+        }
     }
 }
@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::{OrderStatus, User};
+use tracing::info;
+
+use crate::{
+    error::AppError, AppState, Checkout, OrderStatusInput, Page, RecordOutput, SearchOrder,
+};
+
+pub async fn list_order_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchOrder>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_order_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (orders, total_count) = state.find_order_by_condition(&input, page).await?;
+    Ok(Json(RecordOutput::paged(orders, total_count, page)))
+}
+
+pub async fn get_order_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_order_detail(id).await? {
+        Some(order) => Ok(Json(order)),
+        None => Err(AppError::NotFound(format!("order id {}", id))),
+    }
+}
+
+pub async fn change_order_status_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OrderStatusInput>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("change_order_status_handler {:?} {:?}", id, input);
+    let order = state
+        .change_order_status(id, input.status, None, &user.username)
+        .await?;
+    Ok((StatusCode::OK, Json(order)))
+}
+
+pub async fn checkout_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<Checkout>,
+) -> Result<impl IntoResponse, AppError> {
+    let order = state.checkout(&user, &input).await?;
+    Ok((StatusCode::CREATED, Json(order)))
+}
+
+pub async fn list_my_order_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Query(mut input): Query<SearchOrder>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = Page::new(input.page_num, input.page_size)?;
+    input.user_id = Some(user.id);
+    let (orders, total_count) = state.find_order_by_condition(&input, page).await?;
+    Ok(Json(RecordOutput::paged(orders, total_count, page)))
+}
+
+pub async fn get_my_order_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_order_detail(id).await? {
+        Some(order) if order.order.user_id == user.id => Ok(Json(order)),
+        _ => Err(AppError::NotFound(format!("order id {}", id))),
+    }
+}
+
+pub async fn cancel_my_order_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let order = state
+        .change_order_status(id, OrderStatus::Cancelled, Some(user.id), &user.username)
+        .await?;
+    Ok((StatusCode::OK, Json(order)))
+}
+
+// 用户确认收货
+pub async fn confirm_my_order_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let order = state
+        .change_order_status(id, OrderStatus::Completed, Some(user.id), &user.username)
+        .await?;
+    Ok((StatusCode::OK, Json(order)))
+}
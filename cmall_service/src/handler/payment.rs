@@ -0,0 +1,51 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::User;
+use tracing::info;
+
+use crate::{error::AppError, AppState};
+
+pub async fn pay_my_order_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let payment = state.pay_order(id, user.id).await?;
+    Ok((StatusCode::CREATED, Json(payment)))
+}
+
+// 支付渠道的回调不携带登录凭证，依靠签名校验
+pub async fn payment_webhook_handler(
+    State(state): State<AppState>,
+    Path(gateway): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    info!("payment_webhook_handler {:?}", gateway);
+    state
+        .handle_payment_webhook(&gateway, &headers, &body)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_order_payments_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let payments = state.find_order_payments(id).await?;
+    Ok(Json(payments))
+}
+
+pub async fn sync_payment_handler(
+    State(state): State<AppState>,
+    Path((id, payment_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("sync_payment_handler {:?} {:?}", id, payment_id);
+    let payment = state.sync_payment(id, payment_id).await?;
+    Ok((StatusCode::OK, Json(payment)))
+}
@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::{ProductStatus, User};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    check_export_total, error::AppError, export_response, AppState, CreateProduct, ExportFormat,
+    ExportLocale, ExportOptions, OperateProduct, OperateSku, Page, ProductStatusInput,
+    RecordOutput, SearchProduct, MAX_EXPORT_ROWS,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProduct {
+    pub name: Option<String>,
+    pub category_id: Option<i64>,
+    pub status: Option<ProductStatus>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
+pub async fn create_product_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<CreateProduct>,
+) -> Result<impl IntoResponse, AppError> {
+    let product = state.create_product(&input, user.username).await?;
+    Ok((StatusCode::CREATED, Json(product)))
+}
+
+pub async fn list_product_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchProduct>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_product_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (products, total_count) = state.find_product_by_condition(&input, page).await?;
+    Ok(Json(RecordOutput::paged(products, total_count, page)))
+}
+
+pub async fn export_products_handler(
+    State(state): State<AppState>,
+    Query(input): Query<ExportProduct>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("export_products_handler {:?}", input);
+    let search = SearchProduct {
+        name: input.name.clone(),
+        category_id: input.category_id,
+        status: input.status.clone(),
+        ..SearchProduct::default()
+    };
+    let (products, total) = state
+        .find_product_by_condition(&search, Page::first(MAX_EXPORT_ROWS))
+        .await?;
+    check_export_total(total, MAX_EXPORT_ROWS)?;
+    export_response(&products, ExportOptions::new(input.format, input.locale))
+}
+
+pub async fn get_product_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_product_detail(id).await? {
+        Some(product) => Ok(Json(product)),
+        None => Err(AppError::NotFound(format!("product id {}", id))),
+    }
+}
+
+pub async fn update_product_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OperateProduct>,
+) -> Result<impl IntoResponse, AppError> {
+    let product = state.update_product(id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(product)))
+}
+
+pub async fn set_product_status_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<ProductStatusInput>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("set_product_status_handler {:?} {:?}", id, input);
+    let product = state
+        .set_product_status(id, input.status, user.username)
+        .await?;
+    Ok((StatusCode::OK, Json(product)))
+}
+
+pub async fn delete_product_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("delete_product_handler {:?}", id);
+    let result = state.delete_product(id).await?;
+    Ok((StatusCode::OK, Json(result)))
+}
+
+pub async fn create_sku_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OperateSku>,
+) -> Result<impl IntoResponse, AppError> {
+    let sku = state.create_sku(id, &input).await?;
+    Ok((StatusCode::CREATED, Json(sku)))
+}
+
+pub async fn update_sku_handler(
+    State(state): State<AppState>,
+    Path((id, sku_id)): Path<(i64, i64)>,
+    Json(input): Json<OperateSku>,
+) -> Result<impl IntoResponse, AppError> {
+    let sku = state.update_sku(id, sku_id, &input).await?;
+    Ok((StatusCode::OK, Json(sku)))
+}
+
+pub async fn delete_sku_handler(
+    State(state): State<AppState>,
+    Path((id, sku_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("delete_sku_handler {:?} {:?}", id, sku_id);
+    let result = state.delete_sku(id, sku_id).await?;
+    Ok((StatusCode::OK, Json(result)))
+}
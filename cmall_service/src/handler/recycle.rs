@@ -0,0 +1,27 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use tracing::info;
+
+use crate::{error::AppError, AppState, Page, RecordOutput, SearchRecycle};
+
+pub async fn list_recycle_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchRecycle>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_recycle_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (items, total_count) = state.find_recycle_items(&input, page).await?;
+    Ok(Json(RecordOutput::paged(items, total_count, page)))
+}
+
+pub async fn purge_recycle_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let output = state.purge_expired().await?;
+    info!("purge_recycle_handler {:?}", output);
+    Ok((StatusCode::OK, Json(output)))
+}
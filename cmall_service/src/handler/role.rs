@@ -10,7 +10,10 @@ use cmall_core::{EffectStatus, User};
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::{error::AppError, AppState, OperateRole, RecordOutput};
+use crate::{
+    check_export_total, error::AppError, export_response, AppState, ExportFormat, ExportLocale,
+    ExportOptions, OperateRole, Page, RecordOutput, RoleMenus, RoleMfa, MAX_EXPORT_ROWS,
+};
 
 // #[serde(deny_unknown_fields)]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -23,6 +26,19 @@ pub struct SearchRole {
     pub page_num: i64,
     pub page_size: i64,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRole {
+    pub code: Option<String>,
+    pub status: Option<EffectStatus>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
 pub async fn create_role_handler(
     Extension(user): Extension<User>,
     State(state): State<AppState>,
@@ -33,21 +49,32 @@ pub async fn create_role_handler(
 }
 
 pub async fn list_role_handler(
-    Extension(user): Extension<User>,
     State(state): State<AppState>,
     Query(input): Query<SearchRole>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("list_role_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
     let (roles, total_count) = state
+        .find_role_by_condition(input.code.as_deref(), input.status, page)
+        .await?;
+
+    Ok(Json(RecordOutput::paged(roles, total_count, page)))
+}
+
+pub async fn export_roles_handler(
+    State(state): State<AppState>,
+    Query(input): Query<ExportRole>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("export_roles_handler {:?}", input);
+    let (roles, total) = state
         .find_role_by_condition(
             input.code.as_deref(),
             input.status,
-            input.page_num,
-            input.page_size,
+            Page::first(MAX_EXPORT_ROWS),
         )
         .await?;
-
-    Ok(Json(RecordOutput::new(roles, total_count)))
+    check_export_total(total, MAX_EXPORT_ROWS)?;
+    export_response(&roles, ExportOptions::new(input.format, input.locale))
 }
 
 pub async fn update_role_handler(
@@ -56,14 +83,26 @@ pub async fn update_role_handler(
     Path(id): Path<i64>,
     Json(input): Json<OperateRole>,
 ) -> Result<impl IntoResponse, AppError> {
-    let role = state.update_role(id, &input).await?;
+    let role = state.update_role(id, &input, user.username).await?;
     Ok((StatusCode::OK, Json(role)))
 }
 
-pub async fn delete_role_handler(
+pub async fn set_role_mfa_handler(
     Extension(user): Extension<User>,
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Json(input): Json<RoleMfa>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("set_role_mfa_handler {:?} {:?}", id, input);
+    let role = state
+        .set_role_mfa_required(id, input.required, user.username)
+        .await?;
+    Ok((StatusCode::OK, Json(role)))
+}
+
+pub async fn delete_role_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("delete_role_handler {:?}", id);
     let result = state.delete_role(id).await?;
@@ -71,3 +110,41 @@ pub async fn delete_role_handler(
 
     Ok((StatusCode::OK, success))
 }
+
+pub async fn restore_role_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("restore_role_handler {:?}", id);
+    let role = state.restore_role(id, user.username).await?;
+    Ok((StatusCode::OK, Json(role)))
+}
+
+pub async fn list_role_menus_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let menu_ids = state.find_role_menu_ids(id).await?;
+    Ok(Json(menu_ids))
+}
+
+pub async fn assign_role_menus_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<RoleMenus>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("assign_role_menus_handler {:?} {:?}", id, input);
+    let menu_ids = state.assign_role_menus(id, &input.menu_ids).await?;
+    Ok((StatusCode::OK, Json(menu_ids)))
+}
+
+pub async fn revoke_role_menus_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<RoleMenus>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("revoke_role_menus_handler {:?} {:?}", id, input);
+    let menu_ids = state.revoke_role_menus(id, &input.menu_ids).await?;
+    Ok((StatusCode::OK, Json(menu_ids)))
+}
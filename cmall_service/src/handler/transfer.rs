@@ -0,0 +1,60 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::User;
+use tracing::info;
+
+use crate::{error::AppError, AppState, CreateTransfer, Page, RecordOutput, SearchTransfer};
+
+pub async fn create_transfer_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<CreateTransfer>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("create_transfer_handler {:?}", input);
+    let transfer = state.create_transfer(&input, user.username).await?;
+    Ok((StatusCode::CREATED, Json(transfer)))
+}
+
+pub async fn list_transfer_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchTransfer>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_transfer_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (transfers, total_count) = state.find_transfer_by_condition(&input, page).await?;
+    Ok(Json(RecordOutput::paged(transfers, total_count, page)))
+}
+
+pub async fn get_transfer_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_transfer_detail(id).await? {
+        Some(transfer) => Ok(Json(transfer)),
+        None => Err(AppError::NotFound(format!("transfer id {}", id))),
+    }
+}
+
+pub async fn complete_transfer_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("complete_transfer_handler {:?}", id);
+    let transfer = state.complete_transfer(id, user.username).await?;
+    Ok((StatusCode::OK, Json(transfer)))
+}
+
+pub async fn cancel_transfer_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("cancel_transfer_handler {:?}", id);
+    let transfer = state.cancel_transfer(id, user.username).await?;
+    Ok((StatusCode::OK, Json(transfer)))
+}
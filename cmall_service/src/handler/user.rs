@@ -1,33 +1,22 @@
-use std::fs;
-
 use axum::{
-    extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{Multipart, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Extension, Json,
 };
-use cmall_core::{User, UserStatus};
-use rust_xlsxwriter::{Format, Workbook};
-use serde::{Deserialize, Serialize};
+use cmall_core::{ExportStatus, User};
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
 use tracing::info;
 
-use crate::{error::AppError, CreateUser, UpdateUser};
-use crate::{AppState, RecordOutput};
+use crate::{
+    attachment_headers, error::AppError, ChangePassword, CreateUser, ExportJobOutput, ExportUser,
+    ImportFormat, ImportUsers, MfaCode, ResetPasswordOutput, SearchUser, UpdateUser, UserExport,
+};
+use crate::{AppState, Page, RecordOutput};
 
-// #[serde(deny_unknown_fields)]
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[serde(deny_unknown_fields)]
-#[serde(expecting = "params is error")]
-#[serde(rename_all = "camelCase")]
-pub struct SearchUser {
-    #[serde(default)]
-    pub username: Option<String>,
-    pub phone: Option<String>,
-    pub email: Option<String>,
-    pub status: Option<UserStatus>,
-    pub page_num: i64,
-    pub page_size: i64,
-}
+const USER_EXPORT_PREFIX: &str = "/api/v1/user/export";
 
 pub async fn list_user_handler(
     State(state): State<AppState>,
@@ -35,17 +24,9 @@ pub async fn list_user_handler(
 ) -> Result<impl IntoResponse, AppError> {
     info!("search user: {:?}", input);
     // let user = state.
-    let (users, total_count) = state
-        .find_user_by_conditions(
-            input.username.as_deref(),
-            input.email.as_deref(),
-            input.phone.as_deref(),
-            input.status,
-            input.page_num,
-            input.page_size,
-        )
-        .await?;
-    Ok(Json(RecordOutput::new(users, total_count)))
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (users, total_count) = state.find_user_by_conditions(&input, page).await?;
+    Ok(Json(RecordOutput::paged(users, total_count, page)))
 }
 
 pub async fn get_user_handler(
@@ -59,8 +40,80 @@ pub async fn get_user_handler(
     }
 }
 
-pub async fn update_user_handler(
+pub async fn my_menus_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let menus = state.find_user_menus(user.id).await?;
+    Ok(Json(menus))
+}
+
+pub async fn change_my_password_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<ChangePassword>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("change_my_password_handler {:?}", user.id);
+    state.change_password(user.id, &input).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn setup_my_mfa_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let setup = state.setup_mfa(&user).await?;
+    Ok((StatusCode::OK, Json(setup)))
+}
+
+pub async fn enable_my_mfa_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<MfaCode>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("enable_my_mfa_handler {:?}", user.id);
+    let codes = state.enable_mfa(&user, &input.code).await?;
+    Ok((StatusCode::OK, Json(codes)))
+}
+
+pub async fn disable_my_mfa_handler(
     Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<MfaCode>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("disable_my_mfa_handler {:?}", user.id);
+    state.disable_mfa(&user, &input.code).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn reset_user_password_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("reset_user_password_handler {:?}", id);
+    let temp_password = state.reset_password(id).await?;
+    Ok((StatusCode::OK, Json(ResetPasswordOutput { temp_password })))
+}
+
+pub async fn unlock_user_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("unlock_user_handler {:?}", id);
+    let user = state.unlock_user(id).await?;
+    Ok((StatusCode::OK, Json(user)))
+}
+
+pub async fn restore_user_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("restore_user_handler {:?}", id);
+    let user = state.restore_user(id).await?;
+    Ok((StatusCode::OK, Json(user)))
+}
+
+pub async fn update_user_handler(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Json(input): Json<UpdateUser>,
@@ -69,9 +122,26 @@ pub async fn update_user_handler(
     Ok((StatusCode::OK, Json(user)))
 }
 
+pub async fn add_user_role_handler(
+    State(state): State<AppState>,
+    Path((id, role_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("add_user_role_handler {:?} {:?}", id, role_id);
+    let user = state.add_user_role(id, role_id).await?;
+    Ok((StatusCode::OK, Json(user)))
+}
+
+pub async fn remove_user_role_handler(
+    State(state): State<AppState>,
+    Path((id, role_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("remove_user_role_handler {:?} {:?}", id, role_id);
+    let user = state.remove_user_role(id, role_id).await?;
+    Ok((StatusCode::OK, Json(user)))
+}
+
 // create_user_handler
 pub async fn create_user_handler(
-    Extension(user): Extension<User>,
     State(state): State<AppState>,
     Json(input): Json<CreateUser>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -91,59 +161,72 @@ pub async fn delete_user_handler(
     Ok((StatusCode::OK, success))
 }
 
-pub async fn export_users_handler(
+// 上传 file 字段，根据文件扩展名识别 CSV 或 XLSX
+pub async fn import_users_handler(
     State(state): State<AppState>,
+    Query(input): Query<ImportUsers>,
+    mut multipart: Multipart,
 ) -> Result<impl IntoResponse, AppError> {
-    info!("export_file_handler");
-    let users = state.find_users().await?;
-
-    // Create a new Excel file object.
-    let mut workbook = Workbook::new();
-
-    // Add a worksheet to the workbook.
-    let worksheet = workbook.add_worksheet();
-    let date_format = Format::new().set_num_format("d mmm yyyy");
-
-    // Iterate over the data and write it out row by row.
-    let mut row = 1;
-    for user in &users {
-        worksheet.write(row, 0, user.id)?;
-        worksheet.write(row, 1, user.username.clone())?;
-        worksheet.write(row, 2, user.phone.clone())?;
-        worksheet.write(row, 3, user.email.clone())?;
-        worksheet.write(row, 4, user.status.to_string())?;
-        worksheet.write_with_format(
-            row,
-            5,
-            user.create_time.format("%Y-%m-%d %H:%M:%S").to_string(),
-            &date_format,
-        )?;
-        row += 1;
+    let mut file = None;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("file") {
+            let file_name = field.file_name().unwrap_or_default().to_string();
+            let data = field.bytes().await?;
+            file = Some((file_name, data));
+        }
     }
-    let bold = Format::new().set_bold();
-    // Write some column headers.
-    worksheet.write_with_format(0, 0, "编号", &bold)?;
-    worksheet.write_with_format(0, 1, "名称", &bold)?;
-    worksheet.write_with_format(0, 2, "电话", &bold)?;
-    worksheet.write_with_format(0, 3, "邮箱", &bold)?;
-    worksheet.write_with_format(0, 4, "状态", &bold)?;
-    worksheet.write_with_format(0, 5, "创建时间", &bold)?;
-
-    worksheet.set_column_width(1, 15)?;
-    worksheet.set_column_width(2, 15)?;
-    worksheet.set_column_width(3, 15)?;
-    worksheet.set_column_width(5, 25)?;
-
-    // Save the file to disk.
-    let path = "user.xlsx";
-    workbook.save(path)?;
-
-    info!("Path: {}", path);
-    let mime = mime_guess::from_path(path).first_or_octet_stream();
-    // TODO: streaming
-    let body = fs::read(path)?;
-    info!("Body: {:?}", body);
-    let mut headers = HeaderMap::new();
-    headers.insert("content-type", mime.to_string().parse().unwrap());
-    Ok((headers, body))
+    let (file_name, data) =
+        file.ok_or_else(|| AppError::ImportError("file is required".to_string()))?;
+    info!("import_users_handler {} {:?}", file_name, input);
+    let format = ImportFormat::from_file_name(&file_name)
+        .ok_or_else(|| AppError::ImportError(format!("unsupported file type: {}", file_name)))?;
+    let report = state.import_users(format, &data, input.dry_run).await?;
+    Ok((StatusCode::OK, Json(report)))
+}
+
+pub async fn export_users_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Query(input): Query<ExportUser>,
+) -> Result<Response, AppError> {
+    info!("export_users_handler {:?}", input);
+    let res = match state.export_users(user.id, &input).await? {
+        UserExport::File { file_name, data } => {
+            (attachment_headers(&file_name)?, data).into_response()
+        }
+        UserExport::Job(job) => (
+            StatusCode::ACCEPTED,
+            Json(ExportJobOutput::new(job, USER_EXPORT_PREFIX)),
+        )
+            .into_response(),
+    };
+    Ok(res)
+}
+
+pub async fn get_export_job_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let job = state
+        .find_export_job(&id, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("export job {}", id)))?;
+    Ok(Json(ExportJobOutput::new(job, USER_EXPORT_PREFIX)))
+}
+
+// 以流的方式返回后台任务生成的文件
+pub async fn download_export_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let job = state
+        .find_export_job(&id, user.id)
+        .await?
+        .filter(|job| job.status == ExportStatus::Done)
+        .ok_or_else(|| AppError::NotFound(format!("finished export job {}", id)))?;
+    let file = File::open(&job.file_path).await?;
+    let body = Body::from_stream(ReaderStream::new(file));
+    Ok((attachment_headers(&job.file_name)?, body))
 }
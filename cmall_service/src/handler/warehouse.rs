@@ -0,0 +1,58 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use cmall_core::User;
+use tracing::info;
+
+use crate::{error::AppError, AppState, OperateWarehouse, Page, RecordOutput, SearchWarehouse};
+
+pub async fn create_warehouse_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Json(input): Json<OperateWarehouse>,
+) -> Result<impl IntoResponse, AppError> {
+    let warehouse = state.create_warehouse(&input, user.username).await?;
+    Ok((StatusCode::CREATED, Json(warehouse)))
+}
+
+pub async fn list_warehouse_handler(
+    State(state): State<AppState>,
+    Query(input): Query<SearchWarehouse>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("list_warehouse_handler {:?}", input);
+    let page = Page::new(input.page_num, input.page_size)?;
+    let (warehouses, total_count) = state.find_warehouse_by_condition(&input, page).await?;
+    Ok(Json(RecordOutput::paged(warehouses, total_count, page)))
+}
+
+pub async fn get_warehouse_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.find_warehouse_by_id(id).await? {
+        Some(warehouse) => Ok(Json(warehouse)),
+        None => Err(AppError::NotFound(format!("warehouse id {}", id))),
+    }
+}
+
+pub async fn update_warehouse_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(input): Json<OperateWarehouse>,
+) -> Result<impl IntoResponse, AppError> {
+    let warehouse = state.update_warehouse(id, &input, user.username).await?;
+    Ok((StatusCode::OK, Json(warehouse)))
+}
+
+pub async fn delete_warehouse_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("delete_warehouse_handler {:?}", id);
+    let result = state.delete_warehouse(id).await?;
+    Ok((StatusCode::OK, Json(result)))
+}
@@ -1,13 +1,18 @@
+mod cache;
 mod config;
 mod error;
+mod export;
 mod handler;
+mod mailer;
 mod models;
+mod pagination;
+mod payment;
 mod router;
 mod serde_error;
 
 use anyhow::Context;
 use axum::{
-    http::Method,
+    http::{HeaderMap, Method},
     middleware::from_fn_with_state,
     response::IntoResponse,
     routing::{get, post},
@@ -18,15 +23,28 @@ use error::AppError;
 use sqlx::PgPool;
 use tower_http::cors::{Any, CorsLayer};
 // use sqlx_db_tester::TestPg;
-use std::{ops::Deref, sync::Arc};
+use serde_json::Value;
+use std::{net::SocketAddr, ops::Deref, sync::Arc, time::Duration};
 use tokio::fs;
 
-use cmall_core::{verify_token, DecodingKeyPair, EncodingKeyPair, TokenVerify, User};
+use cache::TtlCache;
+use cmall_core::{
+    audit, verify_token, AuditEntry, AuditRecorder, DecodingKeyPair, EncodingKeyPair,
+    PermissionVerify, SessionId, TokenVerify, User, VerifiedToken,
+};
 pub use config::*;
+pub use export::*;
 pub use handler::*;
+pub use mailer::*;
 pub use models::*;
+pub use pagination::*;
+pub use payment::*;
 pub use router::*;
 
+// 已登录用户的缓存时间，用户状态或角色变更最迟在此时间后生效
+const USER_CACHE_TTL: Duration = Duration::from_secs(30);
+const USER_CACHE_CAPACITY: usize = 10_000;
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     inner: Arc<AppStateInner>,
@@ -44,6 +62,9 @@ pub struct AppStateInner {
     pub(crate) secret_key: EncodingKeyPair,
     pub(crate) public_key: DecodingKeyPair,
     pub(crate) pool: PgPool,
+    pub(crate) user_cache: TtlCache<i64, User>,
+    pub(crate) mailer: MailSender,
+    pub(crate) payment: PaymentProvider,
 }
 
 impl AppState {
@@ -62,12 +83,18 @@ impl AppState {
             .await
             .context("Connect to database failed")?;
 
+        let mailer = MailSender::try_new(&config.mail)?;
+        let payment = PaymentProvider::try_new(&config.payment)?;
+
         Ok(Self {
             inner: Arc::new(AppStateInner {
                 config,
                 secret_key,
                 public_key,
                 pool,
+                user_cache: TtlCache::new(USER_CACHE_TTL, USER_CACHE_CAPACITY),
+                mailer,
+                payment,
             }),
         })
     }
@@ -93,11 +120,24 @@ pub fn setup_router(state: AppState) -> Result<Router, AppError> {
             Method::PATCH,
         ])
         .allow_origin(origins)
-        .allow_headers(Any);
-    let base_router = setup_base_router()
+        .allow_headers(Any)
+        .expose_headers([CART_TOKEN_HEADER]);
+    let base_router = setup_base_router(&state)
+        .route("/signout", post(signout_handler))
+        .layer(from_fn_with_state(state.clone(), audit::<AppState>))
+        // 购物车和用户自己的订单操作频繁且只涉及本人数据，不记录审计日志
+        .nest("/cart", setup_cart_router())
+        .nest("/order/me", setup_my_order_router())
         .layer(from_fn_with_state(state.clone(), verify_token::<AppState>))
         .route("/signin", post(signin_handler))
+        .route("/signin/mfa", post(mfa_signin_handler))
+        .route("/refresh", post(refresh_handler))
         .route("/signup", post(signup_handler))
+        .route("/forgot-password", post(forgot_password_handler))
+        .route("/reset-password", post(confirm_password_reset_handler))
+        .route("/public/category/tree", get(public_category_tree_handler))
+        .nest("/public/cart", setup_guest_cart_router())
+        .route("/payment/webhook/:gateway", post(payment_webhook_handler))
         .layer(cors);
     let cmall_router = Router::new()
         .route("/", get(index_handler))
@@ -109,8 +149,77 @@ pub fn setup_router(state: AppState) -> Result<Router, AppError> {
 impl TokenVerify for AppState {
     type Error = AppError;
 
-    fn verify(&self, token: &str) -> Result<User, Self::Error> {
-        Ok(self.public_key.verify(token)?)
+    async fn verify(&self, token: &str) -> Result<VerifiedToken, Self::Error> {
+        let claims = self.public_key.verify(token)?;
+        let session_id: i64 = claims
+            .session_id
+            .parse()
+            .map_err(|_| AppError::SessionRevoked(claims.session_id.clone()))?;
+        // 会话已注销或过期时，未过期的 access token 也不再有效
+        match self.find_active_session(session_id).await? {
+            Some(session) if session.user_id == claims.user_id => {}
+            _ => return Err(AppError::SessionRevoked(claims.session_id)),
+        }
+        let user = self.find_signed_in_user(claims.user_id).await?;
+        Ok(VerifiedToken {
+            user,
+            session_id: SessionId(claims.session_id),
+        })
+    }
+}
+
+impl PermissionVerify for AppState {
+    type Error = AppError;
+
+    async fn permissions(&self, user: &User) -> Result<Vec<String>, Self::Error> {
+        // 管理员重置密码后，修改密码前不授予任何权限
+        if user.must_change_password {
+            return Err(AppError::PasswordChangeRequired(user.id));
+        }
+        // 角色要求两步验证但用户尚未开启时，只能访问个人设置接口
+        if user.mfa_required && !user.mfa_enabled {
+            return Err(AppError::MfaRequired(user.id));
+        }
+        self.find_user_permissions(user.id).await
+    }
+}
+
+impl AuditRecorder for AppState {
+    type Error = AppError;
+
+    fn client_ip(&self, headers: &HeaderMap, addr: Option<SocketAddr>) -> String {
+        AppState::client_ip(self, headers, addr)
+    }
+
+    async fn snapshot(
+        &self,
+        target_type: &str,
+        target_id: i64,
+    ) -> Result<Option<Value>, Self::Error> {
+        self.find_audit_snapshot(target_type, target_id).await
+    }
+
+    async fn record(&self, entry: AuditEntry) -> Result<(), Self::Error> {
+        self.create_audit_log(&entry).await?;
+        Ok(())
+    }
+}
+
+impl AppState {
+    // 获取客户端地址，只有配置信任代理时才读取 X-Forwarded-For
+    pub fn client_ip(&self, headers: &HeaderMap, addr: Option<SocketAddr>) -> String {
+        if self.config.auth.lockout.trust_forwarded_for {
+            let forwarded = headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(|v| v.trim().to_string());
+            if let Some(ip) = forwarded.filter(|ip| !ip.is_empty()) {
+                return ip;
+            }
+        }
+        addr.map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
     }
 }
 
@@ -122,6 +231,9 @@ async fn index_handler() -> impl IntoResponse {
 mod test_util {
     use super::*;
 
+    use std::collections::BTreeMap;
+
+    use cmall_core::{EffectStatus, ProductDetail, ProductStatus};
     use sqlx::{Executor, PgPool};
     use sqlx_db_tester::TestPg;
 
@@ -144,6 +256,8 @@ mod test_util {
             let server_url = &config.server.db_url[..post];
 
             let (tdb, pool) = get_test_pool(Some(server_url)).await;
+            let mailer = MailSender::try_new(&config.mail)?;
+            let payment = PaymentProvider::try_new(&config.payment)?;
 
             let state = Self {
                 inner: Arc::new(AppStateInner {
@@ -151,10 +265,53 @@ mod test_util {
                     secret_key,
                     public_key,
                     pool,
+                    user_cache: TtlCache::new(USER_CACHE_TTL, USER_CACHE_CAPACITY),
+                    mailer,
+                    payment,
                 }),
             };
             Ok((tdb, state))
         }
+
+        // 创建在售商品并在默认仓库入库
+        pub async fn create_on_sale_product(
+            &self,
+            quantity: i64,
+        ) -> Result<ProductDetail, AppError> {
+            let by = "admin".to_string();
+            let detail = self
+                .create_product(&test_product("CUP"), by.clone())
+                .await?;
+            let receive = ReceiveStock {
+                warehouse_id: 1,
+                quantity,
+                reference: "".to_string(),
+            };
+            self.receive_stock(detail.skus[0].id, &receive, by.clone())
+                .await?;
+            self.set_product_status(detail.product.id, ProductStatus::OnSale, by)
+                .await?;
+            Ok(detail)
+        }
+    }
+
+    // 单价 500、只有一个启用 sku 的商品
+    pub fn test_product(code: &str) -> CreateProduct {
+        CreateProduct {
+            product: OperateProduct {
+                category_id: None,
+                name: code.to_lowercase(),
+                description: "".to_string(),
+                price: 500,
+                attributes: vec![],
+            },
+            skus: vec![OperateSku {
+                sku_code: code.to_string(),
+                price: 500,
+                attributes: BTreeMap::new(),
+                status: EffectStatus::Enable,
+            }],
+        }
     }
 
     pub async fn get_test_pool(server_url: Option<&str>) -> (TestPg, PgPool) {
@@ -0,0 +1,161 @@
+use std::{future::Future, path::PathBuf};
+
+use chrono::Utc;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use tokio::fs;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error::AppError, MailConfig, MailSenderConfig};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer {
+    fn send(&self, mail: &Mail) -> impl Future<Output = Result<(), AppError>> + Send;
+}
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+// 根据配置选择的邮件发送方式
+pub enum MailSender {
+    Smtp(SmtpMailer),
+    File(FileMailer),
+}
+
+impl MailSender {
+    pub fn try_new(config: &MailConfig) -> Result<Self, AppError> {
+        let sender = match &config.sender {
+            MailSenderConfig::Smtp {
+                host,
+                port,
+                username,
+                password,
+                starttls,
+            } => {
+                let builder = if *starttls {
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                } else {
+                    AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                }
+                .map_err(|e| AppError::MailError(e.to_string()))?;
+                let transport = builder
+                    .port(*port)
+                    .credentials(Credentials::new(username.clone(), password.clone()))
+                    .build();
+                let from = config
+                    .from
+                    .parse()
+                    .map_err(|e: lettre::address::AddressError| {
+                        AppError::MailError(e.to_string())
+                    })?;
+                Self::Smtp(SmtpMailer { from, transport })
+            }
+            MailSenderConfig::File { dir } => {
+                Self::File(FileMailer::new(&config.from, dir.clone()))
+            }
+        };
+        Ok(sender)
+    }
+}
+
+impl Mailer for MailSender {
+    async fn send(&self, mail: &Mail) -> Result<(), AppError> {
+        match self {
+            Self::Smtp(mailer) => mailer.send(mail).await,
+            Self::File(mailer) => mailer.send(mail).await,
+        }
+    }
+}
+
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), AppError> {
+        let to: Mailbox = mail
+            .to
+            .parse()
+            .map_err(|e: lettre::address::AddressError| AppError::MailError(e.to_string()))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body.clone())
+            .map_err(|e| AppError::MailError(e.to_string()))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| AppError::MailError(e.to_string()))?;
+        info!("mail sent to {}", mail.to);
+        Ok(())
+    }
+}
+
+impl FileMailer {
+    pub fn new(from: &str, dir: PathBuf) -> Self {
+        Self {
+            from: from.to_string(),
+            dir,
+        }
+    }
+}
+
+impl Mailer for FileMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), AppError> {
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!("{}.eml", Uuid::now_v7()));
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nDate: {}\r\nSubject: {}\r\n\r\n{}\r\n",
+            self.from,
+            mail.to,
+            Utc::now().to_rfc2822(),
+            mail.subject,
+            mail.body
+        );
+        fs::write(&path, content).await?;
+        info!("mail to {} written to {}", mail.to, path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_mailer {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_file_mailer_should_write_mail() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("cmall_mail_{}", Uuid::now_v7()));
+        let mailer = FileMailer::new("Cmall <noreply@localhost>", dir.clone());
+        let mail = Mail {
+            to: "elixy@qq.com".to_string(),
+            subject: "hello".to_string(),
+            body: "reset link".to_string(),
+        };
+        mailer.send(&mail).await?;
+
+        let mut entries = fs::read_dir(&dir).await?;
+        let entry = entries.next_entry().await?.expect("mail file should exist");
+        let content = fs::read_to_string(entry.path()).await?;
+        assert!(content.contains("To: elixy@qq.com"));
+        assert!(content.contains("reset link"));
+        fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+}
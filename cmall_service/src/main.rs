@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use cmall_core::User;
 
 use anyhow::Result;
@@ -15,13 +17,17 @@ async fn main() -> Result<()> {
     let addr = format!("{}:{}", config.server.host, config.server.port);
 
     let state = AppState::try_new(config).await.unwrap();
+    state.spawn_order_jobs();
 
     let app = setup_router(state)?;
-  
+
     let listener = TcpListener::bind(&addr).await.unwrap();
     info!("Listening on: {}", addr);
-    axum::serve(listener, app.into_make_service()).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
-    
     Ok(())
 }
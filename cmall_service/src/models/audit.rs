@@ -0,0 +1,339 @@
+use chrono::{DateTime, Utc};
+use cmall_core::{AuditEntry, AuditLog};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    check_export_total, error::AppError, AppState, ExportColumn, ExportFormat, ExportLocale,
+    Exportable, Keyset, Page, PageInfo, QueryFilter,
+};
+
+// 导出审计日志的最大行数
+const MAX_AUDIT_EXPORT_ROWS: i64 = 50_000;
+
+const AUDIT_COLUMNS: &str = "id, actor_id, actor_name, action, target_type, target_id, before_data, after_data, status_code, ip, create_time";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchAudit {
+    pub actor_id: Option<i64>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    // 传入上一页返回的 nextCursor 时使用游标分页
+    pub cursor: Option<i64>,
+    #[serde(default = "default_page_num")]
+    pub page_num: i64,
+    #[serde(default = "default_page_size")]
+    pub page_size: i64,
+}
+
+// 导出条件与查询条件相同，不需要分页
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportAudit {
+    pub actor_id: Option<i64>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
+impl Exportable for AuditLog {
+    const NAME: &'static str = "audit";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("id", "编号", "ID", |l: &AuditLog| l.id.into()),
+            ExportColumn::new("actorName", "操作人", "Actor", |l: &AuditLog| {
+                l.actor_name.as_str().into()
+            }),
+            ExportColumn::new("actorId", "操作人编号", "Actor ID", |l: &AuditLog| {
+                l.actor_id.into()
+            }),
+            ExportColumn::new("action", "操作", "Action", |l: &AuditLog| {
+                l.action.as_str().into()
+            })
+            .width(30.0),
+            ExportColumn::new(
+                "targetType",
+                "对象类型",
+                "Target Type",
+                |l: &AuditLog| l.target_type.as_str().into(),
+            ),
+            ExportColumn::new("targetId", "对象编号", "Target ID", |l: &AuditLog| {
+                l.target_id.into()
+            }),
+            ExportColumn::new("statusCode", "状态码", "Status Code", |l: &AuditLog| {
+                l.status_code.into()
+            }),
+            ExportColumn::new("ip", "IP", "IP", |l: &AuditLog| l.ip.as_str().into()),
+            ExportColumn::new("beforeData", "变更前", "Before", |l: &AuditLog| {
+                l.before_data.clone().into()
+            })
+            .width(40.0),
+            ExportColumn::new("afterData", "变更后", "After", |l: &AuditLog| {
+                l.after_data.clone().into()
+            })
+            .width(40.0),
+            ExportColumn::new("createTime", "操作时间", "Time", |l: &AuditLog| {
+                l.create_time.into()
+            })
+            .width(25.0),
+        ]
+    }
+}
+
+impl Keyset for AuditLog {
+    fn key(&self) -> i64 {
+        self.id
+    }
+}
+
+fn default_page_num() -> i64 {
+    1
+}
+
+fn default_page_size() -> i64 {
+    20
+}
+
+impl AppState {
+    pub async fn create_audit_log(&self, entry: &AuditEntry) -> Result<AuditLog, AppError> {
+        let log = sqlx::query_as(
+            r#"
+            INSERT INTO audit_logs (actor_id, actor_name, action, target_type, target_id, before_data, after_data, status_code, ip)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, actor_id, actor_name, action, target_type, target_id, before_data, after_data, status_code, ip, create_time
+        "#,
+        )
+        .bind(entry.actor_id)
+        .bind(&entry.actor_name)
+        .bind(&entry.action)
+        .bind(&entry.target_type)
+        .bind(entry.target_id)
+        .bind(&entry.before_data)
+        .bind(&entry.after_data)
+        .bind(entry.status_code)
+        .bind(&entry.ip)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(log)
+    }
+
+    pub async fn find_audit_log_by_condition(
+        &self,
+        input: &SearchAudit,
+        page: Page,
+    ) -> Result<(Vec<AuditLog>, i64), AppError> {
+        audit_filter(input)
+            .fetch_page(&self.pool, AUDIT_COLUMNS, "audit_logs", "id DESC", page)
+            .await
+    }
+
+    // 审计日志增长较快，翻页较深时使用游标分页
+    pub async fn find_audit_logs_after(
+        &self,
+        input: &SearchAudit,
+        cursor: Option<i64>,
+        limit: i64,
+    ) -> Result<(Vec<AuditLog>, PageInfo), AppError> {
+        audit_filter(input)
+            .fetch_after(&self.pool, AUDIT_COLUMNS, "audit_logs", "id", cursor, limit)
+            .await
+    }
+
+    pub async fn find_audit_logs_for_export(
+        &self,
+        input: &ExportAudit,
+    ) -> Result<Vec<AuditLog>, AppError> {
+        let input = SearchAudit {
+            actor_id: input.actor_id,
+            action: input.action.clone(),
+            target_type: input.target_type.clone(),
+            target_id: input.target_id,
+            start_time: input.start_time,
+            end_time: input.end_time,
+            ..SearchAudit::default()
+        };
+        let (logs, total) = audit_filter(&input)
+            .fetch_page(
+                &self.pool,
+                AUDIT_COLUMNS,
+                "audit_logs",
+                "id DESC",
+                Page::first(MAX_AUDIT_EXPORT_ROWS),
+            )
+            .await?;
+        check_export_total(total, MAX_AUDIT_EXPORT_ROWS)?;
+        Ok(logs)
+    }
+
+    // 加载操作对象的当前数据，作为审计日志中变更前的数据
+    pub async fn find_audit_snapshot(
+        &self,
+        target_type: &str,
+        target_id: i64,
+    ) -> Result<Option<Value>, AppError> {
+        let value = match target_type {
+            "user" => self
+                .find_user_by_id(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "role" => self
+                .find_role_by_id(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "dept" => self
+                .find_department_by_id(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "menu" => self
+                .find_menu_by_id(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "category" => self
+                .find_category_by_id(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "inventory" => self
+                .find_sku_inventories(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "warehouse" => self
+                .find_warehouse_by_id(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "transfer" => self
+                .find_transfer_detail(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "order" => self
+                .find_order_detail(target_id)
+                .await?
+                .map(serde_json::to_value),
+            "product" => self
+                .find_product_detail(target_id)
+                .await?
+                .map(serde_json::to_value),
+            _ => None,
+        };
+        value.transpose().map_err(|e| AppError::AnyError(e.into()))
+    }
+}
+
+fn audit_filter(input: &SearchAudit) -> QueryFilter {
+    QueryFilter::new()
+        .eq("actor_id", input.actor_id)
+        .eq("action", input.action.clone())
+        .eq("target_type", input.target_type.clone())
+        .eq("target_id", input.target_id)
+        .gte("create_time", input.start_time)
+        .lt("create_time", input.end_time)
+}
+
+#[cfg(test)]
+mod test_audit {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_audit_log_should_be_searchable() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        let before = state.find_audit_snapshot("user", 1).await?;
+        assert_eq!(before.as_ref().unwrap()["id"], 1);
+        assert!(state.find_audit_snapshot("unknown", 1).await?.is_none());
+
+        let entry = AuditEntry {
+            actor_id: 1,
+            actor_name: "admin".to_string(),
+            action: "DELETE /api/v1/user/:id".to_string(),
+            target_type: "user".to_string(),
+            target_id: Some(1),
+            before_data: before,
+            after_data: None,
+            status_code: 200,
+            ip: "127.0.0.1".to_string(),
+        };
+        let log = state.create_audit_log(&entry).await?;
+        assert_eq!(log.target_id, Some(1));
+
+        let input = SearchAudit {
+            target_type: Some("user".to_string()),
+            page_num: 1,
+            page_size: 10,
+            ..SearchAudit::default()
+        };
+        let page = Page::new(1, 10)?;
+        let (logs, total) = state.find_audit_log_by_condition(&input, page).await?;
+        assert_eq!(total, 1);
+        assert_eq!(logs[0], log);
+
+        let input = SearchAudit {
+            target_type: Some("role".to_string()),
+            ..input
+        };
+        let (logs, total) = state.find_audit_log_by_condition(&input, page).await?;
+        assert_eq!(total, 0);
+        assert!(logs.is_empty());
+
+        // 超长的请求路径和转发地址也要完整记录
+        let entry = AuditEntry {
+            action: format!("POST /api/v1/{}", "a".repeat(1000)),
+            ip: vec!["10.0.0.1"; 100].join(", "),
+            ..entry
+        };
+        let log = state.create_audit_log(&entry).await?;
+        assert_eq!(log.action, entry.action);
+        assert_eq!(log.ip, entry.ip);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_cursor_pagination_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let mut ids = vec![];
+        for i in 0..3 {
+            let entry = AuditEntry {
+                actor_id: 1,
+                actor_name: "admin".to_string(),
+                action: format!("POST /api/v1/role/{}", i),
+                target_type: "role".to_string(),
+                target_id: Some(i),
+                before_data: None,
+                after_data: None,
+                status_code: 200,
+                ip: "127.0.0.1".to_string(),
+            };
+            ids.push(state.create_audit_log(&entry).await?.id);
+        }
+
+        let input = SearchAudit::default();
+        let (logs, info) = state.find_audit_logs_after(&input, None, 2).await?;
+        assert_eq!(
+            logs.iter().map(|l| l.id).collect::<Vec<_>>(),
+            [ids[2], ids[1]]
+        );
+        assert!(info.has_more);
+        assert_eq!(info.next_cursor, Some(ids[1]));
+
+        let (logs, info) = state
+            .find_audit_logs_after(&input, info.next_cursor, 2)
+            .await?;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, ids[0]);
+        assert!(!info.has_more);
+        assert_eq!(info.next_cursor, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,402 @@
+use chrono::{Duration, Utc};
+use cmall_core::{Cart, CartItem};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, Postgres, Transaction};
+
+use super::session::{generate_token, hash_token};
+use crate::{error::AppError, AppState};
+
+// 单个 SKU 在购物车中的数量上限
+const MAX_CART_QUANTITY: i64 = 999;
+
+// 启用仓库中的可售数量之和，s 为 product_skus
+const SKU_AVAILABLE: &str = "COALESCE((SELECT SUM(i.on_hand - i.reserved) FROM inventories i JOIN warehouses w ON w.id = i.warehouse_id WHERE i.sku_id = s.id AND w.status = 'enable'), 0)::BIGINT";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CartOwner {
+    User(i64),
+    // 游客购物车的令牌，数据库中只保存哈希
+    Guest(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddCartItem {
+    pub sku_id: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCartItem {
+    pub quantity: i64,
+}
+
+impl AppState {
+    // 读取时重新校验价格和库存，购物车不存在时返回空购物车
+    pub async fn find_cart(&self, owner: &CartOwner) -> Result<Cart, AppError> {
+        let cart_id: Option<i64> = match owner {
+            CartOwner::User(user_id) => {
+                sqlx::query_scalar("SELECT id FROM carts WHERE user_id = $1")
+                    .bind(user_id)
+                    .fetch_optional(&self.pool)
+                    .await?
+            }
+            CartOwner::Guest(token) => {
+                sqlx::query_scalar("SELECT id FROM carts WHERE guest_token = $1")
+                    .bind(hash_token(token))
+                    .fetch_optional(&self.pool)
+                    .await?
+            }
+        };
+        let Some(cart_id) = cart_id else {
+            return Ok(Cart::default());
+        };
+        let items = find_cart_items(&self.pool, cart_id).await?;
+        Cart::build(items).ok_or_else(|| AppError::InvalidCart("amount overflow".to_string()))
+    }
+
+    // 游客加购，没有令牌或令牌对应的购物车已过期时创建新的购物车。
+    // 新购物车和第一件商品在同一事务中写入，校验失败时不会留下空购物车，
+    // 新令牌只返回这一次，之后由客户端保存
+    pub async fn add_guest_cart_item(
+        &self,
+        token: Option<String>,
+        input: &AddCartItem,
+    ) -> Result<(String, Cart), AppError> {
+        if let Some(token) = token {
+            let existed: bool =
+                sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM carts WHERE guest_token = $1)")
+                    .bind(hash_token(&token))
+                    .fetch_one(&self.pool)
+                    .await?;
+            if existed {
+                let cart = self
+                    .add_cart_item(&CartOwner::Guest(token.clone()), input)
+                    .await?;
+                return Ok((token, cart));
+            }
+        }
+        let price = self
+            .check_cart_quantity(input.sku_id, input.quantity)
+            .await?;
+        let token = generate_token();
+        let mut tx = self.pool.begin().await?;
+        let cart_id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO carts (guest_token) VALUES ($1) RETURNING id
+        "#,
+        )
+        .bind(hash_token(&token))
+        .fetch_one(&mut *tx)
+        .await?;
+        save_cart_item(&mut tx, cart_id, input.sku_id, input.quantity, price).await?;
+        tx.commit().await?;
+        let cart = self.find_cart(&CartOwner::Guest(token.clone())).await?;
+        Ok((token, cart))
+    }
+
+    // 重复加入同一 SKU 时累加数量，并以当前价格作为加入价格
+    pub async fn add_cart_item(
+        &self,
+        owner: &CartOwner,
+        input: &AddCartItem,
+    ) -> Result<Cart, AppError> {
+        if input.quantity <= 0 {
+            return Err(AppError::InvalidCart(format!(
+                "invalid quantity: {}",
+                input.quantity
+            )));
+        }
+        let mut tx = self.pool.begin().await?;
+        let cart_id = lock_cart(&mut tx, owner).await?;
+        let existing: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT quantity FROM cart_items WHERE cart_id = $1 AND sku_id = $2
+        "#,
+        )
+        .bind(cart_id)
+        .bind(input.sku_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let quantity = existing.unwrap_or_default().saturating_add(input.quantity);
+        let price = self.check_cart_quantity(input.sku_id, quantity).await?;
+        save_cart_item(&mut tx, cart_id, input.sku_id, quantity, price).await?;
+        tx.commit().await?;
+        self.find_cart(owner).await
+    }
+
+    pub async fn update_cart_item(
+        &self,
+        owner: &CartOwner,
+        sku_id: i64,
+        input: &UpdateCartItem,
+    ) -> Result<Cart, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let cart_id = lock_cart(&mut tx, owner).await?;
+        self.check_cart_quantity(sku_id, input.quantity).await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE cart_items SET quantity = $1, update_time = CURRENT_TIMESTAMP WHERE cart_id = $2 AND sku_id = $3
+        "#,
+        )
+        .bind(input.quantity)
+        .bind(cart_id)
+        .bind(sku_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("cart sku id {}", sku_id)));
+        }
+        tx.commit().await?;
+        self.find_cart(owner).await
+    }
+
+    pub async fn remove_cart_item(&self, owner: &CartOwner, sku_id: i64) -> Result<Cart, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let cart_id = lock_cart(&mut tx, owner).await?;
+        let result = sqlx::query(
+            r#"
+            DELETE FROM cart_items WHERE cart_id = $1 AND sku_id = $2
+        "#,
+        )
+        .bind(cart_id)
+        .bind(sku_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("cart sku id {}", sku_id)));
+        }
+        tx.commit().await?;
+        self.find_cart(owner).await
+    }
+
+    // 登录时把游客购物车合并到用户购物车，同一 SKU 数量相加，令牌随之失效
+    pub async fn merge_guest_cart(&self, token: &str, user_id: i64) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let guest_id: Option<i64> =
+            sqlx::query_scalar("SELECT id FROM carts WHERE guest_token = $1 FOR UPDATE")
+                .bind(hash_token(token))
+                .fetch_optional(&mut *tx)
+                .await?;
+        // 令牌无效时不影响登录
+        let Some(guest_id) = guest_id else {
+            return Ok(());
+        };
+        let cart_id = lock_cart(&mut tx, &CartOwner::User(user_id)).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO cart_items (cart_id, sku_id, quantity, added_price)
+            SELECT $1, sku_id, quantity, added_price FROM cart_items WHERE cart_id = $2
+            ON CONFLICT (cart_id, sku_id) DO UPDATE SET quantity = LEAST(cart_items.quantity + EXCLUDED.quantity, $3), update_time = CURRENT_TIMESTAMP
+        "#,
+        )
+        .bind(cart_id)
+        .bind(guest_id)
+        .bind(MAX_CART_QUANTITY)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM carts WHERE id = $1
+        "#,
+        )
+        .bind(guest_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // 删除超过有效期未修改的游客购物车，由后台任务定时调用
+    pub(crate) async fn purge_expired_guest_carts(&self) -> Result<u64, AppError> {
+        let before = Utc::now() - Duration::seconds(self.config.cart.guest_expires_in as i64);
+        let result = sqlx::query(
+            r#"
+            DELETE FROM carts WHERE guest_token IS NOT NULL AND update_time < $1
+        "#,
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    // 检查 SKU 是否在售且库存足够，返回当前价格
+    async fn check_cart_quantity(&self, sku_id: i64, quantity: i64) -> Result<i64, AppError> {
+        if !(1..=MAX_CART_QUANTITY).contains(&quantity) {
+            return Err(AppError::InvalidCart(format!(
+                "quantity must be between 1 and {}",
+                MAX_CART_QUANTITY
+            )));
+        }
+        let sku: Option<(i64, bool, i64)> = sqlx::query_as(&format!(
+            "SELECT s.price, (s.status = 'enable' AND p.status = 'on_sale'), {} FROM product_skus s JOIN products p ON p.id = s.product_id WHERE s.id = $1",
+            SKU_AVAILABLE
+        ))
+        .bind(sku_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((price, on_sale, available)) = sku else {
+            return Err(AppError::NotFound(format!("sku id {}", sku_id)));
+        };
+        if !on_sale {
+            return Err(AppError::InvalidCart(format!(
+                "sku {} is not on sale",
+                sku_id
+            )));
+        }
+        if available < quantity {
+            return Err(AppError::InsufficientStock(sku_id));
+        }
+        Ok(price)
+    }
+}
+
+async fn save_cart_item(
+    tx: &mut Transaction<'_, Postgres>,
+    cart_id: i64,
+    sku_id: i64,
+    quantity: i64,
+    price: i64,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO cart_items (cart_id, sku_id, quantity, added_price) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (cart_id, sku_id) DO UPDATE SET quantity = EXCLUDED.quantity, added_price = EXCLUDED.added_price, update_time = CURRENT_TIMESTAMP
+    "#,
+    )
+    .bind(cart_id)
+    .bind(sku_id)
+    .bind(quantity)
+    .bind(price)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// 购物车中的商品及其最新价格和可售数量
+pub(crate) async fn find_cart_items<'e>(
+    executor: impl PgExecutor<'e>,
+    cart_id: i64,
+) -> Result<Vec<CartItem>, AppError> {
+    let items = sqlx::query_as(&format!(
+        "SELECT c.sku_id, s.sku_code, s.product_id, p.name AS product_name, s.attributes, c.quantity, c.added_price, s.price, (s.status = 'enable' AND p.status = 'on_sale') AS on_sale, {} AS available FROM cart_items c JOIN product_skus s ON s.id = c.sku_id JOIN products p ON p.id = s.product_id WHERE c.cart_id = $1 ORDER BY c.create_time, c.sku_id",
+        SKU_AVAILABLE
+    ))
+    .bind(cart_id)
+    .fetch_all(executor)
+    .await?;
+    Ok(items)
+}
+
+// 锁定购物车，用户购物车不存在时自动创建，游客购物车必须已存在
+pub(crate) async fn lock_cart(
+    tx: &mut Transaction<'_, Postgres>,
+    owner: &CartOwner,
+) -> Result<i64, AppError> {
+    match owner {
+        CartOwner::User(user_id) => {
+            let id = sqlx::query_scalar(
+                r#"
+                INSERT INTO carts (user_id) VALUES ($1)
+                ON CONFLICT (user_id) DO UPDATE SET update_time = CURRENT_TIMESTAMP
+                RETURNING id
+            "#,
+            )
+            .bind(user_id)
+            .fetch_one(&mut **tx)
+            .await?;
+            Ok(id)
+        }
+        CartOwner::Guest(token) => {
+            let id: Option<i64> = sqlx::query_scalar(
+                r#"
+                UPDATE carts SET update_time = CURRENT_TIMESTAMP WHERE guest_token = $1 RETURNING id
+            "#,
+            )
+            .bind(hash_token(token))
+            .fetch_optional(&mut **tx)
+            .await?;
+            id.ok_or_else(|| AppError::NotFound("cart".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_cart {
+    use super::*;
+    use crate::test_util::test_product;
+    use anyhow::Result;
+    use cmall_core::CartIssue;
+
+    #[tokio::test]
+    async fn test_guest_cart_should_merge_on_sign_in() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let detail = state.create_on_sale_product(3).await?;
+        let sku = detail.skus[0].clone();
+
+        // 校验失败时不创建购物车
+        let add = AddCartItem {
+            sku_id: sku.id,
+            quantity: 4,
+        };
+        let ret = state.add_guest_cart_item(None, &add).await;
+        assert!(matches!(ret, Err(AppError::InsufficientStock(_))));
+        let carts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM carts")
+            .fetch_one(&state.pool)
+            .await?;
+        assert_eq!(carts, 0);
+
+        let add = AddCartItem { quantity: 2, ..add };
+        let (token, cart) = state.add_guest_cart_item(None, &add).await?;
+        assert_eq!(cart.subtotal, 1000);
+        let guest = CartOwner::Guest(token.clone());
+        let ret = state
+            .add_cart_item(
+                &guest,
+                &AddCartItem {
+                    quantity: 4,
+                    ..add.clone()
+                },
+            )
+            .await;
+        assert!(matches!(ret, Err(AppError::InsufficientStock(_))));
+
+        let user = CartOwner::User(1);
+        state.add_cart_item(&user, &add).await?;
+        state.merge_guest_cart(&token, 1).await?;
+        assert_eq!(state.find_cart(&guest).await?, Cart::default());
+        // 合并后数量超过库存，提示库存不足且不计入小计
+        let cart = state.find_cart(&user).await?;
+        assert_eq!(cart.lines.len(), 1);
+        assert_eq!(cart.lines[0].item.quantity, 4);
+        assert_eq!(cart.subtotal, 0);
+
+        let update = UpdateCartItem { quantity: 3 };
+        state.update_cart_item(&user, sku.id, &update).await?;
+        let mut operate_sku = test_product("CUP").skus[0].clone();
+        operate_sku.price = 450;
+        state
+            .update_sku(detail.product.id, sku.id, &operate_sku)
+            .await?;
+        let cart = state.find_cart(&user).await?;
+        assert_eq!(cart.lines[0].issues, vec![CartIssue::PriceChanged]);
+        assert_eq!(cart.subtotal, 1350);
+
+        let cart = state.remove_cart_item(&user, sku.id).await?;
+        assert!(cart.lines.is_empty());
+
+        // 过期的游客购物车被清理，之后使用旧令牌加购会得到新的购物车
+        let add = AddCartItem { quantity: 1, ..add };
+        let (token, _) = state.add_guest_cart_item(None, &add).await?;
+        sqlx::query("UPDATE carts SET update_time = update_time - INTERVAL '31 days'")
+            .execute(&state.pool)
+            .await?;
+        assert_eq!(state.purge_expired_guest_carts().await?, 1);
+        let (new_token, cart) = state.add_guest_cart_item(Some(token.clone()), &add).await?;
+        assert_ne!(new_token, token);
+        assert_eq!(cart.total_quantity, 1);
+        Ok(())
+    }
+}
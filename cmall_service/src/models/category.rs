@@ -0,0 +1,451 @@
+use chrono::Utc;
+use cmall_core::{Category, CategoryNode, EffectStatus};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use crate::{error::AppError, AppState, Page, QueryFilter};
+
+const CATEGORY_COLUMNS: &str = "id, parent_id, name, icon, order_num, status, description, create_time, create_by, update_time, update_by";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateCategory {
+    pub parent_id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub icon: String,
+    pub order_num: i32,
+    pub status: EffectStatus,
+    #[serde(default)]
+    pub description: String,
+}
+
+// 移动到新的上级分类下的指定位置，位置从 1 开始
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveCategory {
+    pub parent_id: Option<i64>,
+    pub order_num: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCategory {
+    pub name: Option<String>,
+    pub parent_id: Option<i64>,
+    pub status: Option<EffectStatus>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+impl AppState {
+    pub async fn create_category(
+        &self,
+        input: &OperateCategory,
+        create_by: String,
+    ) -> Result<Category, AppError> {
+        if let Some(parent_id) = input.parent_id {
+            if self.find_category_by_id(parent_id).await?.is_none() {
+                return Err(AppError::NotFound(format!("category id {}", parent_id)));
+            }
+        }
+        let category = sqlx::query_as(&format!(
+            "INSERT INTO categories (parent_id, name, icon, order_num, status, description, create_by, update_by) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {}",
+            CATEGORY_COLUMNS
+        ))
+        .bind(input.parent_id)
+        .bind(&input.name)
+        .bind(&input.icon)
+        .bind(input.order_num)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(&create_by)
+        .bind(&create_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(category)
+    }
+
+    pub async fn update_category(
+        &self,
+        id: i64,
+        input: &OperateCategory,
+        update_by: String,
+    ) -> Result<Category, AppError> {
+        if self.find_category_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("category id {}", id)));
+        }
+        let mut tx = self.pool.begin().await?;
+        check_parent_category(&mut tx, id, input.parent_id).await?;
+        let category = sqlx::query_as(&format!(
+            "UPDATE categories SET parent_id = $1, name = $2, icon = $3, order_num = $4, status = $5, description = $6, update_by = $7, update_time = $8 WHERE id = $9 RETURNING {}",
+            CATEGORY_COLUMNS
+        ))
+        .bind(input.parent_id)
+        .bind(&input.name)
+        .bind(&input.icon)
+        .bind(input.order_num)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(update_by)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(category)
+    }
+
+    // 移动分类后重新编排新旧两级同级分类的 order_num，保持从 1 开始连续
+    pub async fn move_category(
+        &self,
+        id: i64,
+        input: &MoveCategory,
+        update_by: String,
+    ) -> Result<Category, AppError> {
+        let Some(category) = self.find_category_by_id(id).await? else {
+            return Err(AppError::NotFound(format!("category id {}", id)));
+        };
+        let mut tx = self.pool.begin().await?;
+        check_parent_category(&mut tx, id, input.parent_id).await?;
+        sqlx::query(
+            r#"
+            UPDATE categories SET parent_id = $1, update_by = $2, update_time = $3 WHERE id = $4
+        "#,
+        )
+        .bind(input.parent_id)
+        .bind(update_by)
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let mut siblings = find_sibling_ids(&mut tx, input.parent_id, id).await?;
+        let position = (input.order_num.max(1) as usize - 1).min(siblings.len());
+        siblings.insert(position, id);
+        renumber_categories(&mut tx, &siblings).await?;
+        if category.parent_id != input.parent_id {
+            let siblings = find_sibling_ids(&mut tx, category.parent_id, id).await?;
+            renumber_categories(&mut tx, &siblings).await?;
+        }
+        tx.commit().await?;
+
+        self.find_category_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("category id {}", id)))
+    }
+
+    pub async fn delete_category(&self, id: i64) -> Result<bool, AppError> {
+        if self.find_category_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("category id {}", id)));
+        }
+        let child_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM categories WHERE parent_id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if child_count > 0 {
+            return Err(AppError::CategoryHasChildren(id));
+        }
+        let product_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM products WHERE category_id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if product_count > 0 {
+            return Err(AppError::CategoryHasProducts(id));
+        }
+        let result = sqlx::query(
+            r#"
+            DELETE FROM categories WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find_category_by_id(&self, id: i64) -> Result<Option<Category>, AppError> {
+        let category = sqlx::query_as(&format!(
+            "SELECT {} FROM categories WHERE id = $1",
+            CATEGORY_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(category)
+    }
+
+    pub async fn find_category_by_condition(
+        &self,
+        input: &SearchCategory,
+        page: Page,
+    ) -> Result<(Vec<Category>, i64), AppError> {
+        QueryFilter::new()
+            .contains("name", input.name.as_deref())
+            .eq("parent_id", input.parent_id)
+            .eq("status", input.status.clone())
+            .fetch_page(
+                &self.pool,
+                CATEGORY_COLUMNS,
+                "categories",
+                "order_num, id",
+                page,
+            )
+            .await
+    }
+
+    pub async fn find_category_tree(
+        &self,
+        status: Option<EffectStatus>,
+    ) -> Result<Vec<CategoryNode>, AppError> {
+        let categories = QueryFilter::new()
+            .eq("status", status)
+            .fetch_all(&self.pool, CATEGORY_COLUMNS, "categories", "order_num, id")
+            .await?;
+        Ok(CategoryNode::build_tree(categories))
+    }
+
+    // 商城前台使用的分类树，停用分类及其下级分类都不返回
+    pub async fn find_public_category_tree(&self) -> Result<Vec<CategoryNode>, AppError> {
+        let categories = sqlx::query_as(&format!(
+            r#"
+            WITH RECURSIVE tree AS (
+                SELECT id FROM categories WHERE parent_id IS NULL AND status = 'enable'
+                UNION
+                SELECT c.id FROM categories c JOIN tree t ON c.parent_id = t.id WHERE c.status = 'enable'
+            )
+            SELECT {} FROM categories WHERE id IN (SELECT id FROM tree)
+        "#,
+            CATEGORY_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(CategoryNode::build_tree(categories))
+    }
+}
+
+// 上级分类必须存在，且不能是自身或自身的下级分类。
+// 修改上级分类的事务互斥执行，避免并发移动时各自通过检查后形成环
+async fn check_parent_category(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    parent_id: Option<i64>,
+) -> Result<(), AppError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('categories'))")
+        .execute(&mut **tx)
+        .await?;
+    let existed: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM categories WHERE id = $1
+    "#,
+    )
+    .bind(parent_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    if existed.is_none() {
+        return Err(AppError::NotFound(format!("category id {}", parent_id)));
+    }
+    let descendants: Vec<i64> = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE sub AS (
+            SELECT id FROM categories WHERE id = $1
+            UNION
+            SELECT c.id FROM categories c JOIN sub s ON c.parent_id = s.id
+        )
+        SELECT id FROM sub
+    "#,
+    )
+    .bind(id)
+    .fetch_all(&mut **tx)
+    .await?;
+    if descendants.contains(&parent_id) {
+        return Err(AppError::InvalidCategoryParent(parent_id));
+    }
+    Ok(())
+}
+
+// 按当前顺序取出同级分类，不包含 exclude
+async fn find_sibling_ids(
+    tx: &mut Transaction<'_, Postgres>,
+    parent_id: Option<i64>,
+    exclude: i64,
+) -> Result<Vec<i64>, AppError> {
+    let ids = sqlx::query_scalar(
+        r#"
+        SELECT id FROM categories WHERE parent_id IS NOT DISTINCT FROM $1 AND id <> $2 ORDER BY order_num, id
+    "#,
+    )
+    .bind(parent_id)
+    .bind(exclude)
+    .fetch_all(&mut **tx)
+    .await?;
+    Ok(ids)
+}
+
+async fn renumber_categories(
+    tx: &mut Transaction<'_, Postgres>,
+    ids: &[i64],
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE categories c SET order_num = s.order_num
+        FROM UNNEST($1::BIGINT[]) WITH ORDINALITY AS s(id, order_num)
+        WHERE c.id = s.id
+    "#,
+    )
+    .bind(ids)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_category {
+    use super::*;
+    use anyhow::Result;
+
+    fn operate(parent_id: Option<i64>, name: &str, order_num: i32) -> OperateCategory {
+        OperateCategory {
+            parent_id,
+            name: name.to_string(),
+            icon: "".to_string(),
+            order_num,
+            status: EffectStatus::Enable,
+            description: "".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_move_category_should_renumber_siblings() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let by = "admin".to_string();
+        let clothes = state
+            .create_category(&operate(None, "clothes", 1), by.clone())
+            .await?;
+        let shoes = state
+            .create_category(&operate(None, "shoes", 2), by.clone())
+            .await?;
+        let shirts = state
+            .create_category(&operate(Some(clothes.id), "shirts", 1), by.clone())
+            .await?;
+        let pants = state
+            .create_category(&operate(Some(clothes.id), "pants", 2), by.clone())
+            .await?;
+
+        // 把 shoes 移到 clothes 下的第一个位置
+        let input = MoveCategory {
+            parent_id: Some(clothes.id),
+            order_num: 1,
+        };
+        let moved = state.move_category(shoes.id, &input, by.clone()).await?;
+        assert_eq!(moved.parent_id, Some(clothes.id));
+        let tree = state.find_category_tree(None).await?;
+        assert_eq!(tree.len(), 1);
+        let children: Vec<(i64, i32)> = tree[0]
+            .children
+            .iter()
+            .map(|n| (n.category.id, n.category.order_num))
+            .collect();
+        assert_eq!(children, vec![(shoes.id, 1), (shirts.id, 2), (pants.id, 3)]);
+
+        let input = MoveCategory {
+            parent_id: Some(shirts.id),
+            order_num: 1,
+        };
+        let ret = state.move_category(clothes.id, &input, by).await;
+        assert!(matches!(ret, Err(AppError::InvalidCategoryParent(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_moves_should_not_create_cycle() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let by = "admin".to_string();
+        let a = state
+            .create_category(&operate(None, "a", 1), by.clone())
+            .await?;
+        let b = state
+            .create_category(&operate(None, "b", 2), by.clone())
+            .await?;
+
+        // 同时把 a 移到 b 下、把 b 移到 a 下，只能有一个成功
+        let tasks: Vec<_> = [(a.id, b.id), (b.id, a.id)]
+            .into_iter()
+            .map(|(id, parent_id)| {
+                let (state, by) = (state.clone(), by.clone());
+                tokio::spawn(async move {
+                    let input = MoveCategory {
+                        parent_id: Some(parent_id),
+                        order_num: 1,
+                    };
+                    state.move_category(id, &input, by).await
+                })
+            })
+            .collect();
+        let mut moved = 0;
+        for task in tasks {
+            match task.await? {
+                Ok(_) => moved += 1,
+                Err(e) => assert!(matches!(e, AppError::InvalidCategoryParent(_))),
+            }
+        }
+        assert_eq!(moved, 1);
+        assert_eq!(state.find_category_tree(None).await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_category_and_public_tree() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let by = "admin".to_string();
+        let clothes = state
+            .create_category(&operate(None, "clothes", 1), by.clone())
+            .await?;
+        let mut input = operate(Some(clothes.id), "shirts", 1);
+        let shirts = state.create_category(&input, by.clone()).await?;
+        assert!(matches!(
+            state.delete_category(clothes.id).await,
+            Err(AppError::CategoryHasChildren(_))
+        ));
+
+        // 停用的分类及其下级不出现在前台分类树中
+        input.status = EffectStatus::Disable;
+        state.update_category(shirts.id, &input, by.clone()).await?;
+        state
+            .create_category(&operate(Some(shirts.id), "polo", 1), by.clone())
+            .await?;
+        let tree = state.find_public_category_tree().await?;
+        assert_eq!(tree.len(), 1);
+        assert!(tree[0].children.is_empty());
+
+        sqlx::query("INSERT INTO products (category_id, name, price, create_by, update_by) VALUES ($1, 'shoe', 100, 'admin', 'admin')")
+            .bind(clothes.id)
+            .execute(&state.pool)
+            .await?;
+        let empty = state
+            .create_category(&operate(None, "empty", 2), by)
+            .await?;
+        assert!(state.delete_category(empty.id).await?);
+        let tree = state.find_category_tree(None).await?;
+        let leaf = tree[0].children[0].children[0].category.id;
+        assert!(state.delete_category(leaf).await?);
+        state.delete_category(shirts.id).await?;
+        assert!(matches!(
+            state.delete_category(clothes.id).await,
+            Err(AppError::CategoryHasProducts(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use cmall_core::{Department, DepartmentNode, EffectStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, AppState, ExportColumn, Exportable, Page, QueryFilter};
+
+const DEPARTMENT_COLUMNS: &str = "id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateDepartment {
+    pub parent_id: Option<i64>,
+    pub identifier: String,
+    pub name: String,
+    #[serde(default)]
+    pub order_num: i32,
+    pub description: String,
+    pub status: EffectStatus,
+}
+
+impl Exportable for Department {
+    const NAME: &'static str = "departments";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("id", "编号", "ID", |d: &Department| d.id.into()),
+            ExportColumn::new("parentId", "上级部门", "Parent ID", |d: &Department| {
+                d.parent_id.into()
+            }),
+            ExportColumn::new("identifier", "标识", "Identifier", |d: &Department| {
+                d.identifier.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("name", "名称", "Name", |d: &Department| {
+                d.name.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("orderNum", "排序", "Order", |d: &Department| {
+                d.order_num.into()
+            }),
+            ExportColumn::new("status", "状态", "Status", |d: &Department| {
+                d.status.to_string().into()
+            }),
+            ExportColumn::new("description", "描述", "Description", |d: &Department| {
+                d.description.as_str().into()
+            })
+            .width(30.0),
+            ExportColumn::new(
+                "createTime",
+                "创建时间",
+                "Create Time",
+                |d: &Department| d.create_time.into(),
+            )
+            .width(25.0),
+        ]
+    }
+}
+
+impl AppState {
+    pub async fn create_department(
+        &self,
+        input: &OperateDepartment,
+        create_by: String,
+    ) -> Result<Department, AppError> {
+        if self
+            .find_department_id_by_identifier(&input.identifier)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::DepartmentAlreadyExisted(input.identifier.clone()));
+        }
+        if let Some(parent_id) = input.parent_id {
+            if self.find_department_by_id(parent_id).await?.is_none() {
+                return Err(AppError::NotFound(format!("department id {}", parent_id)));
+            }
+        }
+        let dept = sqlx::query_as(
+            r#"
+            INSERT INTO departments (parent_id, identifier, name, order_num, status, description, create_by, update_by) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by
+        "#,
+        )
+        .bind(input.parent_id)
+        .bind(&input.identifier)
+        .bind(&input.name)
+        .bind(input.order_num)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(&create_by)
+        .bind(&create_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(dept)
+    }
+
+    pub async fn update_department(
+        &self,
+        id: i64,
+        input: &OperateDepartment,
+        update_by: String,
+    ) -> Result<Department, AppError> {
+        if self.find_department_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("department id {}", id)));
+        }
+        if let Some(dept_id) = self
+            .find_department_id_by_identifier(&input.identifier)
+            .await?
+        {
+            if dept_id != id {
+                return Err(AppError::DepartmentAlreadyExisted(input.identifier.clone()));
+            }
+        }
+        if let Some(parent_id) = input.parent_id {
+            if self.find_department_by_id(parent_id).await?.is_none() {
+                return Err(AppError::NotFound(format!("department id {}", parent_id)));
+            }
+            // 上级部门不能是自身或自身的下级部门
+            let descendants = self.find_department_descendant_ids(id).await?;
+            if descendants.contains(&parent_id) {
+                return Err(AppError::InvalidDepartmentParent(parent_id));
+            }
+        }
+        let dept = sqlx::query_as(
+            r#"
+            UPDATE departments SET parent_id = $1, identifier = $2, name = $3, order_num = $4, status = $5, description = $6, update_by = $7, update_time = $8 WHERE id = $9
+            RETURNING id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by
+        "#,
+        )
+        .bind(input.parent_id)
+        .bind(&input.identifier)
+        .bind(&input.name)
+        .bind(input.order_num)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(update_by)
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(dept)
+    }
+
+    pub async fn delete_department(&self, id: i64) -> Result<bool, AppError> {
+        if self.find_department_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("department id {}", id)));
+        }
+        let user_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM users WHERE dept_id = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if user_count > 0 {
+            return Err(AppError::DepartmentHasUsers(id));
+        }
+        let child_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM departments WHERE parent_id = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if child_count > 0 {
+            return Err(AppError::DepartmentHasChildren(id));
+        }
+        let result = sqlx::query(
+            r#"
+            UPDATE departments SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("department id {}", id)));
+        }
+        Ok(true)
+    }
+
+    // 恢复已软删除的部门，上级部门必须仍然存在
+    pub async fn restore_department(
+        &self,
+        id: i64,
+        update_by: String,
+    ) -> Result<Department, AppError> {
+        let parent_id: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT parent_id FROM departments WHERE id = $1 AND deleted_at IS NOT NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("deleted department id {}", id)))?;
+        if let Some(parent_id) = parent_id {
+            if self.find_department_by_id(parent_id).await?.is_none() {
+                return Err(AppError::NotFound(format!("department id {}", parent_id)));
+            }
+        }
+        let dept = sqlx::query_as(
+            r#"
+            UPDATE departments SET deleted_at = NULL, update_by = $1, update_time = $2 WHERE id = $3
+            RETURNING id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by
+        "#,
+        )
+        .bind(update_by)
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(dept)
+    }
+
+    pub async fn find_department_by_condition(
+        &self,
+        name: Option<&str>,
+        parent_id: Option<i64>,
+        status: Option<EffectStatus>,
+        page: Page,
+    ) -> Result<(Vec<Department>, i64), AppError> {
+        QueryFilter::new()
+            .raw("deleted_at IS NULL")
+            .eq("name", name.map(str::to_string))
+            .eq("parent_id", parent_id)
+            .eq("status", status)
+            .fetch_page(
+                &self.pool,
+                DEPARTMENT_COLUMNS,
+                "departments",
+                "order_num, id",
+                page,
+            )
+            .await
+    }
+
+    pub async fn find_department_tree(
+        &self,
+        status: Option<EffectStatus>,
+    ) -> Result<Vec<DepartmentNode>, AppError> {
+        let depts = sqlx::query_as(
+            r#"
+            SELECT id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by FROM departments
+            WHERE deleted_at IS NULL
+            AND (status = $1 OR $1 IS NULL)
+        "#,
+        )
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(DepartmentNode::build_tree(depts))
+    }
+
+    pub async fn find_department_by_id(&self, id: i64) -> Result<Option<Department>, AppError> {
+        let dept = sqlx::query_as(
+            r#"
+            SELECT id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by FROM departments WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(dept)
+    }
+
+    pub async fn find_department_by_identifier(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<Department>, AppError> {
+        let dept = sqlx::query_as(
+            r#"
+            SELECT id, parent_id, identifier, name, order_num, status, description, create_time, create_by, update_time, update_by FROM departments WHERE identifier = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(identifier)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(dept)
+    }
+
+    // 已软删除的部门仍占用编码，校验唯一性时一并查询
+    async fn find_department_id_by_identifier(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<i64>, AppError> {
+        let id = sqlx::query_scalar(
+            r#"
+            SELECT id FROM departments WHERE identifier = $1
+        "#,
+        )
+        .bind(identifier)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    // 查询部门自身及所有下级部门的 id
+    async fn find_department_descendant_ids(&self, id: i64) -> Result<Vec<i64>, AppError> {
+        let ids = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE sub AS (
+                SELECT id FROM departments WHERE id = $1
+                UNION
+                SELECT d.id FROM departments d JOIN sub ON d.parent_id = sub.id
+            )
+            SELECT id FROM sub
+        "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod test_department {
+    use super::*;
+    use anyhow::Result;
+
+    impl OperateDepartment {
+        fn new(identifier: &str, name: &str, parent_id: Option<i64>) -> Self {
+            Self {
+                parent_id,
+                identifier: identifier.to_string(),
+                name: name.to_string(),
+                order_num: 0,
+                description: "".to_string(),
+                status: EffectStatus::Enable,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_department_crud_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        let input = OperateDepartment::new("sales", "Sales", Some(1));
+        let dept = state.create_department(&input, "admin".into()).await?;
+        assert_eq!(dept.parent_id, Some(1));
+
+        let ret = state.create_department(&input, "admin".into()).await;
+        assert!(matches!(ret, Err(AppError::DepartmentAlreadyExisted(_))));
+
+        // a department can not be moved under itself
+        let input = OperateDepartment::new("sales", "Sales", Some(dept.id));
+        let ret = state
+            .update_department(dept.id, &input, "admin".into())
+            .await;
+        assert!(matches!(ret, Err(AppError::InvalidDepartmentParent(_))));
+
+        let tree = state.find_department_tree(None).await?;
+        assert_eq!(tree.len(), 1);
+        assert!(tree[0].children.iter().any(|n| n.department.id == dept.id));
+
+        assert!(state.delete_department(dept.id).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_department_with_users_should_fail() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        let ret = state.delete_department(1).await;
+        assert!(matches!(ret, Err(AppError::DepartmentHasUsers(1))));
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use chrono::{Duration, Utc};
+use cmall_core::{ExportJob, ExportStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{error::AppError, AppState, ExportFormat};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJobOutput {
+    #[serde(flatten)]
+    pub job: ExportJob,
+    // 任务完成后可用的下载地址
+    pub download_url: String,
+}
+
+impl ExportJobOutput {
+    pub fn new(job: ExportJob, prefix: &str) -> Self {
+        let download_url = format!("{}/{}/download", prefix, job.id);
+        Self { job, download_url }
+    }
+}
+
+impl AppState {
+    pub async fn create_export_job(
+        &self,
+        user_id: i64,
+        resource: &str,
+        file_name: &str,
+        params: &Value,
+    ) -> Result<ExportJob, AppError> {
+        self.purge_expired_export_jobs().await?;
+        let dir = self.export_dir();
+        fs::create_dir_all(&dir).await?;
+        let id = Uuid::now_v7().to_string();
+        // 文件的扩展名与下载时的文件名保持一致
+        let format = ExportFormat::from_file_name(file_name).unwrap_or_default();
+        let file_path = dir.join(format!("{}.{}", id, format.extension()));
+        let job = sqlx::query_as(
+            r#"
+            INSERT INTO export_jobs (id, user_id, resource, params, file_name, file_path) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, resource, status, file_name, file_path, row_count, error, create_time, finish_time
+        "#,
+        )
+        .bind(&id)
+        .bind(user_id)
+        .bind(resource)
+        .bind(params)
+        .bind(file_name)
+        .bind(file_path.to_string_lossy().as_ref())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(job)
+    }
+
+    // 只能查看自己创建的导出任务
+    pub async fn find_export_job(
+        &self,
+        id: &str,
+        user_id: i64,
+    ) -> Result<Option<ExportJob>, AppError> {
+        let job = sqlx::query_as(
+            r#"
+            SELECT id, user_id, resource, status, file_name, file_path, row_count, error, create_time, finish_time FROM export_jobs
+            WHERE id = $1 AND user_id = $2
+        "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(job)
+    }
+
+    pub(crate) async fn update_export_job(
+        &self,
+        id: &str,
+        status: ExportStatus,
+        row_count: i64,
+        error: Option<String>,
+    ) -> Result<(), AppError> {
+        let finish_time = match status {
+            ExportStatus::Done | ExportStatus::Failed => Some(Utc::now()),
+            _ => None,
+        };
+        sqlx::query(
+            r#"
+            UPDATE export_jobs SET status = $1, row_count = $2, error = $3, finish_time = $4 WHERE id = $5
+        "#,
+        )
+        .bind(status)
+        .bind(row_count)
+        .bind(error)
+        .bind(finish_time)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // 删除超过保留时长的导出任务和文件，在创建新任务时顺带清理
+    pub(crate) async fn purge_expired_export_jobs(&self) -> Result<u64, AppError> {
+        let before = Utc::now() - Duration::seconds(self.config.export.retention as i64);
+        let file_paths: Vec<String> = sqlx::query_scalar(
+            r#"
+            DELETE FROM export_jobs WHERE create_time < $1 RETURNING file_path
+        "#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await?;
+        for file_path in &file_paths {
+            // 失败的任务没有生成文件
+            match fs::remove_file(file_path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("remove export file {} error: {:?}", file_path, e),
+            }
+        }
+        if !file_paths.is_empty() {
+            info!("{} expired export jobs removed", file_paths.len());
+        }
+        Ok(file_paths.len() as u64)
+    }
+
+    fn export_dir(&self) -> PathBuf {
+        self.config.server.base_dir.join("exports")
+    }
+}
@@ -0,0 +1,573 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use cmall_core::{
+    plan_allocation, AllocationStrategy, Inventory, StockAllocation, StockLine, StockMovement,
+    StockMovementKind, WarehouseStock,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Postgres, Transaction};
+use tracing::info;
+
+use crate::{error::AppError, AppState, ExportColumn, Exportable, Page, QueryFilter};
+
+const INVENTORY_COLUMNS: &str = "warehouse_id, sku_id, on_hand, reserved, update_time";
+const MOVEMENT_COLUMNS: &str = "id, warehouse_id, sku_id, kind, quantity, reserved, balance, reason, reference, create_by, create_time";
+
+// 未指定阈值时，可售数量不超过该值即视为库存不足
+pub const DEFAULT_LOW_STOCK_THRESHOLD: i64 = 10;
+
+// 可售数量按 SKU 汇总所有启用的仓库，停用的 SKU 不统计
+const LOW_STOCK_FROM: &str = r#"(
+    SELECT s.id AS sku_id, s.sku_code, p.id AS product_id, p.name AS product_name,
+        COALESCE(SUM(i.on_hand), 0)::BIGINT AS on_hand, COALESCE(SUM(i.reserved), 0)::BIGINT AS reserved,
+        COALESCE(SUM(i.on_hand - i.reserved), 0)::BIGINT AS available
+    FROM product_skus s JOIN products p ON p.id = s.product_id
+    LEFT JOIN (inventories i JOIN warehouses w ON w.id = i.warehouse_id AND w.status = 'enable') ON i.sku_id = s.id
+    WHERE s.status = 'enable'
+    GROUP BY s.id, p.id
+) AS stock"#;
+
+// 手动调整库存，quantity 为变动量，可以为负
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjustStock {
+    pub warehouse_id: i64,
+    pub quantity: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiveStock {
+    pub warehouse_id: i64,
+    pub quantity: i64,
+    #[serde(default)]
+    pub reference: String,
+}
+
+// 客户退回的商品重新入库，reference 记录退货对应的订单或退货单号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReturnStock {
+    pub warehouse_id: i64,
+    pub quantity: i64,
+    pub reference: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMovement {
+    pub warehouse_id: Option<i64>,
+    pub kind: Option<StockMovementKind>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LowStockItem {
+    pub sku_id: i64,
+    pub sku_code: String,
+    pub product_id: i64,
+    pub product_name: String,
+    pub on_hand: i64,
+    pub reserved: i64,
+    pub available: i64,
+}
+
+impl Exportable for LowStockItem {
+    const NAME: &'static str = "low-stock";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("skuId", "SKU 编号", "SKU ID", |i: &LowStockItem| {
+                i.sku_id.into()
+            }),
+            ExportColumn::new("skuCode", "SKU 编码", "SKU Code", |i: &LowStockItem| {
+                i.sku_code.as_str().into()
+            })
+            .width(20.0),
+            ExportColumn::new("productName", "商品", "Product", |i: &LowStockItem| {
+                i.product_name.as_str().into()
+            })
+            .width(25.0),
+            ExportColumn::new("onHand", "在库", "On Hand", |i: &LowStockItem| {
+                i.on_hand.into()
+            }),
+            ExportColumn::new("reserved", "已预留", "Reserved", |i: &LowStockItem| {
+                i.reserved.into()
+            }),
+            ExportColumn::new("available", "可售", "Available", |i: &LowStockItem| {
+                i.available.into()
+            }),
+        ]
+    }
+}
+
+impl AppState {
+    // SKU 在各仓库的库存，SKU 不存在时返回 None
+    pub async fn find_sku_inventories(
+        &self,
+        sku_id: i64,
+    ) -> Result<Option<Vec<Inventory>>, AppError> {
+        let existed: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM product_skus WHERE id = $1)
+        "#,
+        )
+        .bind(sku_id)
+        .fetch_one(&self.pool)
+        .await?;
+        if !existed {
+            return Ok(None);
+        }
+        let inventories = sqlx::query_as(&format!(
+            "SELECT {} FROM inventories WHERE sku_id = $1 ORDER BY warehouse_id",
+            INVENTORY_COLUMNS
+        ))
+        .bind(sku_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Some(inventories))
+    }
+
+    pub async fn receive_stock(
+        &self,
+        sku_id: i64,
+        input: &ReceiveStock,
+        create_by: String,
+    ) -> Result<Inventory, AppError> {
+        let allocation = StockAllocation::new(input.warehouse_id, sku_id, input.quantity);
+        self.change_stock(
+            allocation,
+            StockMovementKind::Inbound,
+            "",
+            &input.reference,
+            &create_by,
+        )
+        .await
+    }
+
+    // 退货入库必须关联订单或退货单，便于从流水追溯
+    pub async fn return_stock(
+        &self,
+        sku_id: i64,
+        input: &ReturnStock,
+        create_by: String,
+    ) -> Result<Inventory, AppError> {
+        if input.reference.trim().is_empty() {
+            return Err(AppError::InvalidStock("reference is required".to_string()));
+        }
+        let allocation = StockAllocation::new(input.warehouse_id, sku_id, input.quantity);
+        self.change_stock(
+            allocation,
+            StockMovementKind::Return,
+            &input.reason,
+            &input.reference,
+            &create_by,
+        )
+        .await
+    }
+
+    // 盘点调整必须填写原因，调整后的在库数量不能少于已预留数量
+    pub async fn adjust_stock(
+        &self,
+        sku_id: i64,
+        input: &AdjustStock,
+        create_by: String,
+    ) -> Result<Inventory, AppError> {
+        if input.reason.trim().is_empty() {
+            return Err(AppError::InvalidStock("reason is required".to_string()));
+        }
+        let allocation = StockAllocation::new(input.warehouse_id, sku_id, input.quantity);
+        self.change_stock(
+            allocation,
+            StockMovementKind::Adjustment,
+            &input.reason,
+            "",
+            &create_by,
+        )
+        .await
+    }
+
+    // 按配置的策略从各仓库分配并预留库存，返回分配结果用于后续出库或释放
+    pub async fn reserve_stock(
+        &self,
+        lines: &[StockLine],
+        reference: &str,
+        create_by: &str,
+    ) -> Result<Vec<StockAllocation>, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let allocations = allocate_stock(&mut tx, lines, self.config.inventory.allocation).await?;
+        apply_stock_changes(
+            &mut tx,
+            &allocations,
+            StockMovementKind::Reserve,
+            "",
+            reference,
+            create_by,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(allocations)
+    }
+
+    pub async fn find_stock_movements(
+        &self,
+        sku_id: i64,
+        input: &SearchMovement,
+        page: Page,
+    ) -> Result<(Vec<StockMovement>, i64), AppError> {
+        QueryFilter::new()
+            .eq("sku_id", Some(sku_id))
+            .eq("warehouse_id", input.warehouse_id)
+            .eq("kind", input.kind)
+            .fetch_page(
+                &self.pool,
+                MOVEMENT_COLUMNS,
+                "stock_movements",
+                "id DESC",
+                page,
+            )
+            .await
+    }
+
+    pub async fn find_low_stock(
+        &self,
+        threshold: i64,
+        page: Page,
+    ) -> Result<(Vec<LowStockItem>, i64), AppError> {
+        QueryFilter::new()
+            .bind("available <= ", Some(threshold), "")
+            .fetch_page(
+                &self.pool,
+                "sku_id, sku_code, product_id, product_name, on_hand, reserved, available",
+                LOW_STOCK_FROM,
+                "available, sku_id",
+                page,
+            )
+            .await
+    }
+
+    async fn change_stock(
+        &self,
+        allocation: StockAllocation,
+        kind: StockMovementKind,
+        reason: &str,
+        reference: &str,
+        create_by: &str,
+    ) -> Result<Inventory, AppError> {
+        if self
+            .find_sku_inventories(allocation.sku_id)
+            .await?
+            .is_none()
+        {
+            return Err(AppError::NotFound(format!("sku id {}", allocation.sku_id)));
+        }
+        if self
+            .find_warehouse_by_id(allocation.warehouse_id)
+            .await?
+            .is_none()
+        {
+            return Err(AppError::NotFound(format!(
+                "warehouse id {}",
+                allocation.warehouse_id
+            )));
+        }
+        let mut tx = self.pool.begin().await?;
+        let mut inventories =
+            apply_stock_changes(&mut tx, &[allocation], kind, reason, reference, create_by).await?;
+        tx.commit().await?;
+        info!(
+            "{} stock of sku {} in warehouse {}: {}",
+            kind, allocation.sku_id, allocation.warehouse_id, allocation.quantity
+        );
+        Ok(inventories.remove(0))
+    }
+}
+
+// 锁定相关库存后计算分配方案，并发下单时后到的事务会等待并看到最新的可售数量
+pub(crate) async fn allocate_stock(
+    tx: &mut Transaction<'_, Postgres>,
+    lines: &[StockLine],
+    strategy: AllocationStrategy,
+) -> Result<Vec<StockAllocation>, AppError> {
+    if let Some(line) = lines.iter().find(|l| l.quantity <= 0) {
+        return Err(AppError::InvalidStock(format!(
+            "invalid quantity {} for sku {}",
+            line.quantity, line.sku_id
+        )));
+    }
+    let sku_ids: Vec<i64> = lines.iter().map(|l| l.sku_id).collect();
+    let stock: Vec<WarehouseStock> = sqlx::query_as(
+        r#"
+        SELECT i.warehouse_id, w.priority, i.sku_id, i.on_hand - i.reserved AS available
+        FROM inventories i JOIN warehouses w ON w.id = i.warehouse_id
+        WHERE i.sku_id = ANY($1) AND w.status = 'enable'
+        ORDER BY i.warehouse_id, i.sku_id
+        FOR UPDATE OF i
+    "#,
+    )
+    .bind(&sku_ids)
+    .fetch_all(&mut **tx)
+    .await?;
+    plan_allocation(strategy, &stock, lines).map_err(AppError::InsufficientStock)
+}
+
+// 在同一事务中修改库存并写入流水。按 (warehouse_id, sku_id) 顺序加锁避免死锁，
+// 条件更新保证可售数量不会小于零
+pub(crate) async fn apply_stock_changes(
+    tx: &mut Transaction<'_, Postgres>,
+    allocations: &[StockAllocation],
+    kind: StockMovementKind,
+    reason: &str,
+    reference: &str,
+    create_by: &str,
+) -> Result<Vec<Inventory>, AppError> {
+    let mut merged: BTreeMap<(i64, i64), i64> = BTreeMap::new();
+    for allocation in allocations {
+        *merged
+            .entry((allocation.warehouse_id, allocation.sku_id))
+            .or_default() += allocation.quantity;
+    }
+    let mut inventories = Vec::with_capacity(merged.len());
+    for ((warehouse_id, sku_id), quantity) in merged {
+        let valid = match kind {
+            StockMovementKind::Adjustment => quantity != 0,
+            _ => quantity > 0,
+        };
+        if !valid {
+            return Err(AppError::InvalidStock(format!(
+                "invalid quantity {} for sku {}",
+                quantity, sku_id
+            )));
+        }
+        let (on_hand, reserved) = kind.deltas(quantity);
+        if on_hand > 0 {
+            sqlx::query(
+                r#"
+                INSERT INTO inventories (warehouse_id, sku_id) VALUES ($1, $2) ON CONFLICT (warehouse_id, sku_id) DO NOTHING
+            "#,
+            )
+            .bind(warehouse_id)
+            .bind(sku_id)
+            .execute(&mut **tx)
+            .await?;
+        }
+        let inventory: Option<Inventory> = sqlx::query_as(&format!(
+            r#"
+            UPDATE inventories SET on_hand = on_hand + $3, reserved = reserved + $4, update_time = $5
+            WHERE warehouse_id = $1 AND sku_id = $2 AND reserved + $4 >= 0 AND on_hand + $3 >= reserved + $4
+            RETURNING {}
+        "#,
+            INVENTORY_COLUMNS
+        ))
+        .bind(warehouse_id)
+        .bind(sku_id)
+        .bind(on_hand)
+        .bind(reserved)
+        .bind(Utc::now())
+        .fetch_optional(&mut **tx)
+        .await?;
+        let inventory = inventory.ok_or(AppError::InsufficientStock(sku_id))?;
+        sqlx::query(
+            r#"
+            INSERT INTO stock_movements (warehouse_id, sku_id, kind, quantity, reserved, balance, reason, reference, create_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        )
+        .bind(warehouse_id)
+        .bind(sku_id)
+        .bind(kind)
+        .bind(on_hand)
+        .bind(reserved)
+        .bind(inventory.on_hand)
+        .bind(reason)
+        .bind(reference)
+        .bind(create_by)
+        .execute(&mut **tx)
+        .await?;
+        inventories.push(inventory);
+    }
+    Ok(inventories)
+}
+
+#[cfg(test)]
+mod test_inventory {
+    use super::*;
+    use crate::test_util::test_product;
+    use anyhow::Result;
+
+    // 迁移脚本创建的默认仓库
+    const DEFAULT_WAREHOUSE: i64 = 1;
+
+    async fn create_sku(state: &AppState, code: &str) -> Result<i64> {
+        let detail = state
+            .create_product(&test_product(code), "admin".to_string())
+            .await?;
+        Ok(detail.skus[0].id)
+    }
+
+    fn receive(warehouse_id: i64, quantity: i64) -> ReceiveStock {
+        ReceiveStock {
+            warehouse_id,
+            quantity,
+            reference: "PO-1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stock_changes_should_write_ledger() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = create_sku(&state, "SKU-1").await?;
+        let by = "admin".to_string();
+
+        state
+            .receive_stock(sku_id, &receive(DEFAULT_WAREHOUSE, 10), by.clone())
+            .await?;
+        state
+            .reserve_stock(&[StockLine::new(sku_id, 4)], "SO-1", &by)
+            .await?;
+        let ret = state
+            .reserve_stock(&[StockLine::new(sku_id, 7)], "SO-2", &by)
+            .await;
+        assert!(matches!(ret, Err(AppError::InsufficientStock(_))));
+
+        // 已预留 4 件时最多只能盘亏 6 件
+        let adjust = AdjustStock {
+            warehouse_id: DEFAULT_WAREHOUSE,
+            quantity: -7,
+            reason: "damaged".to_string(),
+        };
+        let ret = state.adjust_stock(sku_id, &adjust, by.clone()).await;
+        assert!(matches!(ret, Err(AppError::InsufficientStock(_))));
+        let adjust = AdjustStock {
+            quantity: -6,
+            ..adjust
+        };
+        let inventory = state.adjust_stock(sku_id, &adjust, by.clone()).await?;
+        assert_eq!((inventory.on_hand, inventory.reserved), (4, 4));
+        assert_eq!(inventory.available(), 0);
+
+        let page = Page::new(1, 10)?;
+        let (movements, total) = state
+            .find_stock_movements(sku_id, &SearchMovement::default(), page)
+            .await?;
+        // 失败的操作不会写入流水
+        assert_eq!(total, 3);
+        assert_eq!(movements[0].kind, StockMovementKind::Adjustment);
+        assert_eq!(movements[0].balance, 4);
+        assert_eq!(movements[2].reference, "PO-1");
+
+        let (items, _) = state.find_low_stock(0, page).await?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].sku_code, "SKU-1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_return_stock_should_write_ledger() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = create_sku(&state, "SKU-4").await?;
+        let by = "admin".to_string();
+
+        let input = ReturnStock {
+            warehouse_id: DEFAULT_WAREHOUSE,
+            quantity: 2,
+            reference: " ".to_string(),
+            reason: "damaged box".to_string(),
+        };
+        let ret = state.return_stock(sku_id, &input, by.clone()).await;
+        assert!(matches!(ret, Err(AppError::InvalidStock(_))));
+        let input = ReturnStock {
+            reference: "SO-1".to_string(),
+            ..input
+        };
+        let inventory = state.return_stock(sku_id, &input, by.clone()).await?;
+        assert_eq!((inventory.on_hand, inventory.reserved), (2, 0));
+
+        let search = SearchMovement {
+            kind: Some(StockMovementKind::Return),
+            ..SearchMovement::default()
+        };
+        let (movements, total) = state
+            .find_stock_movements(sku_id, &search, Page::new(1, 10)?)
+            .await?;
+        assert_eq!(total, 1);
+        assert_eq!(movements[0].quantity, 2);
+        assert_eq!(movements[0].reference, "SO-1");
+        assert_eq!(movements[0].reason, "damaged box");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reservations_should_not_oversell() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = create_sku(&state, "SKU-2").await?;
+        state
+            .receive_stock(sku_id, &receive(DEFAULT_WAREHOUSE, 5), "admin".to_string())
+            .await?;
+
+        let tasks: Vec<_> = (0..20)
+            .map(|i| {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let reference = format!("SO-{}", i);
+                    state
+                        .reserve_stock(&[StockLine::new(sku_id, 1)], &reference, "buyer")
+                        .await
+                })
+            })
+            .collect();
+        let mut reserved = 0;
+        for task in tasks {
+            if task.await?.is_ok() {
+                reserved += 1;
+            }
+        }
+        assert_eq!(reserved, 5);
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        assert_eq!((inventories[0].on_hand, inventories[0].reserved), (5, 5));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sku_with_stock_should_not_be_deleted() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = create_sku(&state, "SKU-3").await?;
+        let product_id: i64 =
+            sqlx::query_scalar("SELECT product_id FROM product_skus WHERE id = $1")
+                .bind(sku_id)
+                .fetch_one(&state.pool)
+                .await?;
+        let by = "admin".to_string();
+        state
+            .receive_stock(sku_id, &receive(DEFAULT_WAREHOUSE, 2), by.clone())
+            .await?;
+        let ret = state.delete_sku(product_id, sku_id).await;
+        assert!(matches!(ret, Err(AppError::SkuHasStock(id)) if id == sku_id));
+        let ret = state.delete_product(product_id).await;
+        assert!(matches!(ret, Err(AppError::SkuHasStock(_))));
+
+        // 盘亏到零后可以删除，库存流水仍然保留
+        let adjust = AdjustStock {
+            warehouse_id: DEFAULT_WAREHOUSE,
+            quantity: -2,
+            reason: "scrapped".to_string(),
+        };
+        state.adjust_stock(sku_id, &adjust, by).await?;
+        assert!(state.delete_sku(product_id, sku_id).await?);
+        let movements: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM stock_movements WHERE sku_id = $1")
+                .bind(sku_id)
+                .fetch_one(&state.pool)
+                .await?;
+        assert_eq!(movements, 2);
+        Ok(())
+    }
+}
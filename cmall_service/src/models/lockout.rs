@@ -0,0 +1,192 @@
+use chrono::{DateTime, Duration, Utc};
+use cmall_core::User;
+
+use crate::{error::AppError, AppState};
+
+impl AppState {
+    // 登录前检查客户端地址和账号是否处于锁定状态
+    pub async fn check_signin_allowed(&self, email: &str, ip: &str) -> Result<(), AppError> {
+        let now = Utc::now();
+        let lockout = &self.config.auth.lockout;
+        let window_start = now - Duration::seconds(lockout.window as i64);
+        let (count, first): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), MIN(create_time) FROM signin_failures WHERE ip = $1 AND create_time > $2
+        "#,
+        )
+        .bind(ip)
+        .bind(window_start)
+        .fetch_one(&self.pool)
+        .await?;
+        if let Some(first) = first {
+            if count >= lockout.ip_max_attempts {
+                let until = first + Duration::seconds(lockout.window as i64);
+                return Err(AppError::TooManyAttempts(retry_after(until, now)));
+            }
+        }
+
+        let locked_until: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            SELECT locked_until FROM users WHERE email = $1
+        "#,
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten();
+        match locked_until {
+            Some(until) if until > now => Err(AppError::TooManyAttempts(retry_after(until, now))),
+            _ => Ok(()),
+        }
+    }
+
+    // 记录一次登录失败，账号连续失败达到阈值时锁定并返回需要等待的秒数
+    pub async fn record_signin_failure(
+        &self,
+        email: &str,
+        ip: &str,
+    ) -> Result<Option<u64>, AppError> {
+        let now = Utc::now();
+        let lockout = &self.config.auth.lockout;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM signin_failures WHERE create_time <= $1
+        "#,
+        )
+        .bind(now - Duration::seconds(lockout.window as i64))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO signin_failures (ip, email, create_time) VALUES ($1, $2, $3)
+        "#,
+        )
+        .bind(ip)
+        .bind(email)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        // 上一次锁定已过期时重新计数
+        sqlx::query(
+            r#"
+            UPDATE users SET failed_attempts = 0, locked_until = NULL WHERE email = $1 AND locked_until <= $2
+        "#,
+        )
+        .bind(email)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        let locked: Option<(i64, Option<DateTime<Utc>>)> = sqlx::query_as(
+            r#"
+            UPDATE users SET failed_attempts = failed_attempts + 1,
+            locked_until = CASE WHEN failed_attempts + 1 >= $1 THEN $2 ELSE NULL END
+            WHERE email = $3
+            RETURNING id, locked_until
+        "#,
+        )
+        .bind(lockout.max_attempts)
+        .bind(now + Duration::seconds(lockout.lock_duration as i64))
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        match locked {
+            Some((id, locked_until)) => {
+                self.user_cache.remove(&id);
+                Ok(locked_until.map(|until| retry_after(until, now)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // 登录成功后清空账号的失败次数
+    pub async fn reset_signin_failures(&self, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users SET failed_attempts = 0, locked_until = NULL WHERE id = $1 AND (failed_attempts > 0 OR locked_until IS NOT NULL)
+        "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() > 0 {
+            self.user_cache.remove(&id);
+        }
+        Ok(())
+    }
+
+    // 管理员解锁账号
+    pub async fn unlock_user(&self, id: i64) -> Result<User, AppError> {
+        if self.find_user_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("user id {}", id)));
+        }
+        self.reset_signin_failures(id).await?;
+        self.find_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))
+    }
+}
+
+fn retry_after(until: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    (until - now).num_seconds().max(1) as u64
+}
+
+#[cfg(test)]
+mod test_lockout {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_account_should_lock_after_failures() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        let max_attempts = state.config.auth.lockout.max_attempts;
+
+        for i in 1..max_attempts {
+            let ip = format!("10.0.0.{}", i);
+            state.check_signin_allowed(&user.email, &ip).await?;
+            assert!(state
+                .record_signin_failure(&user.email, &ip)
+                .await?
+                .is_none());
+        }
+        let retry_after = state.record_signin_failure(&user.email, "10.0.1.1").await?;
+        assert!(retry_after.is_some());
+        let ret = state.check_signin_allowed(&user.email, "10.0.1.2").await;
+        assert!(matches!(ret, Err(AppError::TooManyAttempts(_))));
+
+        let user = state.find_user_by_id(1).await?.unwrap();
+        assert!(user.is_locked());
+        assert_eq!(user.failed_attempts, max_attempts);
+
+        let user = state.unlock_user(1).await?;
+        assert!(!user.is_locked());
+        assert_eq!(user.failed_attempts, 0);
+        state.check_signin_allowed(&user.email, "10.0.1.2").await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ip_should_be_throttled() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let ip_max_attempts = state.config.auth.lockout.ip_max_attempts;
+
+        for i in 0..ip_max_attempts {
+            let email = format!("nobody{}@example.com", i);
+            state.record_signin_failure(&email, "10.0.0.1").await?;
+        }
+        let ret = state.check_signin_allowed("elixy@qq.com", "10.0.0.1").await;
+        assert!(matches!(ret, Err(AppError::TooManyAttempts(secs)) if secs > 0));
+        state
+            .check_signin_allowed("elixy@qq.com", "10.0.0.2")
+            .await?;
+
+        // 超长的邮箱和伪造的转发地址同样只记为一次失败
+        let email = format!("{}@example.com", "a".repeat(200));
+        let ip = "10.0.0.3, ".repeat(50);
+        assert_eq!(state.record_signin_failure(&email, &ip).await?, None);
+        state.check_signin_allowed(&email, &ip).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+use cmall_core::{EffectStatus, Menu, MenuNode, MenuType, ROOT_MENU_ID};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, AppState, ExportColumn, Exportable, Page, QueryFilter};
+
+const MENU_COLUMNS: &str = "id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateMenu {
+    pub menu_id: String,
+    pub path: String,
+    pub chinese_name: String,
+    pub english_name: String,
+    #[serde(default)]
+    pub icon: String,
+    pub order_num: i32,
+    #[serde(rename = "type")]
+    pub menu_type: MenuType,
+    #[serde(default = "default_parent_menu_id")]
+    pub parent_menu_id: String,
+    pub status: EffectStatus,
+    pub description: String,
+}
+
+fn default_parent_menu_id() -> String {
+    ROOT_MENU_ID.to_string()
+}
+
+impl Exportable for Menu {
+    const NAME: &'static str = "menus";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("id", "编号", "ID", |m: &Menu| m.id.into()),
+            ExportColumn::new("menuId", "菜单标识", "Menu ID", |m: &Menu| {
+                m.menu_id.as_str().into()
+            })
+            .width(20.0),
+            ExportColumn::new(
+                "parentMenuId",
+                "上级菜单",
+                "Parent Menu ID",
+                |m: &Menu| m.parent_menu_id.as_str().into(),
+            )
+            .width(20.0),
+            ExportColumn::new("chineseName", "中文名称", "Chinese Name", |m: &Menu| {
+                m.chinese_name.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("englishName", "英文名称", "English Name", |m: &Menu| {
+                m.english_name.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("type", "类型", "Type", |m: &Menu| {
+                m.menu_type.to_string().into()
+            }),
+            ExportColumn::new("path", "路径", "Path", |m: &Menu| m.path.as_str().into())
+                .width(25.0),
+            ExportColumn::new("orderNum", "排序", "Order", |m: &Menu| m.order_num.into()),
+            ExportColumn::new("status", "状态", "Status", |m: &Menu| {
+                m.status.to_string().into()
+            }),
+            ExportColumn::new("createTime", "创建时间", "Create Time", |m: &Menu| {
+                m.create_time.into()
+            })
+            .width(25.0),
+        ]
+    }
+}
+
+impl AppState {
+    pub async fn create_menu(
+        &self,
+        input: &OperateMenu,
+        create_by: String,
+    ) -> Result<Menu, AppError> {
+        if self.find_id_by_menu_id(&input.menu_id).await?.is_some() {
+            return Err(AppError::MenuAlreadyExisted(input.menu_id.clone()));
+        }
+        self.check_parent_menu(&input.parent_menu_id).await?;
+        let menu = sqlx::query_as(
+            r#"
+            INSERT INTO menus (menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by
+        "#,
+        )
+        .bind(&input.menu_id)
+        .bind(&input.path)
+        .bind(&input.chinese_name)
+        .bind(&input.english_name)
+        .bind(&input.icon)
+        .bind(input.order_num)
+        .bind(&input.menu_type)
+        .bind(&input.parent_menu_id)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(&create_by)
+        .bind(&create_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(menu)
+    }
+
+    pub async fn update_menu(
+        &self,
+        id: i64,
+        input: &OperateMenu,
+        update_by: String,
+    ) -> Result<Menu, AppError> {
+        let menu = match self.find_menu_by_id(id).await? {
+            Some(menu) => menu,
+            None => return Err(AppError::NotFound(format!("menu id {}", id))),
+        };
+        if let Some(other_id) = self.find_id_by_menu_id(&input.menu_id).await? {
+            if other_id != id {
+                return Err(AppError::MenuAlreadyExisted(input.menu_id.clone()));
+            }
+        }
+        // 上级菜单不能是自身或自身的下级菜单
+        let descendants = self.find_menu_descendant_ids(&menu.menu_id).await?;
+        if input.parent_menu_id == input.menu_id || descendants.contains(&input.parent_menu_id) {
+            return Err(AppError::InvalidMenuParent(input.parent_menu_id.clone()));
+        }
+        self.check_parent_menu(&input.parent_menu_id).await?;
+
+        let mut tx = self.pool.begin().await?;
+        let updated: Menu = sqlx::query_as(
+            r#"
+            UPDATE menus SET menu_id = $1, path = $2, chinese_name = $3, english_name = $4, icon = $5, order_num = $6, type = $7, parent_menu_id = $8, status = $9, description = $10, update_by = $11, update_time = $12
+            WHERE id = $13
+            RETURNING id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by
+        "#,
+        )
+        .bind(&input.menu_id)
+        .bind(&input.path)
+        .bind(&input.chinese_name)
+        .bind(&input.english_name)
+        .bind(&input.icon)
+        .bind(input.order_num)
+        .bind(&input.menu_type)
+        .bind(&input.parent_menu_id)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(update_by)
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        // menu_id 变更时同步子菜单的 parent_menu_id
+        if updated.menu_id != menu.menu_id {
+            sqlx::query(
+                r#"
+                UPDATE menus SET parent_menu_id = $1 WHERE parent_menu_id = $2
+            "#,
+            )
+            .bind(&updated.menu_id)
+            .bind(&menu.menu_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    pub async fn delete_menu(&self, id: i64) -> Result<bool, AppError> {
+        let menu = match self.find_menu_by_id(id).await? {
+            Some(menu) => menu,
+            None => return Err(AppError::NotFound(format!("menu id {}", id))),
+        };
+        let child_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM menus WHERE parent_menu_id = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(&menu.menu_id)
+        .fetch_one(&self.pool)
+        .await?;
+        if child_count > 0 {
+            return Err(AppError::MenuHasChildren(menu.menu_id));
+        }
+        let result = sqlx::query(
+            r#"
+            UPDATE menus SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("menu id {}", id)));
+        }
+        Ok(true)
+    }
+
+    // 恢复已软删除的菜单，上级菜单必须仍然存在
+    pub async fn restore_menu(&self, id: i64, update_by: String) -> Result<Menu, AppError> {
+        let parent_menu_id: String = sqlx::query_scalar(
+            r#"
+            SELECT parent_menu_id FROM menus WHERE id = $1 AND deleted_at IS NOT NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("deleted menu id {}", id)))?;
+        self.check_parent_menu(&parent_menu_id).await?;
+        let menu = sqlx::query_as(
+            r#"
+            UPDATE menus SET deleted_at = NULL, update_by = $1, update_time = $2 WHERE id = $3
+            RETURNING id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by
+        "#,
+        )
+        .bind(update_by)
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(menu)
+    }
+
+    pub async fn find_menu_by_condition(
+        &self,
+        name: Option<&str>,
+        menu_type: Option<MenuType>,
+        status: Option<EffectStatus>,
+        page: Page,
+    ) -> Result<(Vec<Menu>, i64), AppError> {
+        QueryFilter::new()
+            .raw("deleted_at IS NULL")
+            .eq_any(&["chinese_name", "english_name"], name.map(str::to_string))
+            .eq("type", menu_type)
+            .eq("status", status)
+            .fetch_page(&self.pool, MENU_COLUMNS, "menus", "order_num, id", page)
+            .await
+    }
+
+    pub async fn find_menu_tree(
+        &self,
+        status: Option<EffectStatus>,
+    ) -> Result<Vec<MenuNode>, AppError> {
+        let menus = sqlx::query_as(
+            r#"
+            SELECT id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by FROM menus
+            WHERE deleted_at IS NULL
+            AND (status = $1 OR $1 IS NULL)
+        "#,
+        )
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(MenuNode::build_tree(menus))
+    }
+
+    pub async fn find_menu_by_id(&self, id: i64) -> Result<Option<Menu>, AppError> {
+        let menu = sqlx::query_as(
+            r#"
+            SELECT id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by FROM menus WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(menu)
+    }
+
+    pub async fn find_menu_by_menu_id(&self, menu_id: &str) -> Result<Option<Menu>, AppError> {
+        let menu = sqlx::query_as(
+            r#"
+            SELECT id, menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_time, create_by, update_time, update_by FROM menus WHERE menu_id = $1 AND deleted_at IS NULL
+        "#,
+        )
+        .bind(menu_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(menu)
+    }
+
+    // 已软删除的菜单仍占用 menu_id，校验唯一性时一并查询
+    async fn find_id_by_menu_id(&self, menu_id: &str) -> Result<Option<i64>, AppError> {
+        let id = sqlx::query_scalar(
+            r#"
+            SELECT id FROM menus WHERE menu_id = $1
+        "#,
+        )
+        .bind(menu_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    // 查询菜单自身及所有下级菜单的 menu_id
+    async fn find_menu_descendant_ids(&self, menu_id: &str) -> Result<Vec<String>, AppError> {
+        let ids = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE sub AS (
+                SELECT menu_id FROM menus WHERE menu_id = $1
+                UNION
+                SELECT m.menu_id FROM menus m JOIN sub ON m.parent_menu_id = sub.menu_id
+            )
+            SELECT menu_id FROM sub
+        "#,
+        )
+        .bind(menu_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ids)
+    }
+
+    async fn check_parent_menu(&self, parent_menu_id: &str) -> Result<(), AppError> {
+        if parent_menu_id == ROOT_MENU_ID {
+            return Ok(());
+        }
+        match self.find_menu_by_menu_id(parent_menu_id).await? {
+            // 按钮下不能再挂子菜单
+            Some(parent) if parent.menu_type == MenuType::Button => {
+                Err(AppError::InvalidMenuParent(parent_menu_id.to_string()))
+            }
+            Some(_) => Ok(()),
+            None => Err(AppError::NotFound(format!("menu {}", parent_menu_id))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_menu {
+    use super::*;
+    use anyhow::Result;
+
+    impl OperateMenu {
+        fn new(menu_id: &str, parent_menu_id: &str, menu_type: MenuType, order_num: i32) -> Self {
+            Self {
+                menu_id: menu_id.to_string(),
+                path: format!("/{}", menu_id),
+                chinese_name: menu_id.to_string(),
+                english_name: menu_id.to_string(),
+                icon: "".to_string(),
+                order_num,
+                menu_type,
+                parent_menu_id: parent_menu_id.to_string(),
+                status: EffectStatus::Enable,
+                description: "".to_string(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_menu_tree_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        let input = OperateMenu::new("reports", ROOT_MENU_ID, MenuType::Menu, 9);
+        let reports = state.create_menu(&input, "admin".into()).await?;
+        let input = OperateMenu::new("reports:export", "reports", MenuType::Button, 0);
+        let button = state.create_menu(&input, "admin".into()).await?;
+
+        let input = OperateMenu::new("reports:nested", "reports:export", MenuType::Menu, 0);
+        let ret = state.create_menu(&input, "admin".into()).await;
+        assert!(matches!(ret, Err(AppError::InvalidMenuParent(_))));
+
+        let tree = state.find_menu_tree(None).await?;
+        let node = tree
+            .iter()
+            .find(|n| n.menu.id == reports.id)
+            .expect("reports menu should be a root node");
+        assert_eq!(node.children[0].menu.id, button.id);
+
+        let ret = state.delete_menu(reports.id).await;
+        assert!(matches!(ret, Err(AppError::MenuHasChildren(_))));
+        assert!(state.delete_menu(button.id).await?);
+        assert!(state.delete_menu(reports.id).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+use chrono::{DateTime, Duration, Utc};
+use cmall_core::User;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use super::session::{generate_token, hash_token};
+use crate::{error::AppError, AppState};
+
+const MFA_ISSUER: &str = "Cmall";
+const MFA_DIGITS: usize = 6;
+const MFA_STEP: u64 = 30;
+// 密码验证通过后，完成第二步验证的时限（秒）
+const MFA_CHALLENGE_EXPIRES_IN: u64 = 5 * 60;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaCode {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaSignin {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct UserMfa {
+    secret: String,
+    enabled_at: Option<DateTime<Utc>>,
+    last_used_step: i64,
+}
+
+impl AppState {
+    // 生成新的 TOTP 密钥，验证通过前不会生效
+    pub async fn setup_mfa(&self, user: &User) -> Result<MfaSetup, AppError> {
+        if user.mfa_enabled {
+            return Err(AppError::MfaAlreadyEnabled(user.id));
+        }
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let totp = build_totp(&secret, &user.email)?;
+        sqlx::query(
+            r#"
+            INSERT INTO user_mfa (user_id, secret) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET secret = $2, enabled_at = NULL, last_used_step = 0, update_time = $3
+        "#,
+        )
+        .bind(user.id)
+        .bind(&secret)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(MfaSetup {
+            secret,
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    // 使用验证器生成的验证码确认绑定，同时生成恢复码
+    pub async fn enable_mfa(&self, user: &User, code: &str) -> Result<RecoveryCodes, AppError> {
+        let Some(mfa) = self.find_user_mfa(user.id).await? else {
+            return Err(AppError::MfaNotEnrolled(user.id));
+        };
+        if mfa.enabled_at.is_some() {
+            return Err(AppError::MfaAlreadyEnabled(user.id));
+        }
+        let totp = build_totp(&mfa.secret, &user.email)?;
+        let Some(step) = match_totp(&totp, code, mfa.last_used_step) else {
+            return Err(AppError::InvalidMfaCode);
+        };
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+        let code_hashes: Vec<String> = recovery_codes.iter().map(|c| hash_token(c)).collect();
+
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE user_mfa SET enabled_at = $1, last_used_step = $2, update_time = $1 WHERE user_id = $3
+        "#,
+        )
+        .bind(now)
+        .bind(step as i64)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE users SET mfa_enabled = TRUE, update_time = $1 WHERE id = $2
+        "#,
+        )
+        .bind(now)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM mfa_recovery_codes WHERE user_id = $1
+        "#,
+        )
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, unnest($2::VARCHAR[])
+        "#,
+        )
+        .bind(user.id)
+        .bind(&code_hashes)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.user_cache.remove(&user.id);
+        Ok(RecoveryCodes { recovery_codes })
+    }
+
+    // 关闭两步验证，角色要求两步验证的用户不能关闭
+    pub async fn disable_mfa(&self, user: &User, code: &str) -> Result<(), AppError> {
+        if user.mfa_required {
+            return Err(AppError::MfaRequired(user.id));
+        }
+        if !self.verify_mfa_code(user.id, code).await? {
+            return Err(AppError::InvalidMfaCode);
+        }
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM mfa_recovery_codes WHERE user_id = $1
+        "#,
+        )
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM user_mfa WHERE user_id = $1
+        "#,
+        )
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE users SET mfa_enabled = FALSE, update_time = $1 WHERE id = $2
+        "#,
+        )
+        .bind(Utc::now())
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.user_cache.remove(&user.id);
+        Ok(())
+    }
+
+    // 校验 TOTP 验证码或恢复码，同一时间窗口的验证码和恢复码都只能使用一次
+    pub async fn verify_mfa_code(&self, user_id: i64, code: &str) -> Result<bool, AppError> {
+        let mfa = match self.find_user_mfa(user_id).await? {
+            Some(mfa) if mfa.enabled_at.is_some() => mfa,
+            _ => return Ok(false),
+        };
+        let code = code.trim();
+        let email: String = sqlx::query_scalar(
+            r#"
+            SELECT email FROM users WHERE id = $1
+        "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        let totp = build_totp(&mfa.secret, &email)?;
+        if let Some(step) = match_totp(&totp, code, mfa.last_used_step) {
+            let result = sqlx::query(
+                r#"
+                UPDATE user_mfa SET last_used_step = $1, update_time = $2 WHERE user_id = $3 AND last_used_step < $1
+            "#,
+            )
+            .bind(step as i64)
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        let result = sqlx::query(
+            r#"
+            UPDATE mfa_recovery_codes SET used_at = $1 WHERE user_id = $2 AND code_hash = $3 AND used_at IS NULL
+        "#,
+        )
+        .bind(Utc::now())
+        .bind(user_id)
+        .bind(hash_token(&code.to_lowercase()))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // 密码验证通过后签发第二步验证使用的临时令牌
+    pub async fn create_mfa_challenge(&self, user_id: i64) -> Result<MfaChallenge, AppError> {
+        let token = generate_token();
+        sqlx::query(
+            r#"
+            INSERT INTO mfa_challenges (user_id, token_hash, expires_at) VALUES ($1, $2, $3)
+        "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(Utc::now() + Duration::seconds(MFA_CHALLENGE_EXPIRES_IN as i64))
+        .execute(&self.pool)
+        .await?;
+        Ok(MfaChallenge {
+            mfa_required: true,
+            mfa_token: token,
+            expires_in: MFA_CHALLENGE_EXPIRES_IN,
+        })
+    }
+
+    pub async fn find_mfa_challenge_user(&self, token: &str) -> Result<User, AppError> {
+        let user_id: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT user_id FROM mfa_challenges WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+        "#,
+        )
+        .bind(hash_token(token))
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(user_id) = user_id else {
+            return Err(AppError::InvalidMfaToken);
+        };
+        self.find_user_by_id(user_id)
+            .await?
+            .ok_or(AppError::InvalidMfaToken)
+    }
+
+    pub async fn consume_mfa_challenge(&self, token: &str) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE mfa_challenges SET used_at = $1 WHERE token_hash = $2 AND used_at IS NULL AND expires_at > $1
+        "#,
+        )
+        .bind(Utc::now())
+        .bind(hash_token(token))
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::InvalidMfaToken);
+        }
+        Ok(())
+    }
+
+    async fn find_user_mfa(&self, user_id: i64) -> Result<Option<UserMfa>, AppError> {
+        let mfa = sqlx::query_as(
+            r#"
+            SELECT secret, enabled_at, last_used_step FROM user_mfa WHERE user_id = $1
+        "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(mfa)
+    }
+}
+
+fn build_totp(secret: &str, account: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::TotpError(format!("{:?}", e)))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        MFA_DIGITS,
+        0,
+        MFA_STEP,
+        secret,
+        Some(MFA_ISSUER.to_string()),
+        account.to_string(),
+    )
+    .map_err(|e| AppError::TotpError(format!("{:?}", e)))
+}
+
+// 允许前后各一个时间窗口的时钟偏差，返回匹配的时间窗口
+fn match_totp(totp: &TOTP, code: &str, last_used_step: i64) -> Option<u64> {
+    let current = Utc::now().timestamp() as u64 / MFA_STEP;
+    (current.saturating_sub(1)..=current + 1)
+        .filter(|step| *step as i64 > last_used_step)
+        .find(|step| totp.generate(step * MFA_STEP) == code)
+}
+
+fn generate_recovery_code() -> String {
+    let token = generate_token();
+    format!("{}-{}", &token[..5], &token[5..10])
+}
+
+#[cfg(test)]
+mod test_mfa {
+    use super::*;
+    use anyhow::Result;
+    use cmall_core::PermissionVerify;
+
+    fn current_code(setup: &MfaSetup, user: &User) -> Result<String> {
+        let totp = build_totp(&setup.secret, &user.email)?;
+        Ok(totp.generate(Utc::now().timestamp() as u64))
+    }
+
+    #[tokio::test]
+    async fn test_mfa_enrollment_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+
+        let setup = state.setup_mfa(&user).await?;
+        assert!(setup.otpauth_uri.starts_with("otpauth://totp/Cmall:"));
+        let ret = state.enable_mfa(&user, "000000x").await;
+        assert!(matches!(ret, Err(AppError::InvalidMfaCode)));
+
+        let code = current_code(&setup, &user)?;
+        let codes = state.enable_mfa(&user, &code).await?;
+        assert_eq!(codes.recovery_codes.len(), RECOVERY_CODE_COUNT);
+        let user = state.find_user_by_id(1).await?.unwrap();
+        assert!(user.mfa_enabled);
+
+        // the code used for enrollment can not be replayed
+        assert!(!state.verify_mfa_code(user.id, &code).await?);
+        // recovery codes are single use
+        let recovery_code = &codes.recovery_codes[0];
+        assert!(state.verify_mfa_code(user.id, recovery_code).await?);
+        assert!(!state.verify_mfa_code(user.id, recovery_code).await?);
+
+        let challenge = state.create_mfa_challenge(user.id).await?;
+        let found = state.find_mfa_challenge_user(&challenge.mfa_token).await?;
+        assert_eq!(found.id, user.id);
+        state.consume_mfa_challenge(&challenge.mfa_token).await?;
+        let ret = state.find_mfa_challenge_user(&challenge.mfa_token).await;
+        assert!(matches!(ret, Err(AppError::InvalidMfaToken)));
+
+        state.disable_mfa(&user, &codes.recovery_codes[1]).await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        assert!(!user.mfa_enabled);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_role_should_require_mfa() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        state.set_role_mfa_required(1, true, "admin".into()).await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        assert!(user.mfa_required);
+        let ret = state.permissions(&user).await;
+        assert!(matches!(ret, Err(AppError::MfaRequired(1))));
+
+        let setup = state.setup_mfa(&user).await?;
+        let code = current_code(&setup, &user)?;
+        state.enable_mfa(&user, &code).await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        assert!(!state.permissions(&user).await?.is_empty());
+        let ret = state.disable_mfa(&user, &code).await;
+        assert!(matches!(ret, Err(AppError::MfaRequired(1))));
+        Ok(())
+    }
+}
@@ -1,5 +1,72 @@
 mod user;
-pub use user::{CreateUser, LoginUser, UpdateUser};
+pub use user::{
+    ChangePassword, CreateUser, LoginUser, ResetPasswordOutput, SearchUser, SignupUser, SortOrder,
+    UpdateUser, UserSortBy,
+};
+
+mod user_export;
+pub use user_export::{ExportUser, UserExport};
+
+mod user_import;
+pub use user_import::{ImportFormat, ImportRowError, ImportUserReport, ImportUsers};
 
 mod role;
-pub use role::OperateRole;
+pub use role::{OperateRole, RoleMfa};
+
+mod department;
+pub use department::OperateDepartment;
+
+mod menu;
+pub use menu::OperateMenu;
+
+mod role_menu;
+pub use role_menu::{RoleMenus, UserMenus};
+
+mod session;
+pub use session::{AuthTokens, RefreshToken, UserSession};
+
+mod password_reset;
+pub use password_reset::{ConfirmPasswordReset, ForgotPassword};
+
+mod lockout;
+
+mod mfa;
+pub use mfa::{MfaChallenge, MfaCode, MfaSetup, MfaSignin, RecoveryCodes};
+
+mod audit;
+pub use audit::{ExportAudit, SearchAudit};
+
+mod recycle;
+pub use recycle::{PurgeOutput, RecycleItem, SearchRecycle};
+
+mod export_job;
+pub use export_job::ExportJobOutput;
+
+mod product;
+pub use product::{
+    CreateProduct, OperateAttribute, OperateProduct, OperateSku, ProductStatusInput, SearchProduct,
+};
+
+mod category;
+pub use category::{MoveCategory, OperateCategory, SearchCategory};
+
+mod inventory;
+pub use inventory::{
+    AdjustStock, LowStockItem, ReceiveStock, ReturnStock, SearchMovement,
+    DEFAULT_LOW_STOCK_THRESHOLD,
+};
+
+mod warehouse;
+pub use warehouse::{OperateWarehouse, SearchWarehouse};
+
+mod stock_transfer;
+pub use stock_transfer::{CreateTransfer, SearchTransfer};
+
+mod cart;
+pub use cart::{AddCartItem, CartOwner, UpdateCartItem};
+
+mod order;
+pub use order::{Checkout, OrderSortBy, OrderStatusInput, SearchOrder};
+
+mod payment;
+pub use payment::PaymentOutput;
@@ -0,0 +1,481 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use cmall_core::{
+    Cart, Order, OrderDetail, OrderItem, OrderStatus, StockAllocation, StockLine,
+    StockMovementKind, User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use tracing::{info, warn};
+
+use super::{
+    cart::{find_cart_items, lock_cart},
+    inventory::{allocate_stock, apply_stock_changes},
+    payment::mark_order_refunding,
+};
+use crate::{error::AppError, AppState, CartOwner, Page, QueryFilter, SortOrder};
+
+const ORDER_COLUMNS: &str = "id, user_id, status, total_amount, remark, create_time, update_time";
+const ORDER_ITEM_COLUMNS: &str =
+    "sku_id, sku_code, product_id, product_name, attributes, price, quantity, line_total";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkout {
+    #[serde(default)]
+    pub remark: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderStatusInput {
+    pub status: OrderStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOrder {
+    pub user_id: Option<i64>,
+    pub status: Option<OrderStatus>,
+    // 包含该 SKU 的订单
+    pub sku_id: Option<i64>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub sort_by: OrderSortBy,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+// 允许排序的字段，避免拼接任意列名
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OrderSortBy {
+    Id,
+    TotalAmount,
+    #[default]
+    CreateTime,
+    UpdateTime,
+}
+
+impl OrderSortBy {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::TotalAmount => "total_amount",
+            Self::CreateTime => "create_time",
+            Self::UpdateTime => "update_time",
+        }
+    }
+}
+
+impl AppState {
+    // 在同一事务中按购物车生成订单、快照商品价格并预留库存，成功后清空购物车
+    pub async fn checkout(&self, user: &User, input: &Checkout) -> Result<OrderDetail, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let cart_id = lock_cart(&mut tx, &CartOwner::User(user.id)).await?;
+        let items = find_cart_items(&mut *tx, cart_id).await?;
+        if items.is_empty() {
+            return Err(AppError::InvalidCart("cart is empty".to_string()));
+        }
+        if let Some(item) = items.iter().find(|i| !i.on_sale) {
+            return Err(AppError::InvalidCart(format!(
+                "sku {} is not on sale",
+                item.sku_id
+            )));
+        }
+        let cart = Cart::build(items)
+            .ok_or_else(|| AppError::InvalidCart("amount overflow".to_string()))?;
+        let total_amount = cart
+            .lines
+            .iter()
+            .try_fold(0i64, |total, line| total.checked_add(line.line_total))
+            .ok_or_else(|| AppError::InvalidCart("amount overflow".to_string()))?;
+
+        let order: Order = sqlx::query_as(&format!(
+            "INSERT INTO orders (user_id, total_amount, remark) VALUES ($1, $2, $3) RETURNING {}",
+            ORDER_COLUMNS
+        ))
+        .bind(user.id)
+        .bind(total_amount)
+        .bind(&input.remark)
+        .fetch_one(&mut *tx)
+        .await?;
+        for line in &cart.lines {
+            let item = &line.item;
+            sqlx::query(
+                r#"
+                INSERT INTO order_items (order_id, sku_id, sku_code, product_id, product_name, attributes, price, quantity, line_total)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            )
+            .bind(order.id)
+            .bind(item.sku_id)
+            .bind(&item.sku_code)
+            .bind(item.product_id)
+            .bind(&item.product_name)
+            .bind(&item.attributes)
+            .bind(item.price)
+            .bind(item.quantity)
+            .bind(line.line_total)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let lines: Vec<StockLine> = cart
+            .lines
+            .iter()
+            .map(|l| StockLine::new(l.item.sku_id, l.item.quantity))
+            .collect();
+        let allocations = allocate_stock(&mut tx, &lines, self.config.inventory.allocation).await?;
+        apply_stock_changes(
+            &mut tx,
+            &allocations,
+            StockMovementKind::Reserve,
+            "",
+            &order.reference(),
+            &user.username,
+        )
+        .await?;
+        for allocation in &allocations {
+            sqlx::query(
+                r#"
+                INSERT INTO order_allocations (order_id, warehouse_id, sku_id, quantity) VALUES ($1, $2, $3, $4)
+            "#,
+            )
+            .bind(order.id)
+            .bind(allocation.warehouse_id)
+            .bind(allocation.sku_id)
+            .bind(allocation.quantity)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query(
+            r#"
+            DELETE FROM cart_items WHERE cart_id = $1
+        "#,
+        )
+        .bind(cart_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        info!("user {} placed order {}", user.id, order.id);
+        let items = self.find_order_items(order.id).await?;
+        Ok(OrderDetail { order, items })
+    }
+
+    // 按状态机变更订单状态，user_id 不为空时只能操作该用户自己的订单；
+    // 已付款的订单关闭时同时退款
+    pub async fn change_order_status(
+        &self,
+        id: i64,
+        to: OrderStatus,
+        user_id: Option<i64>,
+        update_by: &str,
+    ) -> Result<OrderDetail, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let from = transition_order(&mut tx, id, to, user_id, update_by).await?;
+        let refund = from == OrderStatus::Paid && to == OrderStatus::Closed;
+        if refund {
+            mark_order_refunding(&mut tx, id).await?;
+        }
+        tx.commit().await?;
+        if refund {
+            self.refund_order_payments(id).await?;
+        }
+        self.find_order_detail(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("order id {}", id)))
+    }
+
+    // 关闭超过付款时限的待付款订单，释放预留库存，返回关闭的订单数
+    pub async fn close_expired_orders(&self) -> Result<u64, AppError> {
+        let before = Utc::now() - Duration::seconds(self.config.order.payment_timeout as i64);
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM orders WHERE status = 'pending_payment' AND create_time < $1 ORDER BY id
+        "#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut closed = 0;
+        for id in ids {
+            let mut tx = self.pool.begin().await?;
+            match transition_order(&mut tx, id, OrderStatus::Closed, None, "system").await {
+                Ok(_) => {
+                    tx.commit().await?;
+                    closed += 1;
+                }
+                // 查询之后已被付款或取消
+                Err(AppError::InvalidOrderTransition(_, _)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(closed)
+    }
+
+    // 后台定时关闭超时订单、重试失败的退款并清理过期的游客购物车，启动服务时调用一次
+    pub fn spawn_order_jobs(&self) {
+        let state = self.clone();
+        let period = StdDuration::from_secs(self.config.order.close_interval.max(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                match state.close_expired_orders().await {
+                    Ok(0) => {}
+                    Ok(closed) => info!("{} expired orders closed", closed),
+                    Err(e) => warn!("close expired orders error: {:?}", e),
+                }
+                match state.retry_refunds().await {
+                    Ok(0) => {}
+                    Ok(refunded) => info!("{} pending refunds completed", refunded),
+                    Err(e) => warn!("retry refunds error: {:?}", e),
+                }
+                match state.purge_expired_guest_carts().await {
+                    Ok(0) => {}
+                    Ok(purged) => info!("{} expired guest carts purged", purged),
+                    Err(e) => warn!("purge expired guest carts error: {:?}", e),
+                }
+            }
+        });
+    }
+
+    pub async fn find_order_detail(&self, id: i64) -> Result<Option<OrderDetail>, AppError> {
+        let order: Option<Order> = sqlx::query_as(&format!(
+            "SELECT {} FROM orders WHERE id = $1",
+            ORDER_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(order) = order else {
+            return Ok(None);
+        };
+        let items = self.find_order_items(id).await?;
+        Ok(Some(OrderDetail { order, items }))
+    }
+
+    pub async fn find_order_by_condition(
+        &self,
+        input: &SearchOrder,
+        page: Page,
+    ) -> Result<(Vec<Order>, i64), AppError> {
+        let filter = QueryFilter::new()
+            .eq("user_id", input.user_id)
+            .eq("status", input.status)
+            .bind(
+                "EXISTS(SELECT 1 FROM order_items oi WHERE oi.order_id = orders.id AND oi.sku_id = ",
+                input.sku_id,
+                ")",
+            )
+            .gte("create_time", input.start_time)
+            .lt("create_time", input.end_time);
+        // 排序字段来自白名单，按 id 排序保证分页结果稳定
+        let order_by = format!(
+            "{} {order}, id {order}",
+            input.sort_by.column(),
+            order = input.sort_order.as_sql()
+        );
+        filter
+            .fetch_page(&self.pool, ORDER_COLUMNS, "orders", &order_by, page)
+            .await
+    }
+
+    async fn find_order_items(&self, order_id: i64) -> Result<Vec<OrderItem>, AppError> {
+        let items = sqlx::query_as(&format!(
+            "SELECT {} FROM order_items WHERE order_id = $1 ORDER BY sku_id",
+            ORDER_ITEM_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(items)
+    }
+}
+
+// 锁定订单并校验状态变更，同时出库或释放订单预留的库存，返回变更前的状态
+pub(crate) async fn transition_order(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    to: OrderStatus,
+    user_id: Option<i64>,
+    update_by: &str,
+) -> Result<OrderStatus, AppError> {
+    let order: Option<Order> = sqlx::query_as(&format!(
+        "SELECT {} FROM orders WHERE id = $1 FOR UPDATE",
+        ORDER_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    let order = match order {
+        Some(order) if user_id.is_none_or(|user_id| order.user_id == user_id) => order,
+        _ => return Err(AppError::NotFound(format!("order id {}", id))),
+    };
+    let Some(stock_change) = order.status.transition(to) else {
+        return Err(AppError::InvalidOrderTransition(order.status, to));
+    };
+    if let Some(kind) = stock_change {
+        let allocations: Vec<StockAllocation> = sqlx::query_as(
+            r#"
+            SELECT warehouse_id, sku_id, quantity FROM order_allocations WHERE order_id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_all(&mut **tx)
+        .await?;
+        apply_stock_changes(tx, &allocations, kind, "", &order.reference(), update_by).await?;
+    }
+    sqlx::query(
+        r#"
+        UPDATE orders SET status = $1, update_time = $2 WHERE id = $3
+    "#,
+    )
+    .bind(to)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(&mut **tx)
+    .await?;
+    info!("order {} changed from {} to {}", id, order.status, to);
+    Ok(order.status)
+}
+
+#[cfg(test)]
+mod test_order {
+    use super::*;
+    use crate::{AddCartItem, AdjustStock};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_checkout_should_reserve_stock_and_clear_cart() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = state.create_on_sale_product(5).await?.skus[0].id;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        let owner = CartOwner::User(user.id);
+        let ret = state.checkout(&user, &Checkout::default()).await;
+        assert!(matches!(ret, Err(AppError::InvalidCart(_))));
+
+        let add = AddCartItem {
+            sku_id,
+            quantity: 2,
+        };
+        state.add_cart_item(&owner, &add).await?;
+        let order = state.checkout(&user, &Checkout::default()).await?;
+        assert_eq!(order.order.status, OrderStatus::PendingPayment);
+        assert_eq!(order.order.total_amount, 1000);
+        assert_eq!(order.items[0].price, 500);
+        assert!(state.find_cart(&owner).await?.lines.is_empty());
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        assert_eq!(inventories[0].reserved, 2);
+
+        // 库存不足时整个下单事务回滚，购物车保持不变
+        state
+            .add_cart_item(&owner, &AddCartItem { quantity: 3, ..add })
+            .await?;
+        let adjust = AdjustStock {
+            warehouse_id: 1,
+            quantity: -1,
+            reason: "damaged".to_string(),
+        };
+        state
+            .adjust_stock(sku_id, &adjust, "admin".to_string())
+            .await?;
+        let ret = state.checkout(&user, &Checkout::default()).await;
+        assert!(matches!(ret, Err(AppError::InsufficientStock(_))));
+        assert_eq!(state.find_cart(&owner).await?.lines.len(), 1);
+        let (orders, total) = state
+            .find_order_by_condition(&SearchOrder::default(), Page::new(1, 10)?)
+            .await?;
+        assert_eq!(total, 1);
+        assert_eq!(orders[0].id, order.order.id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_order_status_should_follow_state_machine() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = state.create_on_sale_product(5).await?.skus[0].id;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        let add = AddCartItem {
+            sku_id,
+            quantity: 2,
+        };
+        state.add_cart_item(&CartOwner::User(user.id), &add).await?;
+        let id = state.checkout(&user, &Checkout::default()).await?.order.id;
+
+        // 其他用户看不到该订单
+        let ret = state
+            .change_order_status(id, OrderStatus::Cancelled, Some(2), "other")
+            .await;
+        assert!(matches!(ret, Err(AppError::NotFound(_))));
+        let ret = state
+            .change_order_status(id, OrderStatus::Shipped, None, "admin")
+            .await;
+        assert!(matches!(
+            ret,
+            Err(AppError::InvalidOrderTransition(
+                OrderStatus::PendingPayment,
+                OrderStatus::Shipped
+            ))
+        ));
+
+        for status in [OrderStatus::Paid, OrderStatus::Shipped] {
+            state.change_order_status(id, status, None, "admin").await?;
+        }
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        assert_eq!((inventories[0].on_hand, inventories[0].reserved), (3, 0));
+        let ret = state
+            .change_order_status(id, OrderStatus::Cancelled, Some(user.id), &user.username)
+            .await;
+        assert!(matches!(ret, Err(AppError::InvalidOrderTransition(_, _))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expired_orders_should_be_closed() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let sku_id = state.create_on_sale_product(6).await?.skus[0].id;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        let add = AddCartItem {
+            sku_id,
+            quantity: 2,
+        };
+        let owner = CartOwner::User(user.id);
+        state.add_cart_item(&owner, &add).await?;
+        let expired = state.checkout(&user, &Checkout::default()).await?.order.id;
+        state.add_cart_item(&owner, &add).await?;
+        let paid = state.checkout(&user, &Checkout::default()).await?.order.id;
+        state
+            .change_order_status(paid, OrderStatus::Paid, None, "admin")
+            .await?;
+        state.add_cart_item(&owner, &add).await?;
+        let fresh = state.checkout(&user, &Checkout::default()).await?.order.id;
+        sqlx::query(
+            "UPDATE orders SET create_time = create_time - INTERVAL '1 day' WHERE id <> $1",
+        )
+        .bind(fresh)
+        .execute(&state.pool)
+        .await?;
+
+        // 只关闭超时的待付款订单
+        assert_eq!(state.close_expired_orders().await?, 1);
+        for (id, status) in [
+            (expired, OrderStatus::Closed),
+            (paid, OrderStatus::Paid),
+            (fresh, OrderStatus::PendingPayment),
+        ] {
+            let order = state.find_order_detail(id).await?.unwrap();
+            assert_eq!(order.order.status, status);
+        }
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        assert_eq!(inventories[0].reserved, 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{
+    session::{generate_token, hash_token},
+    user::{check_password, update_password},
+};
+use crate::{error::AppError, AppState, Mail, Mailer};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmPasswordReset {
+    pub token: String,
+    pub new_password: String,
+}
+
+impl AppState {
+    // 申请找回密码，邮箱不存在或用户已停用时同样返回成功，避免泄露注册信息
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), AppError> {
+        let user = match self.find_user_by_email(email).await? {
+            Some(user) if user.status.can_sign_in() => user,
+            _ => {
+                info!("password reset requested for unknown or disabled email");
+                return Ok(());
+            }
+        };
+        let token = self.create_password_reset(user.id).await?;
+        let mail = Mail {
+            to: user.email.clone(),
+            subject: "重置密码 / Reset your password".to_string(),
+            body: format!(
+                "{}，你好：\n\n请在 {} 分钟内打开以下链接重置密码，如非本人操作请忽略本邮件。\n\n{}?token={}\n",
+                user.username,
+                self.config.auth.reset_expires_in / 60,
+                self.config.mail.reset_url,
+                token
+            ),
+        };
+        self.mailer.send(&mail).await
+    }
+
+    // 签发新的重置令牌，同一用户之前未使用的令牌随即失效
+    pub(crate) async fn create_password_reset(&self, user_id: i64) -> Result<String, AppError> {
+        let token = generate_token();
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(self.config.auth.reset_expires_in as i64);
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE password_resets SET used_at = $1 WHERE user_id = $2 AND used_at IS NULL
+        "#,
+        )
+        .bind(now)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO password_resets (user_id, token_hash, expires_at) VALUES ($1, $2, $3)
+        "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(token)
+    }
+
+    // 使用重置令牌设置新密码，令牌只能使用一次，已登录的会话全部失效
+    pub async fn confirm_password_reset(
+        &self,
+        input: &ConfirmPasswordReset,
+    ) -> Result<(), AppError> {
+        check_password(&input.new_password)?;
+        let mut tx = self.pool.begin().await?;
+        let user_id: Option<i64> = sqlx::query_scalar(
+            r#"
+            UPDATE password_resets SET used_at = $1
+            WHERE token_hash = $2 AND used_at IS NULL AND expires_at > $1
+            RETURNING user_id
+        "#,
+        )
+        .bind(Utc::now())
+        .bind(hash_token(&input.token))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(user_id) = user_id else {
+            return Err(AppError::InvalidResetToken);
+        };
+        update_password(&mut tx, user_id, &input.new_password, false).await?;
+        tx.commit().await?;
+        self.user_cache.remove(&user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_password_reset {
+    use super::*;
+    use crate::{CreateUser, LoginUser};
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_password_reset_should_be_single_use() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input = CreateUser::new("test", "test@example.com", "1234567890", "test123");
+        let user = state.create_user(&input).await?;
+        let tokens = state.create_session(&user).await?;
+
+        let stale = state.create_password_reset(user.id).await?;
+        let token = state.create_password_reset(user.id).await?;
+        let input = ConfirmPasswordReset {
+            token: stale,
+            new_password: "hunter22".to_string(),
+        };
+        let ret = state.confirm_password_reset(&input).await;
+        assert!(matches!(ret, Err(AppError::InvalidResetToken)));
+
+        let input = ConfirmPasswordReset {
+            token,
+            new_password: "hunter22".to_string(),
+        };
+        state.confirm_password_reset(&input).await?;
+        let login = LoginUser {
+            email: user.email.clone(),
+            password: "hunter22".to_string(),
+        };
+        assert!(state.verify_user(&login).await?.is_some());
+        let ret = state.refresh_session(&tokens.refresh_token).await;
+        assert!(matches!(ret, Err(AppError::InvalidRefreshToken)));
+
+        let ret = state.confirm_password_reset(&input).await;
+        assert!(matches!(ret, Err(AppError::InvalidResetToken)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_email_should_not_fail() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        state.request_password_reset("nobody@example.com").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,427 @@
+use axum::http::HeaderMap;
+use chrono::Utc;
+use cmall_core::{OrderStatus, Payment, PaymentStatus};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, Postgres, Transaction};
+use tracing::{info, warn};
+
+use super::order::transition_order;
+use crate::{error::AppError, AppState, PaymentGateway, PaymentRequest};
+
+const PAYMENT_COLUMNS: &str =
+    "id, order_id, gateway, external_id, amount, status, create_time, update_time";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentOutput {
+    #[serde(flatten)]
+    pub payment: Payment,
+    pub pay_url: String,
+}
+
+impl AppState {
+    // 为待付款的订单发起一次支付，渠道调用失败时记录为失败
+    pub async fn pay_order(&self, order_id: i64, user_id: i64) -> Result<PaymentOutput, AppError> {
+        let order = match self.find_order_detail(order_id).await? {
+            Some(detail) if detail.order.user_id == user_id => detail.order,
+            _ => return Err(AppError::NotFound(format!("order id {}", order_id))),
+        };
+        if order.status != OrderStatus::PendingPayment {
+            return Err(AppError::InvalidOrderTransition(
+                order.status,
+                OrderStatus::Paid,
+            ));
+        }
+        let payment: Payment = sqlx::query_as(&format!(
+            "INSERT INTO payments (order_id, gateway, amount) VALUES ($1, $2, $3) RETURNING {}",
+            PAYMENT_COLUMNS
+        ))
+        .bind(order.id)
+        .bind(self.payment.name())
+        .bind(order.total_amount)
+        .fetch_one(&self.pool)
+        .await?;
+        let request = PaymentRequest {
+            payment_id: payment.id,
+            order_id: order.id,
+            amount: payment.amount,
+        };
+        let intent = match self.payment.create_intent(&request).await {
+            Ok(intent) => intent,
+            Err(e) => {
+                sqlx::query("UPDATE payments SET status = $1, update_time = $2 WHERE id = $3")
+                    .bind(PaymentStatus::Failed)
+                    .bind(Utc::now())
+                    .bind(payment.id)
+                    .execute(&self.pool)
+                    .await?;
+                return Err(e);
+            }
+        };
+        let payment = sqlx::query_as(&format!(
+            "UPDATE payments SET external_id = $1, update_time = $2 WHERE id = $3 RETURNING {}",
+            PAYMENT_COLUMNS
+        ))
+        .bind(&intent.external_id)
+        .bind(Utc::now())
+        .bind(payment.id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(PaymentOutput {
+            payment,
+            pay_url: intent.pay_url,
+        })
+    }
+
+    // 支付渠道的回调，每个事件只处理一次
+    pub async fn handle_payment_webhook(
+        &self,
+        gateway: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), AppError> {
+        if gateway != self.payment.name() {
+            return Err(AppError::NotFound(format!("payment gateway {}", gateway)));
+        }
+        let event = self.payment.verify_webhook(headers, body)?;
+        let mut tx = self.pool.begin().await?;
+        let event_id: Option<i64> = sqlx::query_scalar(
+            r#"
+            INSERT INTO payment_events (gateway, event_id, external_id, status, payload) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (gateway, event_id) DO NOTHING
+            RETURNING id
+        "#,
+        )
+        .bind(gateway)
+        .bind(&event.event_id)
+        .bind(&event.external_id)
+        .bind(event.status)
+        .bind(Json(&event))
+        .fetch_optional(&mut *tx)
+        .await?;
+        if event_id.is_none() {
+            info!("duplicate payment event {} ignored", event.event_id);
+            return Ok(());
+        }
+        let payment = self
+            .apply_payment_status(
+                &mut tx,
+                &event.external_id,
+                event.status,
+                Some(event.amount),
+            )
+            .await?;
+        tx.commit().await?;
+        if payment.status == PaymentStatus::Refunding {
+            self.try_refund_payment(&payment).await;
+        }
+        Ok(())
+    }
+
+    // 回调丢失时主动向支付渠道查询结果
+    pub async fn sync_payment(&self, order_id: i64, payment_id: i64) -> Result<Payment, AppError> {
+        let payment = self
+            .find_order_payments(order_id)
+            .await?
+            .into_iter()
+            .find(|p| p.id == payment_id)
+            .ok_or_else(|| AppError::NotFound(format!("payment id {}", payment_id)))?;
+        let Some(external_id) = payment.external_id else {
+            return Err(AppError::InvalidPayment(format!(
+                "payment {} was not created by gateway",
+                payment_id
+            )));
+        };
+        if payment.gateway != self.payment.name() {
+            return Err(AppError::NotFound(format!(
+                "payment gateway {}",
+                payment.gateway
+            )));
+        }
+        let status = self.payment.query(&external_id).await?;
+        let mut tx = self.pool.begin().await?;
+        let payment = self
+            .apply_payment_status(&mut tx, &external_id, status, None)
+            .await?;
+        tx.commit().await?;
+        if payment.status == PaymentStatus::Refunding {
+            self.try_refund_payment(&payment).await;
+        }
+        let payment = sqlx::query_as(&format!(
+            "SELECT {} FROM payments WHERE id = $1",
+            PAYMENT_COLUMNS
+        ))
+        .bind(payment_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(payment)
+    }
+
+    pub async fn find_order_payments(&self, order_id: i64) -> Result<Vec<Payment>, AppError> {
+        let payments = sqlx::query_as(&format!(
+            "SELECT {} FROM payments WHERE order_id = $1 ORDER BY id",
+            PAYMENT_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(payments)
+    }
+
+    // 在关闭订单的事务提交后调用，向渠道发起订单待退款的支付
+    pub(crate) async fn refund_order_payments(&self, order_id: i64) -> Result<(), AppError> {
+        let payments: Vec<Payment> = sqlx::query_as(&format!(
+            "SELECT {} FROM payments WHERE order_id = $1 AND status = 'refunding'",
+            PAYMENT_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+        for payment in &payments {
+            self.try_refund_payment(payment).await;
+        }
+        Ok(())
+    }
+
+    // 重试渠道调用失败的退款，由后台任务定时调用，返回完成的退款数
+    pub async fn retry_refunds(&self) -> Result<u64, AppError> {
+        let payments: Vec<Payment> = sqlx::query_as(&format!(
+            "SELECT {} FROM payments WHERE status = 'refunding' ORDER BY id",
+            PAYMENT_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        let mut refunded = 0;
+        for payment in &payments {
+            if self.try_refund_payment(payment).await {
+                refunded += 1;
+            }
+        }
+        Ok(refunded)
+    }
+
+    // 只有待支付的记录会被更新，重复或过期的结果直接忽略，返回处理后的支付记录。
+    // 支付成功时订单变为已付款，订单已取消、关闭或已被其他支付完成时标记为待退款
+    async fn apply_payment_status(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        external_id: &str,
+        status: PaymentStatus,
+        amount: Option<i64>,
+    ) -> Result<Payment, AppError> {
+        let payment: Option<Payment> = sqlx::query_as(&format!(
+            "SELECT {} FROM payments WHERE gateway = $1 AND external_id = $2 FOR UPDATE",
+            PAYMENT_COLUMNS
+        ))
+        .bind(self.payment.name())
+        .bind(external_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+        let mut payment =
+            payment.ok_or_else(|| AppError::NotFound(format!("payment {}", external_id)))?;
+        if amount.is_some_and(|amount| amount != payment.amount) {
+            return Err(AppError::InvalidPayment(format!(
+                "amount of payment {} does not match",
+                payment.id
+            )));
+        }
+        if payment.status != PaymentStatus::Pending || status == PaymentStatus::Pending {
+            return Ok(payment);
+        }
+        payment.status = status;
+        if status == PaymentStatus::Succeeded {
+            match transition_order(tx, payment.order_id, OrderStatus::Paid, None, "system").await {
+                Ok(_) => {}
+                Err(AppError::InvalidOrderTransition(_, _)) => {
+                    payment.status = PaymentStatus::Refunding
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        set_payment_status(tx, payment.id, payment.status).await?;
+        info!("payment {} changed to {:?}", payment.id, payment.status);
+        Ok(payment)
+    }
+
+    // 渠道的退款按交易号幂等，失败时保持待退款状态等待重试，返回是否完成退款
+    async fn try_refund_payment(&self, payment: &Payment) -> bool {
+        let external_id = payment.external_id.as_deref().unwrap_or_default();
+        if let Err(e) = self.payment.refund(external_id, payment.amount).await {
+            warn!("refund payment {} error: {:?}", payment.id, e);
+            return false;
+        }
+        let ret = sqlx::query(
+            r#"
+            UPDATE payments SET status = 'refunded', update_time = $1 WHERE id = $2 AND status = 'refunding'
+        "#,
+        )
+        .bind(Utc::now())
+        .bind(payment.id)
+        .execute(&self.pool)
+        .await;
+        match ret {
+            Ok(_) => {
+                info!("payment {} refunded", payment.id);
+                true
+            }
+            Err(e) => {
+                warn!("update refunded payment {} error: {:?}", payment.id, e);
+                false
+            }
+        }
+    }
+}
+
+// 在关闭订单的事务中把已成功的支付标记为待退款，事务提交后再调用渠道退款
+pub(crate) async fn mark_order_refunding(
+    tx: &mut Transaction<'_, Postgres>,
+    order_id: i64,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE payments SET status = 'refunding', update_time = $1 WHERE order_id = $2 AND status = 'succeeded'
+    "#,
+    )
+    .bind(Utc::now())
+    .bind(order_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+async fn set_payment_status(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    status: PaymentStatus,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE payments SET status = $1, update_time = $2 WHERE id = $3
+    "#,
+    )
+    .bind(status)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_payment {
+    use super::*;
+    use crate::{AddCartItem, CartOwner, Checkout, PaymentProvider};
+    use anyhow::Result;
+
+    // 用户 1 下单购买 2 件单价 500 的商品，返回订单 id
+    async fn place_order(state: &AppState) -> Result<i64> {
+        let sku_id = state.create_on_sale_product(5).await?.skus[0].id;
+        let user = state.find_user_by_id(1).await?.unwrap();
+        let add = AddCartItem {
+            sku_id,
+            quantity: 2,
+        };
+        state.add_cart_item(&CartOwner::User(user.id), &add).await?;
+        Ok(state.checkout(&user, &Checkout::default()).await?.order.id)
+    }
+
+    fn mock(state: &AppState) -> &crate::MockGateway {
+        match &state.payment {
+            PaymentProvider::Mock(gateway) => gateway,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payment_callback_should_mark_order_paid_once() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let order_id = place_order(&state).await?;
+        let ret = state.pay_order(order_id, 2).await;
+        assert!(matches!(ret, Err(AppError::NotFound(_))));
+
+        let output = state.pay_order(order_id, 1).await?;
+        assert_eq!(output.payment.amount, 1000);
+        let external_id = output.payment.external_id.clone().unwrap();
+        let (headers, body) =
+            mock(&state).simulate_callback(&external_id, PaymentStatus::Succeeded)?;
+        let ret = state
+            .handle_payment_webhook("mock", &HeaderMap::new(), &body)
+            .await;
+        assert!(matches!(ret, Err(AppError::InvalidPaymentSignature)));
+
+        // 重复的回调只处理一次
+        for _ in 0..2 {
+            state
+                .handle_payment_webhook("mock", &headers, &body)
+                .await?;
+        }
+        let order = state.find_order_detail(order_id).await?.unwrap();
+        assert_eq!(order.order.status, OrderStatus::Paid);
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM payment_events")
+            .fetch_one(&state.pool)
+            .await?;
+        assert_eq!(events, 1);
+
+        // 已付款的订单关闭时退款
+        state
+            .change_order_status(order_id, OrderStatus::Closed, None, "admin")
+            .await?;
+        let payments = state.find_order_payments(order_id).await?;
+        assert_eq!(payments[0].status, PaymentStatus::Refunded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_payment_after_cancel_should_be_refunded() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let order_id = place_order(&state).await?;
+        let output = state.pay_order(order_id, 1).await?;
+        state
+            .change_order_status(order_id, OrderStatus::Cancelled, Some(1), "elixy")
+            .await?;
+        let ret = state.pay_order(order_id, 1).await;
+        assert!(matches!(ret, Err(AppError::InvalidOrderTransition(_, _))));
+
+        // 回调丢失，通过主动查询得到支付结果
+        let external_id = output.payment.external_id.unwrap();
+        mock(&state).simulate_callback(&external_id, PaymentStatus::Succeeded)?;
+        let payment = state.sync_payment(order_id, output.payment.id).await?;
+        assert_eq!(payment.status, PaymentStatus::Refunded);
+        let order = state.find_order_detail(order_id).await?.unwrap();
+        assert_eq!(order.order.status, OrderStatus::Cancelled);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_refund_should_be_retried() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let order_id = place_order(&state).await?;
+        let output = state.pay_order(order_id, 1).await?;
+        // 数据库已记录支付成功，但渠道侧的交易尚未完成，退款会失败
+        sqlx::query("UPDATE payments SET status = 'succeeded' WHERE id = $1")
+            .bind(output.payment.id)
+            .execute(&state.pool)
+            .await?;
+        sqlx::query("UPDATE orders SET status = 'paid' WHERE id = $1")
+            .bind(order_id)
+            .execute(&state.pool)
+            .await?;
+
+        // 渠道退款失败不影响关闭订单，支付保持待退款
+        state
+            .change_order_status(order_id, OrderStatus::Closed, None, "admin")
+            .await?;
+        let order = state.find_order_detail(order_id).await?.unwrap();
+        assert_eq!(order.order.status, OrderStatus::Closed);
+        let payments = state.find_order_payments(order_id).await?;
+        assert_eq!(payments[0].status, PaymentStatus::Refunding);
+        assert_eq!(state.retry_refunds().await?, 0);
+
+        let external_id = output.payment.external_id.unwrap();
+        mock(&state).simulate_callback(&external_id, PaymentStatus::Succeeded)?;
+        assert_eq!(state.retry_refunds().await?, 1);
+        let payments = state.find_order_payments(order_id).await?;
+        assert_eq!(payments[0].status, PaymentStatus::Refunded);
+        assert_eq!(state.retry_refunds().await?, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::Utc;
+use cmall_core::{
+    check_sku_attributes, EffectStatus, Product, ProductAttribute, ProductDetail, ProductStatus,
+    Sku,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, Postgres, Transaction};
+use tracing::info;
+
+use crate::{error::AppError, AppState, ExportColumn, Exportable, Page, QueryFilter};
+
+const PRODUCT_COLUMNS: &str = "id, category_id, name, description, status, price, create_time, create_by, update_time, update_by";
+const SKU_COLUMNS: &str =
+    "id, product_id, sku_code, price, attributes, status, create_time, update_time";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateAttribute {
+    pub name: String,
+    pub options: Vec<String>,
+}
+
+// 价格以分为单位
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateProduct {
+    pub category_id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub price: i64,
+    #[serde(default)]
+    pub attributes: Vec<OperateAttribute>,
+}
+
+// 创建商品时可以同时创建 SKU
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProduct {
+    #[serde(flatten)]
+    pub product: OperateProduct,
+    #[serde(default)]
+    pub skus: Vec<OperateSku>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateSku {
+    pub sku_code: String,
+    pub price: i64,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    pub status: EffectStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductStatusInput {
+    pub status: ProductStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchProduct {
+    pub name: Option<String>,
+    pub category_id: Option<i64>,
+    pub status: Option<ProductStatus>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+impl Exportable for Product {
+    const NAME: &'static str = "products";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("id", "编号", "ID", |p: &Product| p.id.into()),
+            ExportColumn::new("name", "名称", "Name", |p: &Product| {
+                p.name.as_str().into()
+            })
+            .width(25.0),
+            ExportColumn::new("status", "状态", "Status", |p: &Product| {
+                p.status.to_string().into()
+            }),
+            ExportColumn::new("price", "价格(分)", "Price (cents)", |p: &Product| {
+                p.price.into()
+            }),
+            ExportColumn::new(
+                "createTime",
+                "创建时间",
+                "Create Time",
+                |p: &Product| p.create_time.into(),
+            )
+            .width(25.0),
+        ]
+    }
+}
+
+impl AppState {
+    pub async fn create_product(
+        &self,
+        input: &CreateProduct,
+        create_by: String,
+    ) -> Result<ProductDetail, AppError> {
+        check_product(&input.product)?;
+        self.check_product_category(input.product.category_id)
+            .await?;
+        let mut tx = self.pool.begin().await?;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO products (category_id, name, description, price, create_by, update_by) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id
+        "#,
+        )
+        .bind(input.product.category_id)
+        .bind(&input.product.name)
+        .bind(&input.product.description)
+        .bind(input.product.price)
+        .bind(&create_by)
+        .bind(&create_by)
+        .fetch_one(&mut *tx)
+        .await?;
+        let attributes = set_product_attributes(&mut tx, id, &input.product.attributes).await?;
+        for sku in &input.skus {
+            check_sku(&attributes, sku)?;
+            insert_sku(&mut tx, id, sku).await?;
+        }
+        tx.commit().await?;
+        info!("product {} created with {} skus", id, input.skus.len());
+        self.find_product_detail(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("product id {}", id)))
+    }
+
+    // 替换商品的规格定义，已有 SKU 必须仍然符合新的规格
+    pub async fn update_product(
+        &self,
+        id: i64,
+        input: &OperateProduct,
+        update_by: String,
+    ) -> Result<ProductDetail, AppError> {
+        check_product(input)?;
+        if self.find_product_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("product id {}", id)));
+        }
+        self.check_product_category(input.category_id).await?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE products SET category_id = $1, name = $2, description = $3, price = $4, update_by = $5, update_time = $6 WHERE id = $7
+        "#,
+        )
+        .bind(input.category_id)
+        .bind(&input.name)
+        .bind(&input.description)
+        .bind(input.price)
+        .bind(update_by)
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let attributes = set_product_attributes(&mut tx, id, &input.attributes).await?;
+        let skus: Vec<Sku> = sqlx::query_as(&format!(
+            "SELECT {} FROM product_skus WHERE product_id = $1",
+            SKU_COLUMNS
+        ))
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for sku in &skus {
+            check_sku_attributes(&attributes, &sku.attributes)
+                .map_err(|e| AppError::InvalidProduct(format!("sku {} {}", sku.sku_code, e)))?;
+        }
+        tx.commit().await?;
+        self.find_product_detail(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("product id {}", id)))
+    }
+
+    // 上架的商品需要先下架才能删除，SKU 和规格随商品一起删除
+    pub async fn delete_product(&self, id: i64) -> Result<bool, AppError> {
+        let Some(product) = self.find_product_by_id(id).await? else {
+            return Err(AppError::NotFound(format!("product id {}", id)));
+        };
+        if product.status == ProductStatus::OnSale {
+            return Err(AppError::InvalidProduct(format!(
+                "product {} is on sale",
+                id
+            )));
+        }
+        let mut tx = self.pool.begin().await?;
+        let sku_ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM product_skus WHERE product_id = $1 FOR UPDATE
+        "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        check_sku_deletable(&mut tx, &sku_ids).await?;
+        let result = sqlx::query(
+            r#"
+            DELETE FROM products WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // 上架时至少要有一个启用的 SKU
+    pub async fn set_product_status(
+        &self,
+        id: i64,
+        status: ProductStatus,
+        update_by: String,
+    ) -> Result<Product, AppError> {
+        if self.find_product_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("product id {}", id)));
+        }
+        if status == ProductStatus::OnSale {
+            let has_sku: bool = sqlx::query_scalar(
+                r#"
+                SELECT EXISTS(SELECT 1 FROM product_skus WHERE product_id = $1 AND status = 'enable')
+            "#,
+            )
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+            if !has_sku {
+                return Err(AppError::InvalidProduct(format!(
+                    "product {} has no enabled sku",
+                    id
+                )));
+            }
+        }
+        let product = sqlx::query_as(&format!(
+            "UPDATE products SET status = $1, update_by = $2, update_time = $3 WHERE id = $4 RETURNING {}",
+            PRODUCT_COLUMNS
+        ))
+        .bind(status)
+        .bind(update_by)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(product)
+    }
+
+    pub async fn find_product_by_id(&self, id: i64) -> Result<Option<Product>, AppError> {
+        let product = sqlx::query_as(&format!(
+            "SELECT {} FROM products WHERE id = $1",
+            PRODUCT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(product)
+    }
+
+    pub async fn find_product_detail(&self, id: i64) -> Result<Option<ProductDetail>, AppError> {
+        let Some(product) = self.find_product_by_id(id).await? else {
+            return Ok(None);
+        };
+        let attributes = self.find_product_attributes(id).await?;
+        let skus = sqlx::query_as(&format!(
+            "SELECT {} FROM product_skus WHERE product_id = $1 ORDER BY id",
+            SKU_COLUMNS
+        ))
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Some(ProductDetail {
+            product,
+            attributes,
+            skus,
+        }))
+    }
+
+    pub async fn find_product_by_condition(
+        &self,
+        input: &SearchProduct,
+        page: Page,
+    ) -> Result<(Vec<Product>, i64), AppError> {
+        QueryFilter::new()
+            .contains("name", input.name.as_deref())
+            .eq("category_id", input.category_id)
+            .eq("status", input.status.clone())
+            .fetch_page(&self.pool, PRODUCT_COLUMNS, "products", "id DESC", page)
+            .await
+    }
+
+    pub async fn create_sku(&self, product_id: i64, input: &OperateSku) -> Result<Sku, AppError> {
+        if self.find_product_by_id(product_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("product id {}", product_id)));
+        }
+        let attributes = self.find_product_attributes(product_id).await?;
+        check_sku(&attributes, input)?;
+        self.check_sku_conflict(product_id, None, input).await?;
+        let mut tx = self.pool.begin().await?;
+        let sku = insert_sku(&mut tx, product_id, input).await?;
+        tx.commit().await?;
+        Ok(sku)
+    }
+
+    pub async fn update_sku(
+        &self,
+        product_id: i64,
+        id: i64,
+        input: &OperateSku,
+    ) -> Result<Sku, AppError> {
+        if self.find_sku_by_id(product_id, id).await?.is_none() {
+            return Err(AppError::NotFound(format!("sku id {}", id)));
+        }
+        let attributes = self.find_product_attributes(product_id).await?;
+        check_sku(&attributes, input)?;
+        self.check_sku_conflict(product_id, Some(id), input).await?;
+        let sku = sqlx::query_as(&format!(
+            "UPDATE product_skus SET sku_code = $1, price = $2, attributes = $3, status = $4, update_time = $5 WHERE id = $6 RETURNING {}",
+            SKU_COLUMNS
+        ))
+        .bind(&input.sku_code)
+        .bind(input.price)
+        .bind(Json(&input.attributes))
+        .bind(&input.status)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(sku)
+    }
+
+    pub async fn delete_sku(&self, product_id: i64, id: i64) -> Result<bool, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let sku_id: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM product_skus WHERE id = $1 AND product_id = $2 FOR UPDATE
+        "#,
+        )
+        .bind(id)
+        .bind(product_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if sku_id.is_none() {
+            return Err(AppError::NotFound(format!("sku id {}", id)));
+        }
+        check_sku_deletable(&mut tx, &[id]).await?;
+        sqlx::query(
+            r#"
+            DELETE FROM product_skus WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn find_sku_by_id(&self, product_id: i64, id: i64) -> Result<Option<Sku>, AppError> {
+        let sku = sqlx::query_as(&format!(
+            "SELECT {} FROM product_skus WHERE id = $1 AND product_id = $2",
+            SKU_COLUMNS
+        ))
+        .bind(id)
+        .bind(product_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(sku)
+    }
+
+    async fn find_product_attributes(
+        &self,
+        product_id: i64,
+    ) -> Result<Vec<ProductAttribute>, AppError> {
+        let attributes = sqlx::query_as(
+            r#"
+            SELECT id, product_id, name, options, order_num FROM product_attributes WHERE product_id = $1 ORDER BY order_num
+        "#,
+        )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(attributes)
+    }
+
+    async fn check_product_category(&self, category_id: Option<i64>) -> Result<(), AppError> {
+        if let Some(category_id) = category_id {
+            if self.find_category_by_id(category_id).await?.is_none() {
+                return Err(AppError::NotFound(format!("category id {}", category_id)));
+            }
+        }
+        Ok(())
+    }
+
+    // SKU 编码全局唯一，同一商品下规格组合不能重复
+    async fn check_sku_conflict(
+        &self,
+        product_id: i64,
+        id: Option<i64>,
+        input: &OperateSku,
+    ) -> Result<(), AppError> {
+        let code_existed: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM product_skus WHERE sku_code = $1 AND id IS DISTINCT FROM $2)
+        "#,
+        )
+        .bind(&input.sku_code)
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if code_existed {
+            return Err(AppError::SkuAlreadyExisted(input.sku_code.clone()));
+        }
+        let combination_existed: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM product_skus WHERE product_id = $1 AND attributes = $2 AND id IS DISTINCT FROM $3)
+        "#,
+        )
+        .bind(product_id)
+        .bind(Json(&input.attributes))
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if combination_existed {
+            return Err(AppError::InvalidProduct(format!(
+                "duplicate sku attributes: {:?}",
+                input.attributes
+            )));
+        }
+        Ok(())
+    }
+}
+
+async fn set_product_attributes(
+    tx: &mut Transaction<'_, Postgres>,
+    product_id: i64,
+    attributes: &[OperateAttribute],
+) -> Result<Vec<ProductAttribute>, AppError> {
+    sqlx::query(
+        r#"
+        DELETE FROM product_attributes WHERE product_id = $1
+    "#,
+    )
+    .bind(product_id)
+    .execute(&mut **tx)
+    .await?;
+    let mut result = Vec::with_capacity(attributes.len());
+    for (order_num, attribute) in (0..).zip(attributes.iter()) {
+        let attribute = sqlx::query_as(
+            r#"
+            INSERT INTO product_attributes (product_id, name, options, order_num) VALUES ($1, $2, $3, $4)
+            RETURNING id, product_id, name, options, order_num
+        "#,
+        )
+        .bind(product_id)
+        .bind(&attribute.name)
+        .bind(&attribute.options)
+        .bind(order_num)
+        .fetch_one(&mut **tx)
+        .await?;
+        result.push(attribute);
+    }
+    Ok(result)
+}
+
+async fn insert_sku(
+    tx: &mut Transaction<'_, Postgres>,
+    product_id: i64,
+    input: &OperateSku,
+) -> Result<Sku, AppError> {
+    let sku = sqlx::query_as(&format!(
+        "INSERT INTO product_skus (product_id, sku_code, price, attributes, status) VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+        SKU_COLUMNS
+    ))
+    .bind(product_id)
+    .bind(&input.sku_code)
+    .bind(input.price)
+    .bind(Json(&input.attributes))
+    .bind(&input.status)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|e| match e {
+        // 同一批次中的重复编码或组合由唯一索引拦截
+        sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+            AppError::SkuAlreadyExisted(input.sku_code.clone())
+        }
+        e => e.into(),
+    })?;
+    Ok(sku)
+}
+
+fn check_product(input: &OperateProduct) -> Result<(), AppError> {
+    if input.name.trim().is_empty() {
+        return Err(AppError::InvalidProduct("name is required".to_string()));
+    }
+    if input.price < 0 {
+        return Err(AppError::InvalidProduct(format!(
+            "invalid price: {}",
+            input.price
+        )));
+    }
+    let mut names = HashSet::new();
+    for attribute in &input.attributes {
+        if attribute.name.trim().is_empty() || !names.insert(attribute.name.as_str()) {
+            return Err(AppError::InvalidProduct(format!(
+                "invalid attribute name: {}",
+                attribute.name
+            )));
+        }
+        let options: HashSet<&String> = attribute.options.iter().collect();
+        if options.is_empty() || options.len() != attribute.options.len() {
+            return Err(AppError::InvalidProduct(format!(
+                "attribute {} must have distinct options",
+                attribute.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_sku(attributes: &[ProductAttribute], input: &OperateSku) -> Result<(), AppError> {
+    if input.sku_code.trim().is_empty() {
+        return Err(AppError::InvalidProduct("sku code is required".to_string()));
+    }
+    if input.price < 0 {
+        return Err(AppError::InvalidProduct(format!(
+            "invalid price: {}",
+            input.price
+        )));
+    }
+    check_sku_attributes(attributes, &input.attributes)
+        .map_err(|e| AppError::InvalidProduct(format!("sku {} {}", input.sku_code, e)))
+}
+
+// 删除 SKU 会级联删除库存记录，仍有在库或预留数量时删除会让库存流水无法对账；
+// 调拨单和订单引用过的 SKU 需要保留。调用前需锁定 SKU，避免检查后再有新的仓库入库
+async fn check_sku_deletable(
+    tx: &mut Transaction<'_, Postgres>,
+    sku_ids: &[i64],
+) -> Result<(), AppError> {
+    let used: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT sku_id FROM stock_transfer_items WHERE sku_id = ANY($1)
+        UNION ALL
+        SELECT sku_id FROM order_items WHERE sku_id = ANY($1)
+        LIMIT 1
+    "#,
+    )
+    .bind(sku_ids)
+    .fetch_optional(&mut **tx)
+    .await?;
+    if let Some(sku_id) = used {
+        return Err(AppError::SkuInUse(sku_id));
+    }
+    let stocked: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT sku_id, on_hand, reserved FROM inventories WHERE sku_id = ANY($1) FOR UPDATE
+    "#,
+    )
+    .bind(sku_ids)
+    .fetch_all(&mut **tx)
+    .await?;
+    if let Some((sku_id, _, _)) = stocked
+        .iter()
+        .find(|(_, on_hand, reserved)| *on_hand > 0 || *reserved > 0)
+    {
+        return Err(AppError::SkuHasStock(*sku_id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_product {
+    use super::*;
+    use anyhow::Result;
+
+    fn operate_sku(code: &str, color: &str, size: &str) -> OperateSku {
+        OperateSku {
+            sku_code: code.to_string(),
+            price: 1999,
+            attributes: BTreeMap::from([
+                ("color".to_string(), color.to_string()),
+                ("size".to_string(), size.to_string()),
+            ]),
+            status: EffectStatus::Enable,
+        }
+    }
+
+    fn shirt() -> CreateProduct {
+        CreateProduct {
+            product: OperateProduct {
+                category_id: None,
+                name: "T-Shirt".to_string(),
+                description: "cotton".to_string(),
+                price: 1999,
+                attributes: vec![
+                    OperateAttribute {
+                        name: "color".to_string(),
+                        options: vec!["red".to_string(), "blue".to_string()],
+                    },
+                    OperateAttribute {
+                        name: "size".to_string(),
+                        options: vec!["S".to_string(), "M".to_string()],
+                    },
+                ],
+            },
+            skus: vec![operate_sku("TS-RED-S", "red", "S")],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_product_with_skus_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let detail = state.create_product(&shirt(), "admin".to_string()).await?;
+        assert_eq!(detail.product.status, ProductStatus::Draft);
+        assert_eq!(detail.attributes.len(), 2);
+        assert_eq!(detail.skus.len(), 1);
+        let id = detail.product.id;
+
+        let sku = state
+            .create_sku(id, &operate_sku("TS-BLUE-M", "blue", "M"))
+            .await?;
+        assert_eq!(sku.attributes["size"], "M");
+        // 重复的编码和规格组合
+        let ret = state
+            .create_sku(id, &operate_sku("TS-BLUE-M", "red", "M"))
+            .await;
+        assert!(matches!(ret, Err(AppError::SkuAlreadyExisted(_))));
+        let ret = state
+            .create_sku(id, &operate_sku("TS-OTHER", "blue", "M"))
+            .await;
+        assert!(matches!(ret, Err(AppError::InvalidProduct(_))));
+        let ret = state
+            .create_sku(id, &operate_sku("TS-GREEN", "green", "M"))
+            .await;
+        assert!(matches!(ret, Err(AppError::InvalidProduct(_))));
+
+        let (products, total) = state
+            .find_product_by_condition(
+                &SearchProduct {
+                    name: Some("shirt".to_string()),
+                    ..SearchProduct::default()
+                },
+                Page::new(1, 10)?,
+            )
+            .await?;
+        assert_eq!(total, 1);
+        assert_eq!(products[0].id, id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_product_status_and_attributes_should_be_checked() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let mut input = shirt();
+        input.skus.clear();
+        let detail = state.create_product(&input, "admin".to_string()).await?;
+        let id = detail.product.id;
+
+        let ret = state
+            .set_product_status(id, ProductStatus::OnSale, "admin".to_string())
+            .await;
+        assert!(matches!(ret, Err(AppError::InvalidProduct(_))));
+        state
+            .create_sku(id, &operate_sku("TS-RED-S", "red", "S"))
+            .await?;
+        let product = state
+            .set_product_status(id, ProductStatus::OnSale, "admin".to_string())
+            .await?;
+        assert_eq!(product.status, ProductStatus::OnSale);
+        assert!(matches!(
+            state.delete_product(id).await,
+            Err(AppError::InvalidProduct(_))
+        ));
+
+        // 删除 red 后已有 SKU 不再符合规格
+        let mut update = input.product.clone();
+        update.attributes[0].options = vec!["blue".to_string()];
+        let ret = state.update_product(id, &update, "admin".to_string()).await;
+        assert!(matches!(ret, Err(AppError::InvalidProduct(_))));
+        let detail = state.find_product_detail(id).await?.unwrap();
+        assert_eq!(detail.attributes[0].options, vec!["red", "blue"]);
+
+        state
+            .set_product_status(id, ProductStatus::OffShelf, "admin".to_string())
+            .await?;
+        assert!(state.delete_product(id).await?);
+        assert!(state.find_product_detail(id).await?.is_none());
+        Ok(())
+    }
+}
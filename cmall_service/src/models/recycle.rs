@@ -0,0 +1,285 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{error::AppError, AppState, Page, QueryFilter};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchRecycle {
+    // user / role / dept / menu，为空时查询全部
+    pub target_type: Option<String>,
+    #[serde(default = "default_page_num")]
+    pub page_num: i64,
+    #[serde(default = "default_page_size")]
+    pub page_size: i64,
+}
+
+// 各类已软删除数据的统一视图
+const RECYCLE_ITEMS: &str = r#"(
+    SELECT 'user' AS target_type, id, username AS name, deleted_at FROM users WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'role', id, name, deleted_at FROM roles WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'dept', id, name, deleted_at FROM departments WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'menu', id, menu_id, deleted_at FROM menus WHERE deleted_at IS NOT NULL
+) AS items"#;
+
+fn default_page_num() -> i64 {
+    1
+}
+
+fn default_page_size() -> i64 {
+    20
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecycleItem {
+    pub target_type: String,
+    pub id: i64,
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeOutput {
+    pub users: u64,
+    pub roles: u64,
+    pub departments: u64,
+    pub menus: u64,
+}
+
+impl AppState {
+    // 查询回收站中已软删除的数据
+    pub async fn find_recycle_items(
+        &self,
+        input: &SearchRecycle,
+        page: Page,
+    ) -> Result<(Vec<RecycleItem>, i64), AppError> {
+        QueryFilter::new()
+            .eq("target_type", input.target_type.clone())
+            .fetch_page(
+                &self.pool,
+                "target_type, id, name, deleted_at",
+                RECYCLE_ITEMS,
+                "deleted_at DESC, id DESC",
+                page,
+            )
+            .await
+    }
+
+    // 按配置的保留时长清理回收站
+    pub async fn purge_expired(&self) -> Result<PurgeOutput, AppError> {
+        let before = Utc::now() - Duration::seconds(self.config.recycle.retention as i64);
+        self.purge_deleted(before).await
+    }
+
+    // 彻底删除 before 之前软删除的数据，仍被引用的部门留到下次清理。
+    // 订单需要长期保留，下过单的用户不会被彻底删除
+    pub async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<PurgeOutput, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let user_ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM users u WHERE u.deleted_at < $1
+            AND NOT EXISTS (SELECT 1 FROM orders o WHERE o.user_id = u.id)
+        "#,
+        )
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        for table in [
+            "user_roles",
+            "user_sessions",
+            "password_resets",
+            "mfa_challenges",
+            "mfa_recovery_codes",
+            "user_mfa",
+        ] {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = ANY($1)", table))
+                .bind(&user_ids)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let users = sqlx::query(
+            r#"
+            DELETE FROM users WHERE id = ANY($1)
+        "#,
+        )
+        .bind(&user_ids)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let role_ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM roles WHERE deleted_at < $1
+        "#,
+        )
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        for table in ["role_menus", "user_roles"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE role_id = ANY($1)", table))
+                .bind(&role_ids)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let roles = sqlx::query(
+            r#"
+            DELETE FROM roles WHERE id = ANY($1)
+        "#,
+        )
+        .bind(&role_ids)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // 部门被用户或下级部门引用时不能删除，逐层从叶子部门开始清理
+        let mut departments = 0;
+        loop {
+            let affected = sqlx::query(
+                r#"
+                DELETE FROM departments d WHERE d.deleted_at < $1
+                AND NOT EXISTS (SELECT 1 FROM users u WHERE u.dept_id = d.id)
+                AND NOT EXISTS (SELECT 1 FROM departments c WHERE c.parent_id = d.id)
+            "#,
+            )
+            .bind(before)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if affected == 0 {
+                break;
+            }
+            departments += affected;
+        }
+
+        sqlx::query(
+            r#"
+            DELETE FROM role_menus WHERE menu_id IN (SELECT id FROM menus WHERE deleted_at < $1)
+        "#,
+        )
+        .bind(before)
+        .execute(&mut *tx)
+        .await?;
+        let menus = sqlx::query(
+            r#"
+            DELETE FROM menus WHERE deleted_at < $1
+        "#,
+        )
+        .bind(before)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+        Ok(PurgeOutput {
+            users,
+            roles,
+            departments,
+            menus,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_recycle {
+    use super::*;
+    use anyhow::Result;
+
+    use crate::{CreateUser, OperateRole};
+    use cmall_core::EffectStatus;
+
+    #[tokio::test]
+    async fn test_soft_delete_should_restore_and_purge() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let user = state
+            .create_user(&CreateUser::new("trash", "trash@qq.com", "139", "hunter42"))
+            .await?;
+        let role = state
+            .create_role(
+                &OperateRole {
+                    code: "trash".to_string(),
+                    name: "trash".to_string(),
+                    description: "".to_string(),
+                    status: EffectStatus::Enable,
+                },
+                "admin".to_string(),
+            )
+            .await?;
+
+        state.delete_user(user.id).await?;
+        state.delete_role(role.id).await?;
+        assert!(state.find_user_by_id(user.id).await?.is_none());
+        assert!(state.find_role_by_id(role.id).await?.is_none());
+        // 已删除的邮箱仍被占用
+        let ret = state
+            .create_user(&CreateUser::new("trash", "trash@qq.com", "139", "hunter42"))
+            .await;
+        assert!(ret.is_err());
+
+        let input = SearchRecycle {
+            page_num: 1,
+            page_size: 10,
+            ..SearchRecycle::default()
+        };
+        let page = Page::new(input.page_num, input.page_size)?;
+        let (items, total) = state.find_recycle_items(&input, page).await?;
+        assert_eq!(total, 2);
+        assert_eq!(items.len(), 2);
+        let input = SearchRecycle {
+            target_type: Some("user".to_string()),
+            ..input
+        };
+        let (items, total) = state.find_recycle_items(&input, page).await?;
+        assert_eq!(total, 1);
+        assert_eq!(items[0].id, user.id);
+
+        let restored = state.restore_user(user.id).await?;
+        assert_eq!(restored.roles, vec![1]);
+        assert!(state.restore_user(user.id).await.is_err());
+
+        // 保留期内不清理
+        let purged = state.purge_deleted(Utc::now() - Duration::days(30)).await?;
+        assert_eq!(purged, PurgeOutput::default());
+        let purged = state.purge_deleted(Utc::now()).await?;
+        assert_eq!(purged.roles, 1);
+        assert_eq!(purged.users, 0);
+        assert!(state
+            .restore_role(role.id, "admin".to_string())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge_should_keep_users_with_orders() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let buyer = state
+            .create_user(&CreateUser::new("buyer", "buyer@qq.com", "139", "hunter42"))
+            .await?;
+        let trash = state
+            .create_user(&CreateUser::new("trash", "trash@qq.com", "139", "hunter42"))
+            .await?;
+        sqlx::query("INSERT INTO orders (user_id, total_amount) VALUES ($1, 100)")
+            .bind(buyer.id)
+            .execute(&state.pool)
+            .await?;
+        state.delete_user(buyer.id).await?;
+        state.delete_user(trash.id).await?;
+
+        let purged = state.purge_deleted(Utc::now()).await?;
+        assert_eq!(purged.users, 1);
+        let (items, _) = state
+            .find_recycle_items(&SearchRecycle::default(), Page::new(1, 10)?)
+            .await?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, buyer.id);
+        Ok(())
+    }
+}
@@ -2,7 +2,9 @@ use cmall_core::{EffectStatus, Role};
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::{error::AppError, AppState};
+use crate::{error::AppError, AppState, ExportColumn, Exportable, Page, QueryFilter};
+
+const ROLE_COLUMNS: &str = "id, code, name, description, mfa_required, create_time, create_by, status, update_time, update_by";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OperateRole {
@@ -12,18 +14,62 @@ pub struct OperateRole {
     pub status: EffectStatus,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleMfa {
+    pub required: bool,
+}
+
+impl Exportable for Role {
+    const NAME: &'static str = "roles";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("id", "编号", "ID", |r: &Role| r.id.into()),
+            ExportColumn::new("code", "编码", "Code", |r: &Role| r.code.as_str().into())
+                .width(15.0),
+            ExportColumn::new("name", "名称", "Name", |r: &Role| r.name.as_str().into())
+                .width(15.0),
+            ExportColumn::new("status", "状态", "Status", |r: &Role| {
+                r.status.to_string().into()
+            }),
+            ExportColumn::new(
+                "mfaRequired",
+                "强制两步验证",
+                "MFA Required",
+                |r: &Role| r.mfa_required.into(),
+            ),
+            ExportColumn::new("description", "描述", "Description", |r: &Role| {
+                r.description.as_str().into()
+            })
+            .width(30.0),
+            ExportColumn::new("createTime", "创建时间", "Create Time", |r: &Role| {
+                r.create_time.into()
+            })
+            .width(25.0),
+        ]
+    }
+}
+
 impl AppState {
     pub async fn create_role(
         &self,
         input: &OperateRole,
         create_by: String,
     ) -> Result<Role, AppError> {
-        let role = self.find_role_by_code(input.code.clone()).await?;
-        if role.is_some() {
+        // 已软删除的角色仍占用编码
+        let existed: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM roles WHERE code = $1)
+        "#,
+        )
+        .bind(&input.code)
+        .fetch_one(&self.pool)
+        .await?;
+        if existed {
             return Err(AppError::RoleAlreadyExisted(input.code.clone()));
         }
         let role = sqlx::query_as(r#"
-            insert into roles (code, name, description, status, create_by, update_by) values ($1, $2, $3, $4, $5, $6) returning id, code, name, status, description, create_time, create_by, update_time, update_by
+            insert into roles (code, name, description, status, create_by, update_by) values ($1, $2, $3, $4, $5, $6) returning id, code, name, status, description, mfa_required, create_time, create_by, update_time, update_by
         "#).bind(&input.code)
             .bind(&input.name)
             .bind(&input.description)
@@ -35,32 +81,52 @@ impl AppState {
         Ok(role)
     }
 
-    pub async fn update_role(&self, id: i64, input: &OperateRole) -> Result<Role, AppError> {
+    pub async fn update_role(
+        &self,
+        id: i64,
+        input: &OperateRole,
+        update_by: String,
+    ) -> Result<Role, AppError> {
         let role = self.find_role_by_id(id).await?;
         if role.is_none() {
             return Err(AppError::NotFound(format!("role id {}", id)));
         }
         let role = sqlx::query_as(r#"
-            update roles set name = $1, description = $2, code = $3 where id = $4 returning id, code, name, description, create_time, create_by, status, update_time, update_by
+            update roles set name = $1, description = $2, code = $3, update_by = $4, update_time = $5 where id = $6 returning id, code, name, description, mfa_required, create_time, create_by, status, update_time, update_by
         "#)
         .bind(&input.name)
         .bind(&input.description)
         .bind(&input.code)
+        .bind(update_by)
+        .bind(chrono::Utc::now())
         .bind(id)
         .fetch_one(&self.pool)
         .await?;
         Ok(role)
     }
+    // 软删除角色，保留菜单授权以便恢复
     pub async fn delete_role(&self, id: i64) -> Result<bool, AppError> {
         let role = self.find_role_by_id(id).await?;
         if role.is_none() {
             return Err(AppError::NotFound(format!("role id {}", id)));
         }
+        let user_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM user_roles ur JOIN users u ON u.id = ur.user_id WHERE ur.role_id = $1 AND u.deleted_at IS NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if user_count > 0 {
+            return Err(AppError::RoleHasUsers(id));
+        }
         let result = sqlx::query(
             r#"
-            DELETE FROM roles WHERE id = $1
+            UPDATE roles SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL
         "#,
         )
+        .bind(chrono::Utc::now())
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -70,51 +136,57 @@ impl AppState {
         Ok(true)
     }
 
+    pub async fn restore_role(&self, id: i64, update_by: String) -> Result<Role, AppError> {
+        let role = sqlx::query_as(r#"
+            update roles set deleted_at = null, update_by = $1, update_time = $2 where id = $3 and deleted_at is not null returning id, code, name, description, mfa_required, create_time, create_by, status, update_time, update_by
+        "#)
+        .bind(update_by)
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        role.ok_or_else(|| AppError::NotFound(format!("deleted role id {}", id)))
+    }
+
+    // 要求拥有该角色的用户开启两步验证
+    pub async fn set_role_mfa_required(
+        &self,
+        id: i64,
+        required: bool,
+        update_by: String,
+    ) -> Result<Role, AppError> {
+        let role = sqlx::query_as(r#"
+            update roles set mfa_required = $1, update_by = $2, update_time = $3 where id = $4 and deleted_at is null returning id, code, name, description, mfa_required, create_time, create_by, status, update_time, update_by
+        "#)
+        .bind(required)
+        .bind(update_by)
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        role.ok_or_else(|| AppError::NotFound(format!("role id {}", id)))
+    }
+
     pub async fn find_role_by_condition(
         &self,
         code: Option<&str>,
         status: Option<EffectStatus>,
-        page_num: i64,
-        page_size: i64,
+        page: Page,
     ) -> Result<(Vec<Role>, i64), AppError> {
-        // 需要根据分页信息查询
-        let offset = (page_num - 1) * page_size;
-        let roles = sqlx::query_as(
-            r#"
-            select id, code, name, description, create_time, create_by, status, update_time, update_by from roles 
-            where (code = $1 or $1 is null) 
-            and (status = $2 or $2 is null) 
-            limit $3 offset $4
-        "#,
-        )
-        .bind(code)
-        .bind(status.clone())
-        .bind(page_size)
-        .bind(offset)
-        .fetch_all(&self.pool)
-        .await
-        .unwrap();
+        let (roles, total_count) = QueryFilter::new()
+            .raw("deleted_at IS NULL")
+            .eq("code", code.map(str::to_string))
+            .eq("status", status)
+            .fetch_page(&self.pool, ROLE_COLUMNS, "roles", "id", page)
+            .await?;
         info!("find role by condition: {:?}", roles);
-
-        // 获取满足条件的总记录数
-        let total_count: i64 = sqlx::query_scalar(
-            r#"
-            SELECT COUNT(*) FROM roles
-            WHERE (code = $1 OR $1 IS NULL)
-            AND (status = $2 OR $2 IS NULL)
-            "#,
-        )
-        .bind(code)
-        .bind(status)
-        .fetch_one(&self.pool)
-        .await?;
         Ok((roles, total_count))
     }
 
     pub async fn find_role_by_id(&self, id: i64) -> Result<Option<Role>, AppError> {
         let role = sqlx::query_as::<_, Role>(
             r#"
-            select id, code, name, description, create_time, create_by, status, update_time, update_by from roles where id = $1
+            select id, code, name, description, mfa_required, create_time, create_by, status, update_time, update_by from roles where id = $1 and deleted_at is null
         "#,
         )
         .bind(id)
@@ -125,7 +197,7 @@ impl AppState {
     pub async fn find_role_by_code(&self, code: String) -> Result<Option<Role>, AppError> {
         let role = sqlx::query_as::<_, Role>(
             r#"
-            select id, code, name, description, create_time, create_by, status, update_time, update_by from roles where code = $1
+            select id, code, name, description, mfa_required, create_time, create_by, status, update_time, update_by from roles where code = $1 and deleted_at is null
         "#,
         )
         .bind(code)
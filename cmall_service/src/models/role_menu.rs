@@ -0,0 +1,186 @@
+use cmall_core::{Menu, MenuNode, MenuType};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, AppState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleMenus {
+    pub menu_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserMenus {
+    pub menus: Vec<MenuNode>,
+    pub permissions: Vec<String>,
+}
+
+impl AppState {
+    pub async fn find_role_menu_ids(&self, role_id: i64) -> Result<Vec<i64>, AppError> {
+        if self.find_role_by_id(role_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("role id {}", role_id)));
+        }
+        let menu_ids = sqlx::query_scalar(
+            r#"
+            SELECT rm.menu_id FROM role_menus rm JOIN menus m ON m.id = rm.menu_id
+            WHERE rm.role_id = $1 AND m.deleted_at IS NULL
+            ORDER BY rm.menu_id
+        "#,
+        )
+        .bind(role_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(menu_ids)
+    }
+
+    pub async fn assign_role_menus(
+        &self,
+        role_id: i64,
+        menu_ids: &[i64],
+    ) -> Result<Vec<i64>, AppError> {
+        if self.find_role_by_id(role_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("role id {}", role_id)));
+        }
+        let existed: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM menus WHERE id = ANY($1) AND deleted_at IS NULL
+        "#,
+        )
+        .bind(menu_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        let missing: Vec<i64> = menu_ids
+            .iter()
+            .filter(|id| !existed.contains(id))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::NotFound(format!("menu ids {:?}", missing)));
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO role_menus (role_id, menu_id) SELECT $1, unnest($2::BIGINT[])
+            ON CONFLICT DO NOTHING
+        "#,
+        )
+        .bind(role_id)
+        .bind(menu_ids)
+        .execute(&self.pool)
+        .await?;
+        self.find_role_menu_ids(role_id).await
+    }
+
+    pub async fn revoke_role_menus(
+        &self,
+        role_id: i64,
+        menu_ids: &[i64],
+    ) -> Result<Vec<i64>, AppError> {
+        if self.find_role_by_id(role_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("role id {}", role_id)));
+        }
+        sqlx::query(
+            r#"
+            DELETE FROM role_menus WHERE role_id = $1 AND menu_id = ANY($2)
+        "#,
+        )
+        .bind(role_id)
+        .bind(menu_ids)
+        .execute(&self.pool)
+        .await?;
+        self.find_role_menu_ids(role_id).await
+    }
+
+    // 根据用户的已启用角色查询可见的已启用菜单（含按钮）
+    pub async fn find_user_granted_menus(&self, user_id: i64) -> Result<Vec<Menu>, AppError> {
+        let menus = sqlx::query_as(
+            r#"
+            SELECT DISTINCT m.id, m.menu_id, m.path, m.chinese_name, m.english_name, m.icon, m.order_num, m.type, m.parent_menu_id, m.status, m.description, m.create_time, m.create_by, m.update_time, m.update_by
+            FROM menus m
+            JOIN role_menus rm ON rm.menu_id = m.id
+            JOIN roles r ON r.id = rm.role_id
+            JOIN user_roles ur ON ur.role_id = r.id
+            WHERE ur.user_id = $1
+            AND r.status = 'enable'
+            AND m.status = 'enable'
+            AND r.deleted_at IS NULL
+            AND m.deleted_at IS NULL
+        "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(menus)
+    }
+
+    pub async fn find_user_permissions(&self, user_id: i64) -> Result<Vec<String>, AppError> {
+        let mut permissions: Vec<String> = self
+            .find_user_granted_menus(user_id)
+            .await?
+            .into_iter()
+            .filter(|m| m.menu_type == MenuType::Button)
+            .map(|m| m.menu_id)
+            .collect();
+        permissions.sort();
+        Ok(permissions)
+    }
+
+    pub async fn find_user_menus(&self, user_id: i64) -> Result<UserMenus, AppError> {
+        let (buttons, menus): (Vec<Menu>, Vec<Menu>) = self
+            .find_user_granted_menus(user_id)
+            .await?
+            .into_iter()
+            .partition(|m| m.menu_type == MenuType::Button);
+        let mut permissions: Vec<String> = buttons.into_iter().map(|m| m.menu_id).collect();
+        permissions.sort();
+        Ok(UserMenus {
+            menus: MenuNode::build_tree(menus),
+            permissions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_role_menu {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_user_menus_should_follow_role_menus() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        // user 1 only has the admin role, which is granted every seeded menu
+        let ret = state.find_user_menus(1).await?;
+        assert_eq!(ret.menus.len(), 2);
+        assert_eq!(ret.menus[0].menu.menu_id, "system");
+        assert_eq!(ret.menus[1].menu.menu_id, "mall");
+        assert!(ret.permissions.contains(&"user:delete".to_string()));
+
+        let menu = state.find_menu_by_menu_id("user:delete").await?.unwrap();
+        let menu_ids = state.revoke_role_menus(1, &[menu.id]).await?;
+        assert!(!menu_ids.contains(&menu.id));
+        let permissions = state.find_user_permissions(1).await?;
+        assert!(!permissions.contains(&"user:delete".to_string()));
+
+        let menu_ids = state.assign_role_menus(1, &[menu.id]).await?;
+        assert!(menu_ids.contains(&menu.id));
+
+        let ret = state.assign_role_menus(1, &[404]).await;
+        assert!(matches!(ret, Err(AppError::NotFound(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_role_should_grant_nothing() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        // user 2 has admin, operator and the disabled viewer role
+        let menu = state.find_menu_by_menu_id("role:delete").await?.unwrap();
+        state.assign_role_menus(3, &[menu.id]).await?;
+        state.revoke_role_menus(1, &[menu.id]).await?;
+        let permissions = state.find_user_permissions(2).await?;
+        assert!(!permissions.contains(&"role:delete".to_string()));
+        assert!(permissions.contains(&"user:create".to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Duration, Utc};
+use cmall_core::User;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, Postgres, Transaction};
+
+use crate::{error::AppError, AppState};
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSession {
+    pub id: i64,
+    pub user_id: i64,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthTokens {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshToken {
+    pub refresh_token: String,
+}
+
+impl AppState {
+    // 登录成功后创建会话，签发 access token 和 refresh token
+    pub async fn create_session(&self, user: &User) -> Result<AuthTokens, AppError> {
+        let refresh_token = generate_token();
+        let expires_at = Utc::now() + self.refresh_expires_in();
+        let session: UserSession = sqlx::query_as(
+            r#"
+            INSERT INTO user_sessions (user_id, refresh_token_hash, expires_at) VALUES ($1, $2, $3)
+            RETURNING id, user_id, expires_at, revoked_at, create_time, update_time
+        "#,
+        )
+        .bind(user.id)
+        .bind(hash_token(&refresh_token))
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await?;
+        self.issue_tokens(user, &session, refresh_token)
+    }
+
+    // 使用 refresh token 换取新的令牌，旧 refresh token 随即失效
+    pub async fn refresh_session(
+        &self,
+        refresh_token: &str,
+    ) -> Result<(User, AuthTokens), AppError> {
+        let new_refresh_token = generate_token();
+        let expires_at = Utc::now() + self.refresh_expires_in();
+        let session: Option<UserSession> = sqlx::query_as(
+            r#"
+            UPDATE user_sessions SET refresh_token_hash = $1, expires_at = $2, update_time = $3
+            WHERE refresh_token_hash = $4 AND revoked_at IS NULL AND expires_at > $3
+            RETURNING id, user_id, expires_at, revoked_at, create_time, update_time
+        "#,
+        )
+        .bind(hash_token(&new_refresh_token))
+        .bind(expires_at)
+        .bind(Utc::now())
+        .bind(hash_token(refresh_token))
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(session) = session else {
+            return Err(AppError::InvalidRefreshToken);
+        };
+        let Some(user) = self.find_user_by_id(session.user_id).await? else {
+            return Err(AppError::InvalidRefreshToken);
+        };
+        if !user.status.can_sign_in() {
+            self.revoke_session(session.id).await?;
+            return Err(AppError::UserDisabled(user.id));
+        }
+        let tokens = self.issue_tokens(&user, &session, new_refresh_token)?;
+        Ok((user, tokens))
+    }
+
+    pub async fn revoke_session(&self, id: i64) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_sessions SET revoked_at = $1, update_time = $1 WHERE id = $2 AND revoked_at IS NULL
+        "#,
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // 吊销用户的全部会话，修改或重置密码后已签发的令牌全部失效
+    pub async fn revoke_user_sessions(&self, user_id: i64) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let count = revoke_user_sessions(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    pub async fn find_active_session(&self, id: i64) -> Result<Option<UserSession>, AppError> {
+        let session = sqlx::query_as(
+            r#"
+            SELECT id, user_id, expires_at, revoked_at, create_time, update_time FROM user_sessions
+            WHERE id = $1 AND revoked_at IS NULL AND expires_at > $2
+        "#,
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    fn issue_tokens(
+        &self,
+        user: &User,
+        session: &UserSession,
+        refresh_token: String,
+    ) -> Result<AuthTokens, AppError> {
+        let expires_in = self.config.auth.expires_in;
+        let token = self
+            .secret_key
+            .sign(user.id, &session.id.to_string(), expires_in)?;
+        Ok(AuthTokens {
+            token,
+            refresh_token,
+            expires_in,
+        })
+    }
+
+    fn refresh_expires_in(&self) -> Duration {
+        Duration::seconds(self.config.auth.refresh_expires_in as i64)
+    }
+}
+
+pub(crate) async fn revoke_user_sessions(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: i64,
+) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE user_sessions SET revoked_at = $1, update_time = $1 WHERE user_id = $2 AND revoked_at IS NULL
+    "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub(crate) fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod test_session {
+    use super::*;
+    use crate::UpdateUser;
+    use anyhow::Result;
+    use cmall_core::{TokenVerify, UserStatus};
+
+    #[tokio::test]
+    async fn test_refresh_token_should_rotate() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+
+        let tokens = state.create_session(&user).await?;
+        let claims = state.public_key.verify(&tokens.token)?;
+        assert_eq!(claims.user_id, user.id);
+
+        let (_, refreshed) = state.refresh_session(&tokens.refresh_token).await?;
+        assert_ne!(refreshed.refresh_token, tokens.refresh_token);
+
+        // the rotated refresh token can not be used again
+        let ret = state.refresh_session(&tokens.refresh_token).await;
+        assert!(matches!(ret, Err(AppError::InvalidRefreshToken)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_revoked_session_should_be_rejected() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+
+        let tokens = state.create_session(&user).await?;
+        let claims = state.public_key.verify(&tokens.token)?;
+        let session_id: i64 = claims.session_id.parse()?;
+        assert!(state.find_active_session(session_id).await?.is_some());
+
+        assert!(state.revoke_session(session_id).await?);
+        assert!(state.find_active_session(session_id).await?.is_none());
+        let ret = state.refresh_session(&tokens.refresh_token).await;
+        assert!(matches!(ret, Err(AppError::InvalidRefreshToken)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_user_token_should_be_rejected() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let user = state.find_user_by_id(1).await?.unwrap();
+
+        let tokens = state.create_session(&user).await?;
+        let verified = state.verify(&tokens.token).await?;
+        assert_eq!(verified.user, user);
+
+        let input = UpdateUser {
+            dept_id: user.dept_id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            phone: user.phone.clone(),
+            status: UserStatus::Off,
+            roles: user.roles.clone(),
+            avatar: user.avatar.clone(),
+        };
+        state.update_user(user.id, &input).await?;
+        let ret = state.verify(&tokens.token).await;
+        assert!(matches!(ret, Err(AppError::UserDisabled(1))));
+        Ok(())
+    }
+}
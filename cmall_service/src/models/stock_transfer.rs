@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use cmall_core::{
+    EffectStatus, StockAllocation, StockLine, StockMovementKind, StockTransfer,
+    StockTransferDetail, TransferStatus,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use super::inventory::apply_stock_changes;
+use crate::{error::AppError, AppState, Page, QueryFilter};
+
+const TRANSFER_COLUMNS: &str = "id, from_warehouse_id, to_warehouse_id, status, remark, create_time, create_by, update_time, update_by";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTransfer {
+    pub from_warehouse_id: i64,
+    pub to_warehouse_id: i64,
+    pub items: Vec<StockLine>,
+    #[serde(default)]
+    pub remark: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchTransfer {
+    pub warehouse_id: Option<i64>,
+    pub status: Option<TransferStatus>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+impl AppState {
+    // 创建调拨单时预留调出仓的库存，避免调拨期间被售出
+    pub async fn create_transfer(
+        &self,
+        input: &CreateTransfer,
+        create_by: String,
+    ) -> Result<StockTransferDetail, AppError> {
+        if input.from_warehouse_id == input.to_warehouse_id {
+            return Err(AppError::InvalidTransfer(
+                "source and target warehouse must be different".to_string(),
+            ));
+        }
+        if input.items.is_empty() || input.items.iter().any(|i| i.quantity <= 0) {
+            return Err(AppError::InvalidTransfer(
+                "items must have positive quantity".to_string(),
+            ));
+        }
+        // 停用的仓库不参与调拨
+        for warehouse_id in [input.from_warehouse_id, input.to_warehouse_id] {
+            match self.find_warehouse_by_id(warehouse_id).await? {
+                None => return Err(AppError::NotFound(format!("warehouse id {}", warehouse_id))),
+                Some(w) if w.status != EffectStatus::Enable => {
+                    return Err(AppError::InvalidTransfer(format!(
+                        "warehouse {} is disabled",
+                        warehouse_id
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+        let mut items: BTreeMap<i64, i64> = BTreeMap::new();
+        for item in &input.items {
+            *items.entry(item.sku_id).or_default() += item.quantity;
+        }
+
+        let mut tx = self.pool.begin().await?;
+        // 锁定 SKU，避免写入调拨明细前被删除
+        let sku_ids: Vec<i64> = items.keys().copied().collect();
+        let existed: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM product_skus WHERE id = ANY($1) FOR KEY SHARE
+        "#,
+        )
+        .bind(&sku_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        if let Some(sku_id) = sku_ids.iter().find(|id| !existed.contains(id)) {
+            return Err(AppError::NotFound(format!("sku id {}", sku_id)));
+        }
+        let transfer: StockTransfer = sqlx::query_as(&format!(
+            "INSERT INTO stock_transfers (from_warehouse_id, to_warehouse_id, remark, create_by, update_by) VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+            TRANSFER_COLUMNS
+        ))
+        .bind(input.from_warehouse_id)
+        .bind(input.to_warehouse_id)
+        .bind(&input.remark)
+        .bind(&create_by)
+        .bind(&create_by)
+        .fetch_one(&mut *tx)
+        .await?;
+        for (sku_id, quantity) in &items {
+            sqlx::query(
+                r#"
+                INSERT INTO stock_transfer_items (transfer_id, sku_id, quantity) VALUES ($1, $2, $3)
+            "#,
+            )
+            .bind(transfer.id)
+            .bind(sku_id)
+            .bind(quantity)
+            .execute(&mut *tx)
+            .await?;
+        }
+        let allocations: Vec<StockAllocation> = items
+            .iter()
+            .map(|(sku_id, quantity)| {
+                StockAllocation::new(transfer.from_warehouse_id, *sku_id, *quantity)
+            })
+            .collect();
+        apply_stock_changes(
+            &mut tx,
+            &allocations,
+            StockMovementKind::Reserve,
+            &input.remark,
+            &transfer.reference(),
+            &create_by,
+        )
+        .await?;
+        tx.commit().await?;
+        self.find_transfer_detail(transfer.id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("transfer id {}", transfer.id)))
+    }
+
+    // 调出和调入流水在同一事务中写入，两边数量相抵
+    pub async fn complete_transfer(
+        &self,
+        id: i64,
+        update_by: String,
+    ) -> Result<StockTransferDetail, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let (transfer, items) = lock_pending_transfer(&mut tx, id).await?;
+        let reference = transfer.reference();
+        let outbound: Vec<StockAllocation> = items
+            .iter()
+            .map(|i| StockAllocation::new(transfer.from_warehouse_id, i.sku_id, i.quantity))
+            .collect();
+        apply_stock_changes(
+            &mut tx,
+            &outbound,
+            StockMovementKind::TransferOut,
+            "",
+            &reference,
+            &update_by,
+        )
+        .await?;
+        let inbound: Vec<StockAllocation> = items
+            .iter()
+            .map(|i| StockAllocation::new(transfer.to_warehouse_id, i.sku_id, i.quantity))
+            .collect();
+        apply_stock_changes(
+            &mut tx,
+            &inbound,
+            StockMovementKind::TransferIn,
+            "",
+            &reference,
+            &update_by,
+        )
+        .await?;
+        set_transfer_status(&mut tx, id, TransferStatus::Completed, &update_by).await?;
+        tx.commit().await?;
+        self.find_transfer_detail(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("transfer id {}", id)))
+    }
+
+    pub async fn cancel_transfer(
+        &self,
+        id: i64,
+        update_by: String,
+    ) -> Result<StockTransferDetail, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let (transfer, items) = lock_pending_transfer(&mut tx, id).await?;
+        let allocations: Vec<StockAllocation> = items
+            .iter()
+            .map(|i| StockAllocation::new(transfer.from_warehouse_id, i.sku_id, i.quantity))
+            .collect();
+        apply_stock_changes(
+            &mut tx,
+            &allocations,
+            StockMovementKind::Release,
+            "",
+            &transfer.reference(),
+            &update_by,
+        )
+        .await?;
+        set_transfer_status(&mut tx, id, TransferStatus::Cancelled, &update_by).await?;
+        tx.commit().await?;
+        self.find_transfer_detail(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("transfer id {}", id)))
+    }
+
+    pub async fn find_transfer_detail(
+        &self,
+        id: i64,
+    ) -> Result<Option<StockTransferDetail>, AppError> {
+        let transfer: Option<StockTransfer> = sqlx::query_as(&format!(
+            "SELECT {} FROM stock_transfers WHERE id = $1",
+            TRANSFER_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(transfer) = transfer else {
+            return Ok(None);
+        };
+        let items = sqlx::query_as(
+            r#"
+            SELECT sku_id, quantity FROM stock_transfer_items WHERE transfer_id = $1 ORDER BY sku_id
+        "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Some(StockTransferDetail { transfer, items }))
+    }
+
+    pub async fn find_transfer_by_condition(
+        &self,
+        input: &SearchTransfer,
+        page: Page,
+    ) -> Result<(Vec<StockTransfer>, i64), AppError> {
+        QueryFilter::new()
+            .eq_any(
+                &["from_warehouse_id", "to_warehouse_id"],
+                input.warehouse_id,
+            )
+            .eq("status", input.status)
+            .fetch_page(
+                &self.pool,
+                TRANSFER_COLUMNS,
+                "stock_transfers",
+                "id DESC",
+                page,
+            )
+            .await
+    }
+}
+
+// 锁定调拨单，只有待处理的调拨单可以完成或取消
+async fn lock_pending_transfer(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+) -> Result<(StockTransfer, Vec<StockLine>), AppError> {
+    let transfer: Option<StockTransfer> = sqlx::query_as(&format!(
+        "SELECT {} FROM stock_transfers WHERE id = $1 FOR UPDATE",
+        TRANSFER_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    let transfer = transfer.ok_or_else(|| AppError::NotFound(format!("transfer id {}", id)))?;
+    if transfer.status != TransferStatus::Pending {
+        return Err(AppError::InvalidTransfer(format!(
+            "transfer {} is not pending",
+            id
+        )));
+    }
+    let items = sqlx::query_as(
+        r#"
+        SELECT sku_id, quantity FROM stock_transfer_items WHERE transfer_id = $1 ORDER BY sku_id
+    "#,
+    )
+    .bind(id)
+    .fetch_all(&mut **tx)
+    .await?;
+    Ok((transfer, items))
+}
+
+async fn set_transfer_status(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    status: TransferStatus,
+    update_by: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE stock_transfers SET status = $1, update_by = $2, update_time = $3 WHERE id = $4
+    "#,
+    )
+    .bind(status)
+    .bind(update_by)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_stock_transfer {
+    use super::*;
+    use crate::OperateWarehouse;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_transfer_should_keep_ledger_balanced() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let by = "admin".to_string();
+        let detail = state.create_on_sale_product(10).await?;
+        let (product_id, sku_id) = (detail.product.id, detail.skus[0].id);
+        let warehouse = OperateWarehouse {
+            code: "bj".to_string(),
+            name: "北京仓".to_string(),
+            address: "".to_string(),
+            priority: 2,
+            status: EffectStatus::Enable,
+            description: "".to_string(),
+        };
+        let target = state.create_warehouse(&warehouse, by.clone()).await?.id;
+
+        let input = CreateTransfer {
+            from_warehouse_id: 1,
+            to_warehouse_id: target,
+            items: vec![StockLine::new(sku_id, 4)],
+            remark: "".to_string(),
+        };
+        let transfer = state.create_transfer(&input, by.clone()).await?;
+        assert_eq!(transfer.transfer.status, TransferStatus::Pending);
+        // 调拨中的库存已预留
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        assert_eq!(inventories[0].available(), 6);
+
+        let id = transfer.transfer.id;
+        let done = state.complete_transfer(id, by.clone()).await?;
+        assert_eq!(done.transfer.status, TransferStatus::Completed);
+        let ret = state.cancel_transfer(id, by.clone()).await;
+        assert!(matches!(ret, Err(AppError::InvalidTransfer(_))));
+
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        let stock: Vec<(i64, i64, i64)> = inventories
+            .iter()
+            .map(|i| (i.warehouse_id, i.on_hand, i.reserved))
+            .collect();
+        assert_eq!(stock, vec![(1, 6, 0), (target, 4, 0)]);
+        let balance: i64 = sqlx::query_scalar(
+            "SELECT SUM(quantity)::BIGINT FROM stock_movements WHERE reference = $1 AND kind IN ('transfer_out', 'transfer_in')",
+        )
+        .bind(done.transfer.reference())
+        .fetch_one(&state.pool)
+        .await?;
+        assert_eq!(balance, 0);
+
+        // 取消调拨时释放预留
+        let transfer = state.create_transfer(&input, by.clone()).await?;
+        state
+            .cancel_transfer(transfer.transfer.id, by.clone())
+            .await?;
+        let inventories = state.find_sku_inventories(sku_id).await?.unwrap();
+        assert_eq!(inventories[0].reserved, 0);
+
+        // 调拨过的 SKU 保留调拨记录，不能删除
+        let ret = state.delete_sku(product_id, sku_id).await;
+        assert!(matches!(ret, Err(AppError::SkuInUse(_))));
+
+        let unknown = CreateTransfer {
+            items: vec![StockLine::new(sku_id + 100, 1)],
+            ..input.clone()
+        };
+        let ret = state.create_transfer(&unknown, by.clone()).await;
+        assert!(matches!(ret, Err(AppError::NotFound(_))));
+        let disabled = OperateWarehouse {
+            status: EffectStatus::Disable,
+            ..warehouse
+        };
+        state
+            .update_warehouse(target, &disabled, by.clone())
+            .await?;
+        let ret = state.create_transfer(&input, by).await;
+        assert!(matches!(ret, Err(AppError::InvalidTransfer(_))));
+        Ok(())
+    }
+}
@@ -1,14 +1,20 @@
 use std::mem;
 
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
     Argon2,
 };
+use chrono::{DateTime, Utc};
 use cmall_core::{User, UserStatus};
 use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
 use tracing::info;
 
-use crate::{error::AppError, AppState};
+use super::session::revoke_user_sessions;
+use crate::{error::AppError, AppState, Page, QueryFilter};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +28,86 @@ pub struct CreateUser {
     pub roles: Vec<i64>,
     pub avatar: String,
 }
+
+// 公开注册只接受基本信息，角色、部门和状态由服务端决定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignupUser {
+    pub username: String,
+    pub email: String,
+    pub phone: String,
+    pub password: String,
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchUser {
+    // 用户名和邮箱按不区分大小写的子串匹配
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub status: Option<UserStatus>,
+    pub dept_id: Option<i64>,
+    pub role_id: Option<i64>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub sort_by: UserSortBy,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+// 允许排序的字段，避免拼接任意列名
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum UserSortBy {
+    Id,
+    Username,
+    Email,
+    #[default]
+    CreateTime,
+    UpdateTime,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl UserSortBy {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Username => "username",
+            Self::Email => "email",
+            Self::CreateTime => "create_time",
+            Self::UpdateTime => "update_time",
+        }
+    }
+}
+
+impl SortOrder {
+    pub(crate) fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+// 用户列表返回的字段
+const USER_COLUMNS: &str = r#"
+    id, username, dept_id, email, create_time, update_time, status, avatar, phone, must_change_password, failed_attempts, locked_until, mfa_enabled,
+    ARRAY(SELECT ur.role_id FROM user_roles ur JOIN roles r ON r.id = ur.role_id WHERE ur.user_id = users.id AND r.deleted_at IS NULL ORDER BY ur.role_id) AS roles,
+    EXISTS(SELECT 1 FROM user_roles ur JOIN roles r ON r.id = ur.role_id WHERE ur.user_id = users.id AND r.status = 'enable' AND r.deleted_at IS NULL AND r.mfa_required) AS mfa_required
+"#;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateUser {
@@ -40,39 +126,87 @@ pub struct LoginUser {
     pub password: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePassword {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPasswordOutput {
+    pub temp_password: String,
+}
+
+// 注册用户归属根部门
+const SIGNUP_DEPT_ID: i64 = 1;
+const MIN_PASSWORD_LEN: usize = 6;
+const TEMP_PASSWORD_LEN: usize = 12;
+const TEMP_PASSWORD_CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnpqrstuvwxyz23456789";
+
 impl AppState {
     pub async fn create_user(&self, input: &CreateUser) -> Result<User, AppError> {
-        let user = self.find_user_by_email(&input.email).await?;
-        if user.is_some() {
+        // 已软删除的用户仍占用邮箱，需要恢复而不是重新创建
+        let existed: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)
+        "#,
+        )
+        .bind(&input.email)
+        .fetch_one(&self.pool)
+        .await?;
+        if existed {
             return Err(AppError::UserAlreadyExisted(input.email.clone()));
         };
         let password_hash = format_password(&input.password)?;
-        let user: User = sqlx::query_as(
+        let mut tx = self.pool.begin().await?;
+        let id: i64 = sqlx::query_scalar(
             r#"
-            INSERT INTO users (dept_id, username, password_hash, email, phone, status, avatar, roles) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) 
-            RETURNING id, dept_id, email, phone, username, create_time, update_time, status, avatar, roles
+            INSERT INTO users (dept_id, username, password_hash, email, phone, status, avatar) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
         "#,
         )
-        .bind(&input.dept_id)
+        .bind(input.dept_id)
         .bind(&input.username)
         .bind(password_hash)
         .bind(&input.email)
         .bind(&input.phone)
         .bind(&input.status)
         .bind(&input.avatar)
-        .bind(&input.roles)
-        .fetch_one(&self.pool).await.unwrap();
-        Ok(user)
+        .fetch_one(&mut *tx)
+        .await?;
+        set_user_roles(&mut tx, id, &input.roles).await?;
+        tx.commit().await?;
+
+        self.find_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))
+    }
+
+    // 注册的用户没有任何角色，需要管理员另行授权
+    pub async fn signup_user(&self, input: &SignupUser) -> Result<User, AppError> {
+        let input = CreateUser {
+            dept_id: SIGNUP_DEPT_ID,
+            username: input.username.clone(),
+            email: input.email.clone(),
+            phone: input.phone.clone(),
+            password: input.password.clone(),
+            status: UserStatus::Active,
+            roles: vec![],
+            avatar: "default".to_string(),
+        };
+        self.create_user(&input).await
     }
 
     pub async fn verify_user(&self, input: &LoginUser) -> Result<Option<User>, AppError> {
-        let user:Option<User> = sqlx::query_as(
-            r#"
-            SELECT id, username, dept_id, email, create_time, update_time, status, avatar, roles, phone,password_hash FROM users WHERE email = $1
-        "#,
-        )
+        let user: Option<User> = sqlx::query_as(&format!(
+            "SELECT {}, password_hash FROM users WHERE email = $1 AND deleted_at IS NULL",
+            USER_COLUMNS
+        ))
         .bind(&input.email)
-        .fetch_optional(&self.pool).await?;
+        .fetch_optional(&self.pool)
+        .await?;
         match user {
             Some(mut user) => {
                 let password_hash = mem::take(&mut user.password_hash);
@@ -81,134 +215,347 @@ impl AppState {
                     verify_password(&input.password, &password_hash.unwrap_or_default())?;
                 if is_valid {
                     info!("user found");
-                    return Ok(Some(user));
+                    Ok(Some(user))
                 } else {
                     info!("password not match");
-                    return Ok(None);
+                    Ok(None)
                 }
             }
             None => {
                 info!("user not found");
-                return Ok(None);
+                Ok(None)
             }
         }
     }
 
     pub async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
-        let user  = sqlx::query_as("
-          SELECT id, username, dept_id, email, create_time, update_time, status, avatar, roles, phone FROM users WHERE email = $1
-        ")
+        let user = sqlx::query_as(&format!(
+            "SELECT {} FROM users WHERE email = $1 AND deleted_at IS NULL",
+            USER_COLUMNS
+        ))
         .bind(email)
-        .fetch_optional(&self.pool).await?;
+        .fetch_optional(&self.pool)
+        .await?;
         Ok(user)
     }
     pub async fn find_user_by_id(&self, id: i64) -> Result<Option<User>, AppError> {
-        let user  = sqlx::query_as("
-          SELECT id, username, dept_id, email, create_time, update_time, status, avatar, roles, phone FROM users WHERE id = $1
-        ")
+        let user = sqlx::query_as(&format!(
+            "SELECT {} FROM users WHERE id = $1 AND deleted_at IS NULL",
+            USER_COLUMNS
+        ))
         .bind(id)
-        .fetch_optional(&self.pool).await?;
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user)
+    }
+    // 校验 token 时加载当前用户，短时间缓存以减少数据库查询
+    pub async fn find_signed_in_user(&self, id: i64) -> Result<User, AppError> {
+        let user = match self.user_cache.get(&id) {
+            Some(user) => user,
+            None => {
+                let user = self
+                    .find_user_by_id(id)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))?;
+                self.user_cache.insert(id, user.clone());
+                user
+            }
+        };
+        if !user.status.can_sign_in() {
+            return Err(AppError::UserDisabled(id));
+        }
         Ok(user)
     }
+
     pub async fn find_users(&self) -> Result<Vec<User>, AppError> {
-        let users = sqlx::query_as("
-          SELECT id, username, dept_id, email, create_time, update_time, status, avatar, roles, phone FROM users
-        ")
-        .fetch_all(&self.pool).await?;
+        let users = sqlx::query_as(&format!(
+            "SELECT {} FROM users WHERE deleted_at IS NULL",
+            USER_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
         Ok(users)
     }
 
     // 根据传入的查询条件，一个对象，传递的值可能为空，并集查询user {username,email,roles}
     pub async fn find_user_by_conditions(
         &self,
-        username: Option<&str>,
-        email: Option<&str>,
-        phone: Option<&str>,
-        status: Option<UserStatus>,
-        page_num: i64,
-        page_size: i64,
+        input: &SearchUser,
+        page: Page,
     ) -> Result<(Vec<User>, i64), AppError> {
-        // 需要根据分页信息查询
-        let offset = (page_num - 1) * page_size;
-
-        let users = sqlx::query_as(
-                r#"
-                SELECT id, username, dept_id, email, create_time, update_time, status, avatar, roles, phone FROM users
-                WHERE (username = $1 or $1 IS NULL)
-                AND (email = $2 OR $2 IS NULL)
-                AND (phone = $3 OR $3 IS NULL)
-                AND (status = $4 OR $4 IS NULL)
-                LIMIT $5 OFFSET $6 
-                "#,
-        ).bind(username)
-        .bind(email)
-        .bind(phone)
-        .bind(status.clone())
-        .bind(page_size)
-        .bind(offset)
-            .fetch_all(&self.pool)
-            .await.unwrap();
-
-        // 获取满足条件的总记录数
-        let total_count: i64 = sqlx::query_scalar(
-            r#"
-            SELECT COUNT(*) FROM users
-            WHERE (username = $1 OR $1 IS NULL)
-            AND (email = $2 OR $2 IS NULL)
-            AND (phone = $3 OR $3 IS NULL)
-            AND (status = $4 OR $4 IS NULL)
-            "#,
-        )
-        .bind(username)
-        .bind(email)
-        .bind(phone)
-        .bind(status)
-        .fetch_one(&self.pool)
-        .await?;
-        Ok((users, total_count))
+        let filter = QueryFilter::new()
+            .raw("deleted_at IS NULL")
+            .contains("username", input.username.as_deref())
+            .contains("email", input.email.as_deref())
+            .eq("phone", input.phone.clone())
+            .eq("status", input.status.clone())
+            .eq("dept_id", input.dept_id)
+            .bind(
+                "EXISTS(SELECT 1 FROM user_roles ur WHERE ur.user_id = users.id AND ur.role_id = ",
+                input.role_id,
+                ")",
+            )
+            .gte("create_time", input.start_time)
+            .lt("create_time", input.end_time);
+        // 排序字段来自白名单，按 id 排序保证分页结果稳定
+        let order_by = format!(
+            "{} {order}, id {order}",
+            input.sort_by.column(),
+            order = input.sort_order.as_sql()
+        );
+        filter
+            .fetch_page(&self.pool, USER_COLUMNS, "users", &order_by, page)
+            .await
     }
 
+    // 软删除用户，保留角色关联以便恢复，同时吊销会话和未使用的凭据
     pub async fn delete_user(&self, id: i64) -> Result<bool, AppError> {
-        let user = self.find_user_by_id(id).await?;
-        if user.is_none() {
-            return Err(AppError::NotFound(format!("user id {}", id)));
-        }
+        let mut tx = self.pool.begin().await?;
         let result = sqlx::query(
             r#"
-            DELETE FROM users WHERE id = $1
+            UPDATE users SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL
         "#,
         )
+        .bind(chrono::Utc::now())
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
         if result.rows_affected() == 0 {
             return Err(AppError::NotFound(format!("user id {}", id)));
         }
+        revoke_user_sessions(&mut tx, id).await?;
+        sqlx::query(
+            r#"
+            DELETE FROM password_resets WHERE user_id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM mfa_challenges WHERE user_id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.user_cache.remove(&id);
         Ok(true)
     }
 
+    // 恢复已软删除的用户，所属部门必须仍然存在
+    pub async fn restore_user(&self, id: i64) -> Result<User, AppError> {
+        let dept_id: i64 = sqlx::query_scalar(
+            r#"
+            SELECT dept_id FROM users WHERE id = $1 AND deleted_at IS NOT NULL
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("deleted user id {}", id)))?;
+        if self.find_department_by_id(dept_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("department id {}", dept_id)));
+        }
+        sqlx::query(
+            r#"
+            UPDATE users SET deleted_at = NULL, update_time = $1 WHERE id = $2
+        "#,
+        )
+        .bind(chrono::Utc::now())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        self.user_cache.remove(&id);
+        self.find_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))
+    }
+
     pub async fn update_user(&self, id: i64, input: &UpdateUser) -> Result<User, AppError> {
         let user = self.find_user_by_id(id).await?;
         if user.is_none() {
             return Err(AppError::NotFound(format!("user id {}", id)));
         }
-        let user: User= sqlx::query_as(r#"
-            UPDATE users SET username = $1, email = $2, phone = $3, status = $4, avatar = $5, roles = $6, update_time = $7 WHERE id = $8
-            RETURNING id, username, dept_id, email, create_time, update_time, status, avatar, roles, phone
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(r#"
+            UPDATE users SET username = $1, email = $2, phone = $3, status = $4, avatar = $5, update_time = $6 WHERE id = $7
         "#).bind(&input.username)
         .bind(&input.email)
         .bind(&input.phone)
         .bind(&input.status)
         .bind(&input.avatar)
-        .bind(&input.roles)
-        .bind(chrono::Utc::now().naive_utc())
+        .bind(chrono::Utc::now())
         .bind(id)
-        .fetch_one(&self.pool).await?;
-        Ok(user)
+        .execute(&mut *tx).await?;
+        set_user_roles(&mut tx, id, &input.roles).await?;
+        tx.commit().await?;
+        self.user_cache.remove(&id);
+
+        self.find_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))
+    }
+
+    // 用户修改自己的密码，需要校验旧密码，修改后吊销全部会话
+    pub async fn change_password(&self, id: i64, input: &ChangePassword) -> Result<(), AppError> {
+        let password_hash: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT password_hash FROM users WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))?;
+        if !verify_password(&input.old_password, &password_hash.unwrap_or_default())? {
+            return Err(AppError::InvalidPassword(
+                "old password not match".to_string(),
+            ));
+        }
+        if input.old_password == input.new_password {
+            return Err(AppError::InvalidPassword(
+                "new password must differ from the old one".to_string(),
+            ));
+        }
+        check_password(&input.new_password)?;
+        self.set_password(id, &input.new_password, false).await
+    }
+
+    // 管理员重置用户密码，生成临时密码，用户下次登录后必须修改密码
+    pub async fn reset_password(&self, id: i64) -> Result<String, AppError> {
+        if self.find_user_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("user id {}", id)));
+        }
+        let temp_password = generate_temp_password();
+        self.set_password(id, &temp_password, true).await?;
+        Ok(temp_password)
+    }
+
+    async fn set_password(
+        &self,
+        id: i64,
+        password: &str,
+        must_change_password: bool,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        update_password(&mut tx, id, password, must_change_password).await?;
+        tx.commit().await?;
+        self.user_cache.remove(&id);
+        Ok(())
+    }
+
+    pub async fn add_user_role(&self, id: i64, role_id: i64) -> Result<User, AppError> {
+        if self.find_user_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("user id {}", id)));
+        }
+        if self.find_role_by_id(role_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("role id {}", role_id)));
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO user_roles (user_id, role_id) VALUES ($1, $2) ON CONFLICT DO NOTHING
+        "#,
+        )
+        .bind(id)
+        .bind(role_id)
+        .execute(&self.pool)
+        .await?;
+        self.user_cache.remove(&id);
+        self.find_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))
+    }
+
+    pub async fn remove_user_role(&self, id: i64, role_id: i64) -> Result<User, AppError> {
+        if self.find_user_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("user id {}", id)));
+        }
+        sqlx::query(
+            r#"
+            DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2
+        "#,
+        )
+        .bind(id)
+        .bind(role_id)
+        .execute(&self.pool)
+        .await?;
+        self.user_cache.remove(&id);
+        self.find_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user id {}", id)))
+    }
+}
+
+// 在事务中用 roles 覆盖用户的角色，角色必须存在
+pub(crate) async fn set_user_roles(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: i64,
+    roles: &[i64],
+) -> Result<(), AppError> {
+    let existed: Vec<i64> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM roles WHERE id = ANY($1) AND deleted_at IS NULL
+    "#,
+    )
+    .bind(roles)
+    .fetch_all(&mut **tx)
+    .await?;
+    let missing: Vec<i64> = roles
+        .iter()
+        .filter(|id| !existed.contains(id))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::NotFound(format!("role ids {:?}", missing)));
     }
+    sqlx::query(
+        r#"
+        DELETE FROM user_roles WHERE user_id = $1
+    "#,
+    )
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO user_roles (user_id, role_id) SELECT $1, unnest($2::BIGINT[])
+        ON CONFLICT DO NOTHING
+    "#,
+    )
+    .bind(user_id)
+    .bind(roles)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// 在事务中更新密码并吊销用户的全部会话
+pub(crate) async fn update_password(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: i64,
+    password: &str,
+    must_change_password: bool,
+) -> Result<(), AppError> {
+    let password_hash = format_password(password)?;
+    sqlx::query(
+        r#"
+        UPDATE users SET password_hash = $1, must_change_password = $2, update_time = $3 WHERE id = $4
+    "#,
+    )
+    .bind(password_hash)
+    .bind(must_change_password)
+    .bind(chrono::Utc::now())
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+    revoke_user_sessions(tx, user_id).await?;
+    Ok(())
 }
 
-fn format_password(password: &str) -> Result<String, AppError> {
+pub(crate) fn format_password(password: &str) -> Result<String, AppError> {
     let salt = SaltString::generate(&mut OsRng);
 
     let argon2 = Argon2::default();
@@ -218,6 +565,25 @@ fn format_password(password: &str) -> Result<String, AppError> {
     Ok(password_hash)
 }
 
+pub(crate) fn check_password(password: &str) -> Result<(), AppError> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(AppError::InvalidPassword(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn generate_temp_password() -> String {
+    (0..TEMP_PASSWORD_LEN)
+        .map(|_| {
+            let idx = OsRng.next_u32() as usize % TEMP_PASSWORD_CHARSET.len();
+            TEMP_PASSWORD_CHARSET[idx] as char
+        })
+        .collect()
+}
+
 fn verify_password(password: &str, password_hash: &str) -> Result<bool, AppError> {
     let argon2 = Argon2::default();
     let password_hash = PasswordHash::new(password_hash)?;
@@ -273,7 +639,157 @@ mod test_user {
         let user = state.create_user(&input).await.unwrap();
 
         assert_eq!(user.username, input.username);
+        assert_eq!(user.roles, vec![1]);
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_signup_should_ignore_roles() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input: SignupUser = serde_json::from_value(serde_json::json!({
+            "username": "test",
+            "email": "test@example.com",
+            "phone": "1234567890",
+            "password": "test123",
+            "roles": [1],
+            "deptId": 2,
+            "status": "off",
+        }))?;
+        let user = state.signup_user(&input).await?;
+        assert!(user.roles.is_empty());
+        assert_eq!(user.dept_id, SIGNUP_DEPT_ID);
+        assert_eq!(user.status, UserStatus::Active);
+        assert!(state.find_user_permissions(user.id).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_change_password_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input = CreateUser::new("test", "test@example.com", "1234567890", "test123");
+        let user = state.create_user(&input).await?;
+        let tokens = state.create_session(&user).await?;
+
+        let input = ChangePassword {
+            old_password: "wrong".to_string(),
+            new_password: "hunter22".to_string(),
+        };
+        let ret = state.change_password(user.id, &input).await;
+        assert!(matches!(ret, Err(AppError::InvalidPassword(_))));
+
+        let input = ChangePassword {
+            old_password: "test123".to_string(),
+            new_password: "hunter22".to_string(),
+        };
+        state.change_password(user.id, &input).await?;
+        let login = LoginUser {
+            email: user.email.clone(),
+            password: "hunter22".to_string(),
+        };
+        assert!(state.verify_user(&login).await?.is_some());
+        let ret = state.refresh_session(&tokens.refresh_token).await;
+        assert!(matches!(ret, Err(AppError::InvalidRefreshToken)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_should_force_change() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input = CreateUser::new("test", "test@example.com", "1234567890", "test123");
+        let user = state.create_user(&input).await?;
+
+        let temp_password = state.reset_password(user.id).await?;
+        assert_eq!(temp_password.len(), TEMP_PASSWORD_LEN);
+        let login = LoginUser {
+            email: user.email.clone(),
+            password: temp_password.clone(),
+        };
+        let user = state.verify_user(&login).await?.unwrap();
+        assert!(user.must_change_password);
+
+        let input = ChangePassword {
+            old_password: temp_password,
+            new_password: "hunter22".to_string(),
+        };
+        state.change_password(user.id, &input).await?;
+        let user = state.find_user_by_id(user.id).await?.unwrap();
+        assert!(!user.must_change_password);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_user_roles_should_come_from_user_roles() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        let mut input = CreateUser::new("test", "test@example.com", "1234567890", "test123");
+        input.roles = vec![1, 404];
+        let ret = state.create_user(&input).await;
+        assert!(matches!(ret, Err(AppError::NotFound(_))));
+        assert!(state.find_user_by_email(&input.email).await?.is_none());
+
+        let user = state.add_user_role(1, 2).await?;
+        assert_eq!(user.roles, vec![1, 2]);
+        let user = state.remove_user_role(1, 1).await?;
+        assert_eq!(user.roles, vec![2]);
+
+        let ret = state.delete_role(2).await;
+        assert!(matches!(ret, Err(AppError::RoleHasUsers(2))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_users_should_filter_and_sort() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input = SearchUser {
+            username: Some("shi".to_string()),
+            sort_by: UserSortBy::Username,
+            sort_order: SortOrder::Asc,
+            page_num: 1,
+            page_size: 10,
+            ..SearchUser::default()
+        };
+        let page = Page::new(1, 10)?;
+        let (users, total) = state.find_user_by_conditions(&input, page).await?;
+        assert_eq!(total, 2);
+        assert_eq!(users[0].username, "Alice Shi");
+        assert_eq!(users[1].username, "Eli Shi");
+
+        let input = SearchUser {
+            username: None,
+            email: Some("ACME".to_string()),
+            ..input
+        };
+        let (users, total) = state.find_user_by_conditions(&input, page).await?;
+        assert_eq!(total, 1);
+        assert_eq!(users[0].email, "alice@acme.org");
+
+        let input = SearchUser {
+            email: None,
+            role_id: Some(2),
+            dept_id: Some(1),
+            ..input
+        };
+        let (users, _) = state.find_user_by_conditions(&input, page).await?;
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, 2);
+
+        // 通配符按普通字符匹配
+        let input = SearchUser {
+            role_id: None,
+            username: Some("%".to_string()),
+            ..input
+        };
+        let (_, total) = state.find_user_by_conditions(&input, page).await?;
+        assert_eq!(total, 0);
+
+        let input = SearchUser {
+            username: None,
+            end_time: Some(Utc::now() - chrono::Duration::days(1)),
+            ..input
+        };
+        let (_, total) = state.find_user_by_conditions(&input, page).await?;
+        assert_eq!(total, 0);
+        Ok(())
+    }
 }
@@ -0,0 +1,215 @@
+use chrono::{DateTime, Utc};
+use cmall_core::{ExportJob, ExportStatus, User, UserStatus};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::{
+    check_export_total, error::AppError, export_to_bytes, AppState, ExportColumn, ExportFormat,
+    ExportLocale, ExportOptions, Exportable, Page, SearchUser, MAX_EXPORT_ROWS,
+};
+
+// 超过该行数时转为后台导出
+const EXPORT_SYNC_ROWS: i64 = 5_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportUser {
+    pub username: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub status: Option<UserStatus>,
+    pub dept_id: Option<i64>,
+    pub role_id: Option<i64>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub locale: ExportLocale,
+}
+
+impl Exportable for User {
+    const NAME: &'static str = "users";
+
+    fn columns() -> Vec<ExportColumn<Self>> {
+        vec![
+            ExportColumn::new("id", "编号", "ID", |u: &User| u.id.into()),
+            ExportColumn::new("username", "名称", "Username", |u: &User| {
+                u.username.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("phone", "电话", "Phone", |u: &User| {
+                u.phone.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("email", "邮箱", "Email", |u: &User| {
+                u.email.as_str().into()
+            })
+            .width(15.0),
+            ExportColumn::new("status", "状态", "Status", |u: &User| {
+                u.status.to_string().into()
+            }),
+            ExportColumn::new("createTime", "创建时间", "Create Time", |u: &User| {
+                u.create_time.into()
+            })
+            .width(25.0),
+        ]
+    }
+}
+
+pub enum UserExport {
+    File { file_name: String, data: Vec<u8> },
+    Job(ExportJob),
+}
+
+impl AppState {
+    // 数据量较小时直接返回文件，否则创建后台任务
+    pub async fn export_users(
+        &self,
+        user_id: i64,
+        input: &ExportUser,
+    ) -> Result<UserExport, AppError> {
+        let (users, total) = self.find_users_for_export(input, EXPORT_SYNC_ROWS).await?;
+        let file_name = input.format.file_name(User::NAME);
+        check_export_total(total, MAX_EXPORT_ROWS)?;
+        if total <= EXPORT_SYNC_ROWS {
+            let data = export_to_bytes(&users, ExportOptions::new(input.format, input.locale))?;
+            return Ok(UserExport::File { file_name, data });
+        }
+
+        let params = serde_json::to_value(input).map_err(|e| AppError::AnyError(e.into()))?;
+        let job = self
+            .create_export_job(user_id, "user", &file_name, &params)
+            .await?;
+        info!("user export job {} created for {} rows", job.id, total);
+        let state = self.clone();
+        let (id, input) = (job.id.clone(), input.clone());
+        tokio::spawn(async move {
+            if let Err(e) = state.run_user_export_job(&id, &input).await {
+                warn!("user export job {} failed: {:?}", id, e);
+                if let Err(e) = state
+                    .update_export_job(&id, ExportStatus::Failed, 0, Some(e.to_string()))
+                    .await
+                {
+                    warn!("update export job {} error: {:?}", id, e);
+                }
+            }
+        });
+        Ok(UserExport::Job(job))
+    }
+
+    async fn run_user_export_job(&self, id: &str, input: &ExportUser) -> Result<(), AppError> {
+        self.update_export_job(id, ExportStatus::Running, 0, None)
+            .await?;
+        let (users, total) = self.find_users_for_export(input, MAX_EXPORT_ROWS).await?;
+        // 创建任务后数据可能继续增长，超过上限时任务失败而不是导出不完整的文件
+        check_export_total(total, MAX_EXPORT_ROWS)?;
+        let data = export_to_bytes(&users, ExportOptions::new(input.format, input.locale))?;
+        let file_path: String = sqlx::query_scalar(
+            r#"
+            SELECT file_path FROM export_jobs WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        fs::write(&file_path, data).await?;
+        self.update_export_job(id, ExportStatus::Done, users.len() as i64, None)
+            .await
+    }
+
+    async fn find_users_for_export(
+        &self,
+        input: &ExportUser,
+        limit: i64,
+    ) -> Result<(Vec<User>, i64), AppError> {
+        let input = SearchUser {
+            username: input.username.clone(),
+            email: input.email.clone(),
+            phone: input.phone.clone(),
+            status: input.status.clone(),
+            dept_id: input.dept_id,
+            role_id: input.role_id,
+            start_time: input.start_time,
+            end_time: input.end_time,
+            ..SearchUser::default()
+        };
+        self.find_user_by_conditions(&input, Page::first(limit))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test_user_export {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_export_users_should_apply_filters() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input = ExportUser {
+            status: Some(UserStatus::Off),
+            ..ExportUser::default()
+        };
+        let (users, total) = state.find_users_for_export(&input, 10).await?;
+        assert_eq!(total, 1);
+        assert_eq!(users[0].email, "alice@acme.org");
+
+        match state.export_users(1, &input).await? {
+            UserExport::File { file_name, data } => {
+                assert!(file_name.starts_with("users-"));
+                assert!(file_name.ends_with(".xlsx"));
+                // xlsx 文件是 zip 格式
+                assert_eq!(&data[..2], b"PK");
+            }
+            UserExport::Job(_) => panic!("small export should not create a job"),
+        }
+
+        let input = ExportUser {
+            format: ExportFormat::Csv,
+            locale: ExportLocale::En,
+            ..input
+        };
+        match state.export_users(1, &input).await? {
+            UserExport::File { file_name, data } => {
+                assert!(file_name.ends_with(".csv"));
+                let text = String::from_utf8(data)?;
+                assert!(text.contains("ID,Username,Phone,Email,Status,Create Time"));
+                assert!(text.contains("alice@acme.org"));
+            }
+            UserExport::Job(_) => panic!("small export should not create a job"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_job_should_write_file() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let input = ExportUser::default();
+        let job = state
+            .create_export_job(1, "user", "users.xlsx", &serde_json::to_value(&input)?)
+            .await?;
+        assert_eq!(job.status, ExportStatus::Pending);
+        state.run_user_export_job(&job.id, &input).await?;
+
+        let job = state.find_export_job(&job.id, 1).await?.unwrap();
+        assert_eq!(job.status, ExportStatus::Done);
+        assert!(job.row_count > 0);
+        assert!(fs::metadata(&job.file_path).await?.len() > 0);
+        assert!(state.find_export_job(&job.id, 2).await?.is_none());
+
+        // 过期的任务和文件在下次创建任务时被删除
+        sqlx::query("UPDATE export_jobs SET create_time = create_time - INTERVAL '2 days'")
+            .execute(&state.pool)
+            .await?;
+        let next = state
+            .create_export_job(1, "user", "users.xlsx", &serde_json::to_value(&input)?)
+            .await?;
+        assert!(state.find_export_job(&job.id, 1).await?.is_none());
+        assert!(fs::metadata(&job.file_path).await.is_err());
+        assert!(state.find_export_job(&next.id, 1).await?.is_some());
+        Ok(())
+    }
+}
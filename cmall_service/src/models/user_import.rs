@@ -0,0 +1,520 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+};
+
+use argon2::password_hash::PasswordHash;
+use calamine::{Reader, Xlsx};
+use cmall_core::UserStatus;
+use serde::{Deserialize, Serialize};
+
+use super::user::{check_password, format_password, set_user_roles};
+use crate::{error::AppError, AppState};
+
+// 单次导入的最大行数
+const MAX_IMPORT_ROWS: usize = 5_000;
+// 与 users 表的字段长度一致，超长的行在写入前报告为行错误
+const MAX_USERNAME_LEN: usize = 64;
+const MAX_EMAIL_LEN: usize = 64;
+const MAX_PHONE_LEN: usize = 32;
+const MAX_AVATAR_LEN: usize = 255;
+const MAX_PASSWORD_HASH_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Csv,
+    Xlsx,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportUsers {
+    // 只校验不写入，用于预览导入结果
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRowError {
+    // 文件中的行号，表头为第 1 行
+    pub row: usize,
+    pub email: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportUserReport {
+    pub dry_run: bool,
+    pub total: usize,
+    // 已导入的行数，预览时为可以导入的行数
+    pub imported: usize,
+    pub failed: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+// 文件中的一行，列名已统一为小写并去掉下划线
+struct RawRow {
+    row: usize,
+    cells: HashMap<String, String>,
+}
+
+struct ImportUserRow {
+    row: usize,
+    dept_id: i64,
+    username: String,
+    email: String,
+    phone: String,
+    avatar: String,
+    status: UserStatus,
+    roles: Vec<i64>,
+    password: ImportPassword,
+}
+
+enum ImportPassword {
+    Plain(String),
+    Hashed(String),
+}
+
+impl ImportFormat {
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, ext) = file_name.rsplit_once('.')?;
+        match ext.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "xlsx" => Some(Self::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+impl AppState {
+    // 批量导入用户，有效的行在同一个事务中写入，无效的行逐行返回错误
+    pub async fn import_users(
+        &self,
+        format: ImportFormat,
+        data: &[u8],
+        dry_run: bool,
+    ) -> Result<ImportUserReport, AppError> {
+        let raw_rows = match format {
+            ImportFormat::Csv => read_csv(data)?,
+            ImportFormat::Xlsx => read_xlsx(data)?,
+        };
+        if raw_rows.len() > MAX_IMPORT_ROWS {
+            return Err(AppError::ImportError(format!(
+                "at most {} rows can be imported at once",
+                MAX_IMPORT_ROWS
+            )));
+        }
+
+        let mut errors = Vec::new();
+        let mut rows = Vec::new();
+        for raw in &raw_rows {
+            match parse_row(raw) {
+                Ok(row) => rows.push(row),
+                Err(e) => errors.push(ImportRowError {
+                    row: raw.row,
+                    email: raw.cells.get("email").cloned().unwrap_or_default(),
+                    errors: e,
+                }),
+            }
+        }
+
+        // 邮箱、部门和角色需要对照数据库校验，已软删除的用户仍占用邮箱
+        let emails: Vec<String> = rows.iter().map(|r| r.email.clone()).collect();
+        let existed_emails: HashSet<String> = sqlx::query_scalar(
+            r#"
+            SELECT email FROM users WHERE email = ANY($1)
+        "#,
+        )
+        .bind(&emails)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let dept_ids: Vec<i64> = rows.iter().map(|r| r.dept_id).collect();
+        let existed_depts: HashSet<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM departments WHERE id = ANY($1) AND deleted_at IS NULL
+        "#,
+        )
+        .bind(&dept_ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let role_ids: Vec<i64> = rows.iter().flat_map(|r| r.roles.clone()).collect();
+        let existed_roles: HashSet<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM roles WHERE id = ANY($1) AND deleted_at IS NULL
+        "#,
+        )
+        .bind(&role_ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        let mut seen_emails = HashSet::new();
+        let mut valid_rows = Vec::new();
+        for row in rows {
+            let mut row_errors = Vec::new();
+            if existed_emails.contains(&row.email) {
+                row_errors.push(format!("email {} already existed", row.email));
+            } else if !seen_emails.insert(row.email.clone()) {
+                row_errors.push(format!("email {} is duplicated in the file", row.email));
+            }
+            if !existed_depts.contains(&row.dept_id) {
+                row_errors.push(format!("department id {} not found", row.dept_id));
+            }
+            let missing: Vec<i64> = row
+                .roles
+                .iter()
+                .filter(|id| !existed_roles.contains(id))
+                .copied()
+                .collect();
+            if !missing.is_empty() {
+                row_errors.push(format!("role ids {:?} not found", missing));
+            }
+            if row_errors.is_empty() {
+                valid_rows.push(row);
+            } else {
+                errors.push(ImportRowError {
+                    row: row.row,
+                    email: row.email,
+                    errors: row_errors,
+                });
+            }
+        }
+        errors.sort_by_key(|e| e.row);
+
+        let report = ImportUserReport {
+            dry_run,
+            total: raw_rows.len(),
+            imported: valid_rows.len(),
+            failed: errors.len(),
+            errors,
+        };
+        if dry_run || valid_rows.is_empty() {
+            return Ok(report);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for row in &valid_rows {
+            let password_hash = match &row.password {
+                ImportPassword::Plain(password) => format_password(password)?,
+                ImportPassword::Hashed(hash) => hash.clone(),
+            };
+            let id: i64 = sqlx::query_scalar(
+                r#"
+                INSERT INTO users (dept_id, username, password_hash, email, phone, status, avatar) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING id
+            "#,
+            )
+            .bind(row.dept_id)
+            .bind(&row.username)
+            .bind(password_hash)
+            .bind(&row.email)
+            .bind(&row.phone)
+            .bind(&row.status)
+            .bind(&row.avatar)
+            .fetch_one(&mut *tx)
+            .await?;
+            set_user_roles(&mut tx, id, &row.roles).await?;
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+}
+
+fn read_csv(data: &[u8]) -> Result<Vec<RawRow>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| AppError::ImportError(e.to_string()))?
+        .iter()
+        .map(normalize_header)
+        .collect();
+    let mut rows = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| AppError::ImportError(e.to_string()))?;
+        let cells = headers
+            .iter()
+            .cloned()
+            .zip(record.iter().map(str::to_string))
+            .collect();
+        rows.push(RawRow {
+            row: idx + 2,
+            cells,
+        });
+    }
+    Ok(rows)
+}
+
+fn read_xlsx(data: &[u8]) -> Result<Vec<RawRow>, AppError> {
+    let mut workbook =
+        Xlsx::new(Cursor::new(data)).map_err(|e| AppError::ImportError(e.to_string()))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| AppError::ImportError("workbook has no worksheet".to_string()))?
+        .map_err(|e| AppError::ImportError(e.to_string()))?;
+    let mut sheet_rows = range.rows();
+    let headers: Vec<String> = match sheet_rows.next() {
+        Some(header) => header
+            .iter()
+            .map(|cell| normalize_header(&cell.to_string()))
+            .collect(),
+        None => return Ok(Vec::new()),
+    };
+    let mut rows = Vec::new();
+    for (idx, cells) in sheet_rows.enumerate() {
+        // 跳过末尾的空行
+        if cells.iter().all(|cell| cell.to_string().trim().is_empty()) {
+            continue;
+        }
+        let cells = headers
+            .iter()
+            .cloned()
+            .zip(cells.iter().map(|cell| cell.to_string().trim().to_string()))
+            .collect();
+        rows.push(RawRow {
+            row: idx + 2,
+            cells,
+        });
+    }
+    Ok(rows)
+}
+
+// dept_id、deptId、Dept ID 统一为 deptid
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn parse_row(raw: &RawRow) -> Result<ImportUserRow, Vec<String>> {
+    let cell = |name: &str| {
+        raw.cells
+            .get(name)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    };
+    let mut errors = Vec::new();
+
+    let dept_id = match cell("deptid").map(str::parse::<i64>) {
+        Some(Ok(id)) => id,
+        Some(Err(_)) => {
+            errors.push("dept_id must be a number".to_string());
+            0
+        }
+        None => {
+            errors.push("dept_id is required".to_string());
+            0
+        }
+    };
+    let username = cell("username").unwrap_or_default().to_string();
+    if username.is_empty() {
+        errors.push("username is required".to_string());
+    }
+    let email = cell("email").unwrap_or_default().to_string();
+    if email.is_empty() {
+        errors.push("email is required".to_string());
+    } else if !email.contains('@') {
+        errors.push(format!("invalid email {}", email));
+    }
+    let phone = cell("phone").unwrap_or_default().to_string();
+    let avatar = cell("avatar").unwrap_or("default").to_string();
+    for (name, value, max) in [
+        ("username", &username, MAX_USERNAME_LEN),
+        ("email", &email, MAX_EMAIL_LEN),
+        ("phone", &phone, MAX_PHONE_LEN),
+        ("avatar", &avatar, MAX_AVATAR_LEN),
+    ] {
+        check_length(&mut errors, name, value, max);
+    }
+    let status = match cell("status") {
+        Some(status) => match serde_json::from_value::<UserStatus>(status.to_lowercase().into()) {
+            Ok(status) => Some(status),
+            Err(_) => {
+                errors.push(format!("invalid status {}", status));
+                None
+            }
+        },
+        None => {
+            errors.push("status is required".to_string());
+            None
+        }
+    };
+    // 角色支持 {1,2} 和 1,2 两种写法
+    let roles = cell("roles")
+        .unwrap_or_default()
+        .trim_matches(|c| c == '{' || c == '}')
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::parse::<i64>)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|_| {
+            errors.push("roles must be a list of role ids".to_string());
+            Vec::new()
+        });
+    // 明文密码导入时加密，也可以直接导入已加密的密码
+    let password = match (cell("password"), cell("passwordhash")) {
+        (Some(password), _) => match check_password(password) {
+            Ok(()) => Some(ImportPassword::Plain(password.to_string())),
+            Err(e) => {
+                errors.push(e.to_string());
+                None
+            }
+        },
+        (None, Some(hash)) if hash.chars().count() > MAX_PASSWORD_HASH_LEN => {
+            check_length(&mut errors, "password_hash", hash, MAX_PASSWORD_HASH_LEN);
+            None
+        }
+        (None, Some(hash)) => match PasswordHash::new(hash) {
+            Ok(_) => Some(ImportPassword::Hashed(hash.to_string())),
+            Err(_) => {
+                errors.push("invalid password_hash".to_string());
+                None
+            }
+        },
+        (None, None) => {
+            errors.push("password or password_hash is required".to_string());
+            None
+        }
+    };
+
+    match (status, password) {
+        (Some(status), Some(password)) if errors.is_empty() => Ok(ImportUserRow {
+            row: raw.row,
+            dept_id,
+            username,
+            email,
+            phone,
+            avatar,
+            status,
+            roles,
+            password,
+        }),
+        _ => Err(errors),
+    }
+}
+
+// 数据库按字符计算 VARCHAR 的长度
+fn check_length(errors: &mut Vec<String>, name: &str, value: &str, max: usize) {
+    if value.chars().count() > max {
+        errors.push(format!("{} must be at most {} characters", name, max));
+    }
+}
+
+#[cfg(test)]
+mod test_user_import {
+    use super::*;
+    use anyhow::Result;
+
+    const USERS_CSV: &[u8] = include_bytes!("../../fixtures/users.csv");
+
+    #[test]
+    fn test_import_format_should_follow_extension() {
+        assert_eq!(
+            ImportFormat::from_file_name("users.CSV"),
+            Some(ImportFormat::Csv)
+        );
+        assert_eq!(
+            ImportFormat::from_file_name("a.b.xlsx"),
+            Some(ImportFormat::Xlsx)
+        );
+        assert_eq!(ImportFormat::from_file_name("users.xls"), None);
+        assert_eq!(ImportFormat::from_file_name("users"), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_users_should_report_row_errors() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+
+        let report = state
+            .import_users(ImportFormat::Csv, USERS_CSV, true)
+            .await?;
+        assert!(report.dry_run);
+        assert_eq!(report.total, 11);
+        assert_eq!(report.imported, 10);
+        // elixy@qq.com 已存在
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].row, 10);
+        assert!(state.find_user_by_email("jiang@qq.com").await?.is_none());
+
+        let report = state
+            .import_users(ImportFormat::Csv, USERS_CSV, false)
+            .await?;
+        assert_eq!(report.imported, 10);
+        let user = state.find_user_by_email("jiang@qq.com").await?.unwrap();
+        assert_eq!(user.roles, vec![2]);
+
+        let data = "dept_id,username,email,phone,status,roles,password\n\
+            9,Bad,bad,1,sleeping,x,123\n\
+            1,Good,good@qq.com,1,active,\"1,2\",hunter42\n";
+        let report = state
+            .import_users(ImportFormat::Csv, data.as_bytes(), false)
+            .await?;
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.errors[0].row, 2);
+        assert_eq!(report.errors[0].errors.len(), 4);
+        let user = state.find_user_by_email("good@qq.com").await?.unwrap();
+        assert_eq!(user.roles, vec![1, 2]);
+
+        // 超长的字段在预览时就报告为行错误，不会让整个导入失败
+        let data = format!(
+            "dept_id,username,email,phone,status,roles,password\n\
+            1,Long,{}@qq.com,{},active,2,hunter42\n\
+            1,Short,short@qq.com,1,active,2,hunter42\n",
+            "a".repeat(60),
+            "1".repeat(33)
+        );
+        for dry_run in [true, false] {
+            let report = state
+                .import_users(ImportFormat::Csv, data.as_bytes(), dry_run)
+                .await?;
+            assert_eq!((report.imported, report.failed), (1, 1));
+            assert_eq!(report.errors[0].errors.len(), 2);
+        }
+        assert!(state.find_user_by_email("short@qq.com").await?.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_users_from_xlsx_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (col, header) in [
+            "Dept ID", "Username", "Email", "Phone", "Status", "Roles", "Password",
+        ]
+        .iter()
+        .enumerate()
+        {
+            worksheet.write(0, col as u16, *header)?;
+        }
+        worksheet.write(1, 0, 2)?;
+        worksheet.write(1, 1, "Xlsx")?;
+        worksheet.write(1, 2, "xlsx@qq.com")?;
+        worksheet.write(1, 3, "13800000000")?;
+        worksheet.write(1, 4, "offline")?;
+        worksheet.write(1, 5, 2)?;
+        worksheet.write(1, 6, "hunter42")?;
+        let data = workbook.save_to_buffer()?;
+
+        let report = state.import_users(ImportFormat::Xlsx, &data, false).await?;
+        assert_eq!(report.imported, 1);
+        let user = state.find_user_by_email("xlsx@qq.com").await?.unwrap();
+        assert_eq!(user.dept_id, 2);
+        assert_eq!(user.status, UserStatus::Offline);
+        assert_eq!(user.roles, vec![2]);
+        Ok(())
+    }
+}
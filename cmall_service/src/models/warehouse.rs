@@ -0,0 +1,212 @@
+use chrono::Utc;
+use cmall_core::{EffectStatus, Warehouse};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, AppState, Page, QueryFilter};
+
+const WAREHOUSE_COLUMNS: &str = "id, code, name, address, priority, status, description, create_time, create_by, update_time, update_by";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperateWarehouse {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub address: String,
+    pub priority: i32,
+    pub status: EffectStatus,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(expecting = "params is error")]
+#[serde(rename_all = "camelCase")]
+pub struct SearchWarehouse {
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub status: Option<EffectStatus>,
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+impl AppState {
+    pub async fn create_warehouse(
+        &self,
+        input: &OperateWarehouse,
+        create_by: String,
+    ) -> Result<Warehouse, AppError> {
+        if self.find_warehouse_id_by_code(&input.code).await?.is_some() {
+            return Err(AppError::WarehouseAlreadyExisted(input.code.clone()));
+        }
+        let warehouse = sqlx::query_as(&format!(
+            "INSERT INTO warehouses (code, name, address, priority, status, description, create_by, update_by) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {}",
+            WAREHOUSE_COLUMNS
+        ))
+        .bind(&input.code)
+        .bind(&input.name)
+        .bind(&input.address)
+        .bind(input.priority)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(&create_by)
+        .bind(&create_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(warehouse)
+    }
+
+    // 停用的仓库保留库存，但不再参与下单分配
+    pub async fn update_warehouse(
+        &self,
+        id: i64,
+        input: &OperateWarehouse,
+        update_by: String,
+    ) -> Result<Warehouse, AppError> {
+        if self.find_warehouse_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("warehouse id {}", id)));
+        }
+        if let Some(warehouse_id) = self.find_warehouse_id_by_code(&input.code).await? {
+            if warehouse_id != id {
+                return Err(AppError::WarehouseAlreadyExisted(input.code.clone()));
+            }
+        }
+        let warehouse = sqlx::query_as(&format!(
+            "UPDATE warehouses SET code = $1, name = $2, address = $3, priority = $4, status = $5, description = $6, update_by = $7, update_time = $8 WHERE id = $9 RETURNING {}",
+            WAREHOUSE_COLUMNS
+        ))
+        .bind(&input.code)
+        .bind(&input.name)
+        .bind(&input.address)
+        .bind(input.priority)
+        .bind(&input.status)
+        .bind(&input.description)
+        .bind(update_by)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(warehouse)
+    }
+
+    // 仍有库存、调拨或订单记录的仓库只能停用，不能删除
+    pub async fn delete_warehouse(&self, id: i64) -> Result<bool, AppError> {
+        if self.find_warehouse_by_id(id).await?.is_none() {
+            return Err(AppError::NotFound(format!("warehouse id {}", id)));
+        }
+        let in_use: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM inventories WHERE warehouse_id = $1 AND on_hand > 0)
+                OR EXISTS(SELECT 1 FROM stock_transfers WHERE from_warehouse_id = $1 OR to_warehouse_id = $1)
+                OR EXISTS(SELECT 1 FROM order_allocations WHERE warehouse_id = $1)
+        "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if in_use {
+            return Err(AppError::WarehouseInUse(id));
+        }
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM inventories WHERE warehouse_id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let result = sqlx::query(
+            r#"
+            DELETE FROM warehouses WHERE id = $1
+        "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find_warehouse_by_id(&self, id: i64) -> Result<Option<Warehouse>, AppError> {
+        let warehouse = sqlx::query_as(&format!(
+            "SELECT {} FROM warehouses WHERE id = $1",
+            WAREHOUSE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(warehouse)
+    }
+
+    pub async fn find_warehouse_by_condition(
+        &self,
+        input: &SearchWarehouse,
+        page: Page,
+    ) -> Result<(Vec<Warehouse>, i64), AppError> {
+        QueryFilter::new()
+            .eq("code", input.code.clone())
+            .contains("name", input.name.as_deref())
+            .eq("status", input.status.clone())
+            .fetch_page(
+                &self.pool,
+                WAREHOUSE_COLUMNS,
+                "warehouses",
+                "priority, id",
+                page,
+            )
+            .await
+    }
+
+    async fn find_warehouse_id_by_code(&self, code: &str) -> Result<Option<i64>, AppError> {
+        let id = sqlx::query_scalar(
+            r#"
+            SELECT id FROM warehouses WHERE code = $1
+        "#,
+        )
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod test_warehouse {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_warehouse_crud_should_work() -> Result<()> {
+        let (_tdb, state) = AppState::new_for_test().await?;
+        let mut input = OperateWarehouse {
+            code: "sh".to_string(),
+            name: "上海仓".to_string(),
+            address: "".to_string(),
+            priority: 2,
+            status: EffectStatus::Enable,
+            description: "".to_string(),
+        };
+        let warehouse = state.create_warehouse(&input, "admin".to_string()).await?;
+        let ret = state.create_warehouse(&input, "admin".to_string()).await;
+        assert!(matches!(ret, Err(AppError::WarehouseAlreadyExisted(_))));
+
+        input.status = EffectStatus::Disable;
+        let updated = state
+            .update_warehouse(warehouse.id, &input, "admin".to_string())
+            .await?;
+        assert_eq!(updated.status, EffectStatus::Disable);
+
+        // 默认仓库排在前面
+        let (warehouses, total) = state
+            .find_warehouse_by_condition(&SearchWarehouse::default(), Page::new(1, 10)?)
+            .await?;
+        assert_eq!(total, 2);
+        assert_eq!(warehouses[0].code, "default");
+
+        assert!(state.delete_warehouse(warehouse.id).await?);
+        assert!(state.find_warehouse_by_id(warehouse.id).await?.is_none());
+        Ok(())
+    }
+}
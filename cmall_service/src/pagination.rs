@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgRow, Encode, FromRow, PgPool, Postgres, QueryBuilder, Type};
+
+use crate::error::AppError;
+
+// 单页最多返回的记录数，超过时按上限处理
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+// 经过校验的分页参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Page {
+    pub page_num: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_num: Option<i64>,
+    pub page_size: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<i64>,
+    pub has_more: bool,
+    // 游标分页时用于请求下一页
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+// 支持游标分页的记录，游标为按降序排列的唯一键
+pub trait Keyset {
+    fn key(&self) -> i64;
+}
+
+type Condition = Box<dyn Fn(&mut QueryBuilder<'_, Postgres>) + Send + Sync>;
+
+// 列表和总数共用的查询条件，值为空的条件不会出现在 SQL 中
+#[derive(Default)]
+pub struct QueryFilter {
+    conditions: Vec<Condition>,
+}
+
+impl Page {
+    pub fn new(page_num: i64, page_size: i64) -> Result<Self, AppError> {
+        if page_num < 1 {
+            return Err(AppError::InvalidPagination(format!(
+                "pageNum must be at least 1, got {}",
+                page_num
+            )));
+        }
+        if page_size < 1 {
+            return Err(AppError::InvalidPagination(format!(
+                "pageSize must be at least 1, got {}",
+                page_size
+            )));
+        }
+        Ok(Self {
+            page_num,
+            page_size: page_size.min(MAX_PAGE_SIZE),
+        })
+    }
+
+    // 内部批量读取（如导出）时使用，不受 MAX_PAGE_SIZE 限制
+    pub fn first(limit: i64) -> Self {
+        Self {
+            page_num: 1,
+            page_size: limit,
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page_num - 1).saturating_mul(self.page_size)
+    }
+
+    pub fn info(&self, total: i64) -> PageInfo {
+        PageInfo {
+            page_num: Some(self.page_num),
+            page_size: self.page_size,
+            total_pages: Some((total + self.page_size - 1) / self.page_size),
+            has_more: self.page_num.saturating_mul(self.page_size) < total,
+            next_cursor: None,
+        }
+    }
+}
+
+impl QueryFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 不需要绑定参数的固定条件，例如 deleted_at IS NULL
+    pub fn raw(mut self, sql: &'static str) -> Self {
+        self.conditions.push(Box::new(move |qb| {
+            qb.push(sql);
+        }));
+        self
+    }
+
+    // sql 为参数之前的部分，suffix 为参数之后的部分
+    pub fn bind<T>(mut self, sql: &'static str, value: Option<T>, suffix: &'static str) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Clone + Send + Sync + 'static,
+    {
+        if let Some(value) = value {
+            self.conditions.push(Box::new(move |qb| {
+                qb.push(sql);
+                qb.push_bind(value.clone());
+                qb.push(suffix);
+            }));
+        }
+        self
+    }
+
+    pub fn eq<T>(self, column: &'static str, value: Option<T>) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Clone + Send + Sync + 'static,
+    {
+        self.compare(column, " = ", value)
+    }
+
+    // 任意一列等于该值即可
+    pub fn eq_any<T>(mut self, columns: &'static [&'static str], value: Option<T>) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Clone + Send + Sync + 'static,
+    {
+        if let Some(value) = value {
+            self.conditions.push(Box::new(move |qb| {
+                qb.push("(");
+                for (i, column) in columns.iter().enumerate() {
+                    if i > 0 {
+                        qb.push(" OR ");
+                    }
+                    qb.push(*column);
+                    qb.push(" = ");
+                    qb.push_bind(value.clone());
+                }
+                qb.push(")");
+            }));
+        }
+        self
+    }
+
+    pub fn gte<T>(self, column: &'static str, value: Option<T>) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Clone + Send + Sync + 'static,
+    {
+        self.compare(column, " >= ", value)
+    }
+
+    pub fn lt<T>(self, column: &'static str, value: Option<T>) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Clone + Send + Sync + 'static,
+    {
+        self.compare(column, " < ", value)
+    }
+
+    // 不区分大小写的子串匹配，通配符按普通字符处理
+    pub fn contains(self, column: &'static str, value: Option<&str>) -> Self {
+        self.compare(column, " ILIKE ", value.map(like_pattern))
+    }
+
+    fn compare<T>(mut self, column: &'static str, op: &'static str, value: Option<T>) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Clone + Send + Sync + 'static,
+    {
+        if let Some(value) = value {
+            self.conditions.push(Box::new(move |qb| {
+                qb.push(column);
+                qb.push(op);
+                qb.push_bind(value.clone());
+            }));
+        }
+        self
+    }
+
+    fn push_where(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        qb.push(" WHERE TRUE");
+        for condition in &self.conditions {
+            qb.push(" AND ");
+            condition(qb);
+        }
+    }
+
+    // 按页码查询，同时返回满足条件的总记录数；order_by 必须来自白名单
+    pub async fn fetch_page<T>(
+        &self,
+        pool: &PgPool,
+        columns: &str,
+        from: &str,
+        order_by: &str,
+        page: Page,
+    ) -> Result<(Vec<T>, i64), AppError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut qb = QueryBuilder::new(format!("SELECT {} FROM {}", columns, from));
+        self.push_where(&mut qb);
+        qb.push(format!(" ORDER BY {} LIMIT ", order_by));
+        qb.push_bind(page.page_size);
+        qb.push(" OFFSET ");
+        qb.push_bind(page.offset());
+        let rows = qb.build_query_as().fetch_all(pool).await?;
+
+        let mut qb = QueryBuilder::new(format!("SELECT COUNT(*) FROM {}", from));
+        self.push_where(&mut qb);
+        let total: i64 = qb.build_query_scalar().fetch_one(pool).await?;
+        Ok((rows, total))
+    }
+
+    // 不分页读取全部满足条件的记录，用于组装树等数据量有限的场景
+    pub async fn fetch_all<T>(
+        &self,
+        pool: &PgPool,
+        columns: &str,
+        from: &str,
+        order_by: &str,
+    ) -> Result<Vec<T>, AppError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut qb = QueryBuilder::new(format!("SELECT {} FROM {}", columns, from));
+        self.push_where(&mut qb);
+        qb.push(format!(" ORDER BY {}", order_by));
+        Ok(qb.build_query_as().fetch_all(pool).await?)
+    }
+
+    // 游标分页，按 key 降序返回 cursor 之后的记录，不统计总数
+    pub async fn fetch_after<T>(
+        self,
+        pool: &PgPool,
+        columns: &str,
+        from: &str,
+        key: &'static str,
+        cursor: Option<i64>,
+        limit: i64,
+    ) -> Result<(Vec<T>, PageInfo), AppError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Keyset + Send + Unpin,
+    {
+        let filter = self.compare(key, " < ", cursor);
+        let mut qb = QueryBuilder::new(format!("SELECT {} FROM {}", columns, from));
+        filter.push_where(&mut qb);
+        qb.push(format!(" ORDER BY {} DESC LIMIT ", key));
+        // 多取一条用于判断是否还有下一页
+        qb.push_bind(limit + 1);
+        let mut rows: Vec<T> = qb.build_query_as().fetch_all(pool).await?;
+
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        let info = PageInfo {
+            page_size: limit,
+            has_more,
+            next_cursor: rows.last().filter(|_| has_more).map(Keyset::key),
+            ..PageInfo::default()
+        };
+        Ok((rows, info))
+    }
+}
+
+// 转义通配符后构造子串匹配的 ILIKE 模式
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod test_pagination {
+    use super::*;
+
+    #[test]
+    fn test_page_should_validate_bounds() {
+        assert!(matches!(
+            Page::new(0, 10),
+            Err(AppError::InvalidPagination(_))
+        ));
+        assert!(matches!(
+            Page::new(1, 0),
+            Err(AppError::InvalidPagination(_))
+        ));
+
+        let page = Page::new(3, 1000).unwrap();
+        assert_eq!(page.page_size, MAX_PAGE_SIZE);
+        assert_eq!(page.offset(), 200);
+
+        let info = Page::new(2, 10).unwrap().info(25);
+        assert_eq!(info.total_pages, Some(3));
+        assert!(info.has_more);
+        let info = Page::new(3, 10).unwrap().info(25);
+        assert!(!info.has_more);
+    }
+
+    #[test]
+    fn test_query_filter_should_skip_empty_values() {
+        let filter = QueryFilter::new()
+            .raw("deleted_at IS NULL")
+            .eq("status", Some("active".to_string()))
+            .eq::<i64>("dept_id", None)
+            .contains("username", Some("a_b"));
+        let mut qb = QueryBuilder::new("SELECT id FROM users");
+        filter.push_where(&mut qb);
+        assert_eq!(
+            qb.sql(),
+            "SELECT id FROM users WHERE TRUE AND deleted_at IS NULL AND status = $1 AND username ILIKE $2"
+        );
+        let filter = QueryFilter::new().eq_any(&["chinese_name", "english_name"], Some(1));
+        let mut qb = QueryBuilder::new("SELECT id FROM menus");
+        filter.push_where(&mut qb);
+        assert_eq!(
+            qb.sql(),
+            "SELECT id FROM menus WHERE TRUE AND (chinese_name = $1 OR english_name = $2)"
+        );
+        assert_eq!(like_pattern("a_b%"), "%a\\_b\\%%");
+    }
+}
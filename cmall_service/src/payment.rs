@@ -0,0 +1,298 @@
+use std::{collections::HashMap, future::Future, sync::Mutex};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use cmall_core::PaymentStatus;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{error::AppError, PaymentConfig, PaymentGatewayConfig};
+
+// 模拟支付渠道回调时携带签名的请求头
+pub const MOCK_SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-mock-signature");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentRequest {
+    pub payment_id: i64,
+    pub order_id: i64,
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentIntent {
+    pub external_id: String,
+    // 客户端跳转到该地址完成支付
+    pub pay_url: String,
+}
+
+// 验签后的回调内容，event_id 用于去重
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentEvent {
+    pub event_id: String,
+    pub external_id: String,
+    pub status: PaymentStatus,
+    pub amount: i64,
+}
+
+pub trait PaymentGateway {
+    // 渠道名称，同时是回调地址中的路径参数
+    fn name(&self) -> &'static str;
+
+    fn create_intent(
+        &self,
+        request: &PaymentRequest,
+    ) -> impl Future<Output = Result<PaymentIntent, AppError>> + Send;
+
+    fn query(
+        &self,
+        external_id: &str,
+    ) -> impl Future<Output = Result<PaymentStatus, AppError>> + Send;
+
+    // 同一交易号重复退款必须返回成功，调用方失败后会重试
+    fn refund(
+        &self,
+        external_id: &str,
+        amount: i64,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+
+    // 校验回调签名并解析内容，签名错误时返回 InvalidPaymentSignature
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<PaymentEvent, AppError>;
+}
+
+// 根据配置选择的支付渠道
+pub enum PaymentProvider {
+    Mock(MockGateway),
+}
+
+impl PaymentProvider {
+    pub fn try_new(config: &PaymentConfig) -> Result<Self, AppError> {
+        match &config.gateway {
+            // 模拟渠道不经过真实付款，release 构建中拒绝启动
+            PaymentGatewayConfig::Mock { .. } if !cfg!(debug_assertions) => Err(
+                AppError::PaymentError("mock gateway is not allowed in release builds".to_string()),
+            ),
+            PaymentGatewayConfig::Mock { secret } => {
+                warn!("mock payment gateway enabled, orders can be paid without real payment");
+                let secret = secret.clone().unwrap_or_else(|| {
+                    let mut bytes = [0u8; 32];
+                    OsRng.fill_bytes(&mut bytes);
+                    hex::encode(bytes)
+                });
+                Ok(Self::Mock(MockGateway::new(&secret)))
+            }
+        }
+    }
+}
+
+impl PaymentGateway for PaymentProvider {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Mock(gateway) => gateway.name(),
+        }
+    }
+
+    async fn create_intent(&self, request: &PaymentRequest) -> Result<PaymentIntent, AppError> {
+        match self {
+            Self::Mock(gateway) => gateway.create_intent(request).await,
+        }
+    }
+
+    async fn query(&self, external_id: &str) -> Result<PaymentStatus, AppError> {
+        match self {
+            Self::Mock(gateway) => gateway.query(external_id).await,
+        }
+    }
+
+    async fn refund(&self, external_id: &str, amount: i64) -> Result<(), AppError> {
+        match self {
+            Self::Mock(gateway) => gateway.refund(external_id, amount).await,
+        }
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<PaymentEvent, AppError> {
+        match self {
+            Self::Mock(gateway) => gateway.verify_webhook(headers, body),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MockIntent {
+    amount: i64,
+    status: PaymentStatus,
+}
+
+// 在内存中保存交易的模拟渠道，回调内容使用 HMAC-SHA256 签名
+pub struct MockGateway {
+    secret: String,
+    intents: Mutex<HashMap<String, MockIntent>>,
+}
+
+impl MockGateway {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            intents: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // 模拟用户在渠道侧完成或放弃支付，返回渠道发送的回调请求头和内容
+    pub fn simulate_callback(
+        &self,
+        external_id: &str,
+        status: PaymentStatus,
+    ) -> Result<(HeaderMap, Vec<u8>), AppError> {
+        let amount = {
+            let mut intents = self.intents.lock().expect("mock intents poisoned");
+            let intent = intents
+                .get_mut(external_id)
+                .ok_or_else(|| AppError::NotFound(format!("payment {}", external_id)))?;
+            intent.status = status;
+            intent.amount
+        };
+        let event = PaymentEvent {
+            event_id: Uuid::now_v7().to_string(),
+            external_id: external_id.to_string(),
+            status,
+            amount,
+        };
+        let body = serde_json::to_vec(&event).map_err(|e| AppError::AnyError(e.into()))?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            MOCK_SIGNATURE_HEADER,
+            HeaderValue::from_str(&self.sign(&body))?,
+        );
+        Ok((headers, body))
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("hmac accepts any key")
+    }
+
+    fn sign(&self, body: &[u8]) -> String {
+        let mut mac = self.mac();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+impl PaymentGateway for MockGateway {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn create_intent(&self, request: &PaymentRequest) -> Result<PaymentIntent, AppError> {
+        let external_id = format!("mock_{}", Uuid::now_v7().simple());
+        let intent = MockIntent {
+            amount: request.amount,
+            status: PaymentStatus::Pending,
+        };
+        self.intents
+            .lock()
+            .expect("mock intents poisoned")
+            .insert(external_id.clone(), intent);
+        info!(
+            "mock payment {} created for order {}",
+            external_id, request.order_id
+        );
+        Ok(PaymentIntent {
+            pay_url: format!("mock://pay/{}", external_id),
+            external_id,
+        })
+    }
+
+    async fn query(&self, external_id: &str) -> Result<PaymentStatus, AppError> {
+        let intents = self.intents.lock().expect("mock intents poisoned");
+        intents
+            .get(external_id)
+            .map(|i| i.status)
+            .ok_or_else(|| AppError::NotFound(format!("payment {}", external_id)))
+    }
+
+    // 与真实渠道一样按交易号幂等，重复退款直接返回成功
+    async fn refund(&self, external_id: &str, amount: i64) -> Result<(), AppError> {
+        let mut intents = self.intents.lock().expect("mock intents poisoned");
+        let intent = intents
+            .get_mut(external_id)
+            .ok_or_else(|| AppError::NotFound(format!("payment {}", external_id)))?;
+        if intent.status == PaymentStatus::Refunded && intent.amount == amount {
+            return Ok(());
+        }
+        if intent.status != PaymentStatus::Succeeded || intent.amount != amount {
+            return Err(AppError::PaymentError(format!(
+                "payment {} cannot be refunded",
+                external_id
+            )));
+        }
+        intent.status = PaymentStatus::Refunded;
+        Ok(())
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<PaymentEvent, AppError> {
+        let signature = headers
+            .get(MOCK_SIGNATURE_HEADER)
+            .and_then(|v| hex::decode(v.as_bytes()).ok())
+            .ok_or(AppError::InvalidPaymentSignature)?;
+        let mut mac = self.mac();
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| AppError::InvalidPaymentSignature)?;
+        serde_json::from_slice(body).map_err(|e| AppError::PaymentError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test_payment {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_mock_gateway_should_verify_signature() -> Result<()> {
+        let gateway = MockGateway::new("secret");
+        let request = PaymentRequest {
+            payment_id: 1,
+            order_id: 1,
+            amount: 1000,
+        };
+        let intent = gateway.create_intent(&request).await?;
+        let (headers, body) =
+            gateway.simulate_callback(&intent.external_id, PaymentStatus::Succeeded)?;
+        let event = gateway.verify_webhook(&headers, &body)?;
+        assert_eq!(event.amount, 1000);
+        assert_eq!(
+            gateway.query(&intent.external_id).await?,
+            PaymentStatus::Succeeded
+        );
+
+        // 内容被篡改或使用其他密钥签名时验签失败
+        let mut tampered = body.clone();
+        tampered[0] = b' ';
+        let ret = gateway.verify_webhook(&headers, &tampered);
+        assert!(matches!(ret, Err(AppError::InvalidPaymentSignature)));
+        let ret = MockGateway::new("other").verify_webhook(&headers, &body);
+        assert!(matches!(ret, Err(AppError::InvalidPaymentSignature)));
+
+        gateway.refund(&intent.external_id, 1000).await?;
+        gateway.refund(&intent.external_id, 1000).await?;
+        assert!(gateway.refund(&intent.external_id, 500).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_secret_should_be_random_when_not_configured() -> Result<()> {
+        let config = PaymentConfig {
+            gateway: PaymentGatewayConfig::Mock { secret: None },
+        };
+        let (PaymentProvider::Mock(a), PaymentProvider::Mock(b)) = (
+            PaymentProvider::try_new(&config)?,
+            PaymentProvider::try_new(&config)?,
+        );
+        assert_ne!(a.sign(b"{}"), b.sign(b"{}"));
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+use crate::{export_audit_handler, list_audit_handler, AppState};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_audit_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/export",
+            post(export_audit_handler).require_permission(state, "audit:export"),
+        )
+        .route(
+            "/",
+            get(list_audit_handler).require_permission(state, "audit:query"),
+        )
+}
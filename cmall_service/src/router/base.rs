@@ -1,16 +1,48 @@
 use crate::AppState;
 use axum::Router;
 
-use super::{setup_role_router, setup_user_router};
+use super::{
+    setup_audit_router, setup_category_router, setup_department_router, setup_inventory_router,
+    setup_menu_router, setup_order_router, setup_product_router, setup_recycle_router,
+    setup_role_router, setup_transfer_router, setup_user_router, setup_warehouse_router,
+};
 
-pub fn setup_base_router() -> Router<AppState> {
-    let user_router = setup_user_router();
+pub fn setup_base_router(state: &AppState) -> Router<AppState> {
+    let user_router = setup_user_router(state);
 
-    let role_router = setup_role_router();
+    let role_router = setup_role_router(state);
 
-    let base_router = Router::new()
-        .nest("/user", user_router)
-        .nest("/role", role_router);
+    let department_router = setup_department_router(state);
+
+    let menu_router = setup_menu_router(state);
+
+    let audit_router = setup_audit_router(state);
+
+    let recycle_router = setup_recycle_router(state);
+
+    let product_router = setup_product_router(state);
+
+    let category_router = setup_category_router(state);
 
-    base_router
+    let inventory_router = setup_inventory_router(state);
+
+    let warehouse_router = setup_warehouse_router(state);
+
+    let transfer_router = setup_transfer_router(state);
+
+    let order_router = setup_order_router(state);
+
+    Router::new()
+        .nest("/user", user_router)
+        .nest("/role", role_router)
+        .nest("/dept", department_router)
+        .nest("/menu", menu_router)
+        .nest("/audit", audit_router)
+        .nest("/recycle", recycle_router)
+        .nest("/product", product_router)
+        .nest("/category", category_router)
+        .nest("/inventory", inventory_router)
+        .nest("/warehouse", warehouse_router)
+        .nest("/transfer", transfer_router)
+        .nest("/order", order_router)
 }
@@ -0,0 +1,23 @@
+use crate::{
+    add_guest_cart_item_handler, add_my_cart_item_handler, get_guest_cart_handler,
+    get_my_cart_handler, remove_guest_cart_item_handler, remove_my_cart_item_handler,
+    update_guest_cart_item_handler, update_my_cart_item_handler, AppState,
+};
+use axum::{routing::*, Router};
+
+// 登录用户只能访问自己的购物车，不需要额外权限
+pub fn setup_cart_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_my_cart_handler))
+        .route("/items", post(add_my_cart_item_handler))
+        .route("/items/:sku_id", post(update_my_cart_item_handler))
+        .route("/items/:sku_id", delete(remove_my_cart_item_handler))
+}
+
+pub fn setup_guest_cart_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_guest_cart_handler))
+        .route("/items", post(add_guest_cart_item_handler))
+        .route("/items/:sku_id", post(update_guest_cart_item_handler))
+        .route("/items/:sku_id", delete(remove_guest_cart_item_handler))
+}
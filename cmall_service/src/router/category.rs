@@ -0,0 +1,38 @@
+use crate::{
+    category_tree_handler, create_category_handler, delete_category_handler, get_category_handler,
+    list_category_handler, move_category_handler, update_category_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_category_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/tree",
+            get(category_tree_handler).require_permission(state, "category:query"),
+        )
+        .route(
+            "/:id",
+            get(get_category_handler).require_permission(state, "category:query"),
+        )
+        .route(
+            "/:id",
+            post(update_category_handler).require_permission(state, "category:update"),
+        )
+        .route(
+            "/:id",
+            delete(delete_category_handler).require_permission(state, "category:delete"),
+        )
+        .route(
+            "/:id/move",
+            post(move_category_handler).require_permission(state, "category:update"),
+        )
+        .route(
+            "/",
+            get(list_category_handler).require_permission(state, "category:query"),
+        )
+        .route(
+            "/",
+            post(create_category_handler).require_permission(state, "category:create"),
+        )
+}
@@ -0,0 +1,43 @@
+use crate::{
+    create_department_handler, delete_department_handler, department_tree_handler,
+    export_departments_handler, get_department_handler, list_department_handler,
+    restore_department_handler, update_department_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_department_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/tree",
+            get(department_tree_handler).require_permission(state, "dept:query"),
+        )
+        .route(
+            "/export",
+            post(export_departments_handler).require_permission(state, "dept:export"),
+        )
+        .route(
+            "/:id",
+            get(get_department_handler).require_permission(state, "dept:query"),
+        )
+        .route(
+            "/:id",
+            delete(delete_department_handler).require_permission(state, "dept:delete"),
+        )
+        .route(
+            "/:id",
+            post(update_department_handler).require_permission(state, "dept:update"),
+        )
+        .route(
+            "/:id/restore",
+            post(restore_department_handler).require_permission(state, "dept:restore"),
+        )
+        .route(
+            "/",
+            get(list_department_handler).require_permission(state, "dept:query"),
+        )
+        .route(
+            "/",
+            post(create_department_handler).require_permission(state, "dept:create"),
+        )
+}
@@ -0,0 +1,38 @@
+use crate::{
+    adjust_stock_handler, export_low_stock_handler, get_inventory_handler, list_low_stock_handler,
+    list_stock_movements_handler, receive_stock_handler, return_stock_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_inventory_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/low-stock",
+            get(list_low_stock_handler).require_permission(state, "inventory:query"),
+        )
+        .route(
+            "/low-stock/export",
+            post(export_low_stock_handler).require_permission(state, "inventory:export"),
+        )
+        .route(
+            "/:sku_id",
+            get(get_inventory_handler).require_permission(state, "inventory:query"),
+        )
+        .route(
+            "/:sku_id/movements",
+            get(list_stock_movements_handler).require_permission(state, "inventory:query"),
+        )
+        .route(
+            "/:sku_id/inbound",
+            post(receive_stock_handler).require_permission(state, "inventory:adjust"),
+        )
+        .route(
+            "/:sku_id/return",
+            post(return_stock_handler).require_permission(state, "inventory:adjust"),
+        )
+        .route(
+            "/:sku_id/adjust",
+            post(adjust_stock_handler).require_permission(state, "inventory:adjust"),
+        )
+}
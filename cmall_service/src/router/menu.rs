@@ -0,0 +1,42 @@
+use crate::{
+    create_menu_handler, delete_menu_handler, export_menus_handler, get_menu_handler,
+    list_menu_handler, menu_tree_handler, restore_menu_handler, update_menu_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_menu_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/tree",
+            get(menu_tree_handler).require_permission(state, "menu:query"),
+        )
+        .route(
+            "/export",
+            post(export_menus_handler).require_permission(state, "menu:export"),
+        )
+        .route(
+            "/:id",
+            get(get_menu_handler).require_permission(state, "menu:query"),
+        )
+        .route(
+            "/:id",
+            delete(delete_menu_handler).require_permission(state, "menu:delete"),
+        )
+        .route(
+            "/:id",
+            post(update_menu_handler).require_permission(state, "menu:update"),
+        )
+        .route(
+            "/:id/restore",
+            post(restore_menu_handler).require_permission(state, "menu:restore"),
+        )
+        .route(
+            "/",
+            get(list_menu_handler).require_permission(state, "menu:query"),
+        )
+        .route(
+            "/",
+            post(create_menu_handler).require_permission(state, "menu:create"),
+        )
+}
@@ -7,3 +7,35 @@ pub use base::*;
 mod role;
 pub use role::*;
 
+mod department;
+pub use department::*;
+
+mod menu;
+pub use menu::*;
+
+mod audit;
+pub use audit::*;
+
+mod recycle;
+pub use recycle::*;
+
+mod product;
+pub use product::*;
+
+mod category;
+pub use category::*;
+
+mod inventory;
+pub use inventory::*;
+
+mod warehouse;
+pub use warehouse::*;
+
+mod transfer;
+pub use transfer::*;
+
+mod cart;
+pub use cart::*;
+
+mod order;
+pub use order::*;
@@ -0,0 +1,43 @@
+use crate::{
+    cancel_my_order_handler, change_order_status_handler, checkout_handler,
+    confirm_my_order_handler, get_my_order_handler, get_order_handler, list_my_order_handler,
+    list_order_handler, list_order_payments_handler, pay_my_order_handler, sync_payment_handler,
+    AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_order_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/:id",
+            get(get_order_handler).require_permission(state, "order:query"),
+        )
+        .route(
+            "/:id/status",
+            post(change_order_status_handler).require_permission(state, "order:update"),
+        )
+        .route(
+            "/:id/payments",
+            get(list_order_payments_handler).require_permission(state, "order:query"),
+        )
+        .route(
+            "/:id/payments/:payment_id/sync",
+            post(sync_payment_handler).require_permission(state, "order:update"),
+        )
+        .route(
+            "/",
+            get(list_order_handler).require_permission(state, "order:query"),
+        )
+}
+
+// 用户自己的订单，不需要额外权限
+pub fn setup_my_order_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_my_order_handler))
+        .route("/checkout", post(checkout_handler))
+        .route("/:id", get(get_my_order_handler))
+        .route("/:id/pay", post(pay_my_order_handler))
+        .route("/:id/cancel", post(cancel_my_order_handler))
+        .route("/:id/confirm", post(confirm_my_order_handler))
+}
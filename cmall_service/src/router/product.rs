@@ -0,0 +1,51 @@
+use crate::{
+    create_product_handler, create_sku_handler, delete_product_handler, delete_sku_handler,
+    export_products_handler, get_product_handler, list_product_handler, set_product_status_handler,
+    update_product_handler, update_sku_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_product_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/:id",
+            get(get_product_handler).require_permission(state, "product:query"),
+        )
+        .route(
+            "/:id",
+            post(update_product_handler).require_permission(state, "product:update"),
+        )
+        .route(
+            "/:id",
+            delete(delete_product_handler).require_permission(state, "product:delete"),
+        )
+        .route(
+            "/:id/status",
+            post(set_product_status_handler).require_permission(state, "product:update"),
+        )
+        .route(
+            "/:id/skus",
+            post(create_sku_handler).require_permission(state, "product:update"),
+        )
+        .route(
+            "/:id/skus/:sku_id",
+            post(update_sku_handler).require_permission(state, "product:update"),
+        )
+        .route(
+            "/:id/skus/:sku_id",
+            delete(delete_sku_handler).require_permission(state, "product:update"),
+        )
+        .route(
+            "/export",
+            post(export_products_handler).require_permission(state, "product:export"),
+        )
+        .route(
+            "/",
+            get(list_product_handler).require_permission(state, "product:query"),
+        )
+        .route(
+            "/",
+            post(create_product_handler).require_permission(state, "product:create"),
+        )
+}
@@ -0,0 +1,15 @@
+use crate::{list_recycle_handler, purge_recycle_handler, AppState};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_recycle_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/purge",
+            post(purge_recycle_handler).require_permission(state, "recycle:purge"),
+        )
+        .route(
+            "/",
+            get(list_recycle_handler).require_permission(state, "recycle:query"),
+        )
+}
@@ -1,15 +1,52 @@
-use crate::{create_role_handler, delete_role_handler, list_role_handler, update_role_handler, AppState};
+use crate::{
+    assign_role_menus_handler, create_role_handler, delete_role_handler, export_roles_handler,
+    list_role_handler, list_role_menus_handler, restore_role_handler, revoke_role_menus_handler,
+    set_role_mfa_handler, update_role_handler, AppState,
+};
 use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
 
-pub fn setup_role_router() -> Router<AppState> {
-    let role_router = Router::new()
+pub fn setup_role_router(state: &AppState) -> Router<AppState> {
+    Router::new()
         .route(
             "/:id",
             // get(get_role_handler)
-            delete(delete_role_handler).post(update_role_handler),
+            delete(delete_role_handler).require_permission(state, "role:delete"),
+        )
+        .route(
+            "/:id",
+            post(update_role_handler).require_permission(state, "role:update"),
+        )
+        .route(
+            "/:id/restore",
+            post(restore_role_handler).require_permission(state, "role:restore"),
+        )
+        .route(
+            "/:id/mfa",
+            post(set_role_mfa_handler).require_permission(state, "role:update"),
+        )
+        .route(
+            "/:id/menus",
+            get(list_role_menus_handler).require_permission(state, "role:query"),
+        )
+        .route(
+            "/:id/menus",
+            post(assign_role_menus_handler).require_permission(state, "role:grant"),
+        )
+        .route(
+            "/:id/menus",
+            delete(revoke_role_menus_handler).require_permission(state, "role:grant"),
+        )
+        .route(
+            "/export",
+            post(export_roles_handler).require_permission(state, "role:export"),
+        )
+        .route(
+            "/",
+            get(list_role_handler).require_permission(state, "role:query"),
+        )
+        .route(
+            "/",
+            post(create_role_handler).require_permission(state, "role:create"),
         )
-        // .route("/export", post(export_roles_handler))
-        .route("/", get(list_role_handler).post(create_role_handler));
-
-    role_router
 }
@@ -0,0 +1,30 @@
+use crate::{
+    cancel_transfer_handler, complete_transfer_handler, create_transfer_handler,
+    get_transfer_handler, list_transfer_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_transfer_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/:id",
+            get(get_transfer_handler).require_permission(state, "transfer:query"),
+        )
+        .route(
+            "/:id/complete",
+            post(complete_transfer_handler).require_permission(state, "transfer:update"),
+        )
+        .route(
+            "/:id/cancel",
+            post(cancel_transfer_handler).require_permission(state, "transfer:update"),
+        )
+        .route(
+            "/",
+            get(list_transfer_handler).require_permission(state, "transfer:query"),
+        )
+        .route(
+            "/",
+            post(create_transfer_handler).require_permission(state, "transfer:create"),
+        )
+}
@@ -1,27 +1,74 @@
 use crate::{
-    // create_user_handler,
-    // delete_user_handler,
-    create_user_handler,
-    delete_user_handler,
-    export_users_handler,
-    get_user_handler,
-    list_user_handler,
-    update_user_handler,
-    AppState, // list_user_handler,
-              // update_user_handler,
+    add_user_role_handler, change_my_password_handler, create_user_handler, delete_user_handler,
+    disable_my_mfa_handler, download_export_handler, enable_my_mfa_handler, export_users_handler,
+    get_export_job_handler, get_user_handler, import_users_handler, list_user_handler,
+    my_menus_handler, remove_user_role_handler, reset_user_password_handler, restore_user_handler,
+    setup_my_mfa_handler, unlock_user_handler, update_user_handler, AppState,
 };
 use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
 
-pub fn setup_user_router() -> Router<AppState> {
-    let user_router = Router::new()
+pub fn setup_user_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/me/menus", get(my_menus_handler))
+        .route("/me/password", post(change_my_password_handler))
+        .route("/me/mfa", post(setup_my_mfa_handler))
+        .route("/me/mfa/enable", post(enable_my_mfa_handler))
+        .route("/me/mfa/disable", post(disable_my_mfa_handler))
         .route(
             "/:id",
-            get(get_user_handler)
-                .delete(delete_user_handler)
-                .post(update_user_handler),
+            get(get_user_handler).require_permission(state, "user:query"),
+        )
+        .route(
+            "/:id",
+            delete(delete_user_handler).require_permission(state, "user:delete"),
+        )
+        .route(
+            "/:id",
+            post(update_user_handler).require_permission(state, "user:update"),
+        )
+        .route(
+            "/:id/restore",
+            post(restore_user_handler).require_permission(state, "user:restore"),
+        )
+        .route(
+            "/:id/roles/:role_id",
+            post(add_user_role_handler).require_permission(state, "user:update"),
+        )
+        .route(
+            "/:id/roles/:role_id",
+            delete(remove_user_role_handler).require_permission(state, "user:update"),
+        )
+        .route(
+            "/:id/reset-password",
+            post(reset_user_password_handler).require_permission(state, "user:reset-password"),
+        )
+        .route(
+            "/:id/unlock",
+            post(unlock_user_handler).require_permission(state, "user:unlock"),
+        )
+        .route(
+            "/import",
+            post(import_users_handler).require_permission(state, "user:import"),
+        )
+        .route(
+            "/export",
+            post(export_users_handler).require_permission(state, "user:export"),
+        )
+        .route(
+            "/export/:job_id",
+            get(get_export_job_handler).require_permission(state, "user:export"),
+        )
+        .route(
+            "/export/:job_id/download",
+            get(download_export_handler).require_permission(state, "user:export"),
+        )
+        .route(
+            "/",
+            get(list_user_handler).require_permission(state, "user:query"),
+        )
+        .route(
+            "/",
+            post(create_user_handler).require_permission(state, "user:create"),
         )
-        .route("/export", post(export_users_handler))
-        .route("/", get(list_user_handler).post(create_user_handler));
-
-    user_router
 }
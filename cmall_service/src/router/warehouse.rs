@@ -0,0 +1,30 @@
+use crate::{
+    create_warehouse_handler, delete_warehouse_handler, get_warehouse_handler,
+    list_warehouse_handler, update_warehouse_handler, AppState,
+};
+use axum::{routing::*, Router};
+use cmall_core::RequirePermissionExt;
+
+pub fn setup_warehouse_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/:id",
+            get(get_warehouse_handler).require_permission(state, "warehouse:query"),
+        )
+        .route(
+            "/:id",
+            post(update_warehouse_handler).require_permission(state, "warehouse:update"),
+        )
+        .route(
+            "/:id",
+            delete(delete_warehouse_handler).require_permission(state, "warehouse:delete"),
+        )
+        .route(
+            "/",
+            get(list_warehouse_handler).require_permission(state, "warehouse:query"),
+        )
+        .route(
+            "/",
+            post(create_warehouse_handler).require_permission(state, "warehouse:create"),
+        )
+}
@@ -1,6 +1,5 @@
-
+use std::fmt::Display;
 use std::{self, fmt};
-use std::fmt::{ Display};
 
 use serde::{de, ser};
 use tracing::info;
@@ -50,14 +49,12 @@ impl Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::Message(msg) => formatter.write_str(msg),
-            Error::Eof =>{
+            Error::Eof => {
                 info!("error");
                 formatter.write_str("unexpected end of input")
-            },
-            /* and so forth */
+            } /* and so forth */
         }
     }
 }
 
 impl std::error::Error for Error {}
-
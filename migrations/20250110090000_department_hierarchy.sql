@@ -0,0 +1,7 @@
+-- Add migration script here
+-- add parent/child hierarchy for departments
+ALTER TABLE departments ADD COLUMN IF NOT EXISTS parent_id BIGINT REFERENCES departments(id);
+ALTER TABLE departments ADD COLUMN IF NOT EXISTS order_num INT NOT NULL DEFAULT 0;
+
+-- create index for department for parent_id
+CREATE INDEX IF NOT EXISTS department_parent_index ON departments(parent_id);
@@ -0,0 +1,12 @@
+-- Add migration script here
+-- 添加角色菜单关系表
+CREATE TABLE IF NOT EXISTS role_menus (
+    role_id BIGINT NOT NULL,
+    menu_id BIGINT NOT NULL,
+    PRIMARY KEY (role_id, menu_id),
+    FOREIGN KEY (role_id) REFERENCES roles(id),
+    FOREIGN KEY (menu_id) REFERENCES menus(id)
+);
+
+-- create index for role_menus for menu_id
+CREATE INDEX IF NOT EXISTS role_menus_menu_index ON role_menus(menu_id);
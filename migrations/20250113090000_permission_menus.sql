@@ -0,0 +1,32 @@
+-- Add migration script here
+-- seed system menus and the button permission codes checked by the routers
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('system', '/system', '系统管理', 'System', 'setting', 1, 'menu', 'root', 'enable', '', 'system', 'system'),
+('user', '/system/user', '用户管理', 'User', 'user', 1, 'menu', 'system', 'enable', '', 'system', 'system'),
+('user:query', '', '查询用户', 'Query User', '', 1, 'button', 'user', 'enable', '', 'system', 'system'),
+('user:create', '', '新增用户', 'Create User', '', 2, 'button', 'user', 'enable', '', 'system', 'system'),
+('user:update', '', '修改用户', 'Update User', '', 3, 'button', 'user', 'enable', '', 'system', 'system'),
+('user:delete', '', '删除用户', 'Delete User', '', 4, 'button', 'user', 'enable', '', 'system', 'system'),
+('user:export', '', '导出用户', 'Export User', '', 5, 'button', 'user', 'enable', '', 'system', 'system'),
+('role', '/system/role', '角色管理', 'Role', 'team', 2, 'menu', 'system', 'enable', '', 'system', 'system'),
+('role:query', '', '查询角色', 'Query Role', '', 1, 'button', 'role', 'enable', '', 'system', 'system'),
+('role:create', '', '新增角色', 'Create Role', '', 2, 'button', 'role', 'enable', '', 'system', 'system'),
+('role:update', '', '修改角色', 'Update Role', '', 3, 'button', 'role', 'enable', '', 'system', 'system'),
+('role:delete', '', '删除角色', 'Delete Role', '', 4, 'button', 'role', 'enable', '', 'system', 'system'),
+('role:grant', '', '分配权限', 'Grant Role', '', 5, 'button', 'role', 'enable', '', 'system', 'system'),
+('dept', '/system/dept', '部门管理', 'Department', 'cluster', 3, 'menu', 'system', 'enable', '', 'system', 'system'),
+('dept:query', '', '查询部门', 'Query Department', '', 1, 'button', 'dept', 'enable', '', 'system', 'system'),
+('dept:create', '', '新增部门', 'Create Department', '', 2, 'button', 'dept', 'enable', '', 'system', 'system'),
+('dept:update', '', '修改部门', 'Update Department', '', 3, 'button', 'dept', 'enable', '', 'system', 'system'),
+('dept:delete', '', '删除部门', 'Delete Department', '', 4, 'button', 'dept', 'enable', '', 'system', 'system'),
+('menu', '/system/menu', '菜单管理', 'Menu', 'menu', 4, 'menu', 'system', 'enable', '', 'system', 'system'),
+('menu:query', '', '查询菜单', 'Query Menu', '', 1, 'button', 'menu', 'enable', '', 'system', 'system'),
+('menu:create', '', '新增菜单', 'Create Menu', '', 2, 'button', 'menu', 'enable', '', 'system', 'system'),
+('menu:update', '', '修改菜单', 'Update Menu', '', 3, 'button', 'menu', 'enable', '', 'system', 'system'),
+('menu:delete', '', '删除菜单', 'Delete Menu', '', 4, 'button', 'menu', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+-- grant every seeded menu to the admin role if it exists
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- backfill user_roles from users.roles, skipping role ids that do not exist
+INSERT INTO user_roles(user_id, role_id)
+  SELECT u.id, r.id FROM users u
+  CROSS JOIN LATERAL unnest(u.roles) AS ur(role_id)
+  JOIN roles r ON r.id = ur.role_id
+ON CONFLICT DO NOTHING;
+
+-- user_roles is the only source of role membership from now on
+ALTER TABLE users DROP COLUMN IF EXISTS roles;
+
+-- create index for user_roles for role_id
+CREATE INDEX IF NOT EXISTS user_roles_role_index ON user_roles(role_id);
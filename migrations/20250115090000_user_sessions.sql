@@ -0,0 +1,16 @@
+-- Add migration script here
+-- 登录会话表，refresh token 只保存哈希
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    refresh_token_hash VARCHAR(128) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    create_time TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    update_time TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+-- create index for user_sessions for refresh_token_hash
+CREATE UNIQUE INDEX IF NOT EXISTS refresh_token_hash_index ON user_sessions(refresh_token_hash);
+CREATE INDEX IF NOT EXISTS user_sessions_user_index ON user_sessions(user_id);
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- 管理员重置密码后，用户下次登录必须修改密码
+ALTER TABLE users ADD COLUMN IF NOT EXISTS must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
+
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('user:reset-password', '', '重置密码', 'Reset Password', '', 6, 'button', 'user', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id = 'user:reset-password'
+ON CONFLICT DO NOTHING;
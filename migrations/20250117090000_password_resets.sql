@@ -0,0 +1,14 @@
+-- Add migration script here
+-- 找回密码的重置令牌，只保存哈希，使用一次后失效
+CREATE TABLE IF NOT EXISTS password_resets (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    token_hash VARCHAR(128) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    create_time TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS password_reset_token_hash_index ON password_resets(token_hash);
+CREATE INDEX IF NOT EXISTS password_resets_user_index ON password_resets(user_id);
@@ -0,0 +1,22 @@
+-- Add migration script here
+-- 登录失败次数与账号锁定
+ALTER TABLE users ADD COLUMN IF NOT EXISTS failed_attempts INT NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+
+-- 按客户端地址记录的登录失败，ip 和 email 是客户端原样提交的值，不限制长度
+CREATE TABLE IF NOT EXISTS signin_failures (
+    id BIGSERIAL PRIMARY KEY,
+    ip TEXT NOT NULL,
+    email TEXT NOT NULL,
+    create_time TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS signin_failures_ip_index ON signin_failures(ip, create_time);
+
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('user:unlock', '', '解锁用户', 'Unlock User', '', 7, 'button', 'user', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id = 'user:unlock'
+ON CONFLICT DO NOTHING;
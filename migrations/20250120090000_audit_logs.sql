@@ -0,0 +1,30 @@
+-- Add migration script here
+-- 审计日志，用户删除后仍保留记录，不关联外键
+-- actor_name、action 和 ip 来自请求路径和转发头，不限制长度，避免超长时审计写入失败
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id BIGSERIAL PRIMARY KEY,
+    actor_id BIGINT NOT NULL,
+    actor_name TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target_type VARCHAR(64) NOT NULL,
+    target_id BIGINT,
+    before_data JSONB,
+    after_data JSONB,
+    status_code INT NOT NULL,
+    ip TEXT NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS audit_logs_create_time_index ON audit_logs(create_time);
+CREATE INDEX IF NOT EXISTS audit_logs_target_index ON audit_logs(target_type, target_id);
+CREATE INDEX IF NOT EXISTS audit_logs_actor_index ON audit_logs(actor_id);
+
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('audit', '/system/audit', '审计日志', 'Audit', 'audit', 5, 'menu', 'system', 'enable', '', 'system', 'system'),
+('audit:query', '', '查询审计日志', 'Query Audit Log', '', 1, 'button', 'audit', 'enable', '', 'system', 'system'),
+('audit:export', '', '导出审计日志', 'Export Audit Log', '', 2, 'button', 'audit', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('audit', 'audit:query', 'audit:export')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,25 @@
+-- Add migration script here
+-- 软删除，删除时只记录删除时间，超过保留期后由管理员清理
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE roles ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE departments ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE menus ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_deleted_at_index ON users(deleted_at);
+CREATE INDEX IF NOT EXISTS roles_deleted_at_index ON roles(deleted_at);
+CREATE INDEX IF NOT EXISTS departments_deleted_at_index ON departments(deleted_at);
+CREATE INDEX IF NOT EXISTS menus_deleted_at_index ON menus(deleted_at);
+
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('user:restore', '', '恢复用户', 'Restore User', '', 8, 'button', 'user', 'enable', '', 'system', 'system'),
+('role:restore', '', '恢复角色', 'Restore Role', '', 6, 'button', 'role', 'enable', '', 'system', 'system'),
+('dept:restore', '', '恢复部门', 'Restore Department', '', 5, 'button', 'dept', 'enable', '', 'system', 'system'),
+('menu:restore', '', '恢复菜单', 'Restore Menu', '', 5, 'button', 'menu', 'enable', '', 'system', 'system'),
+('recycle', '/system/recycle', '回收站', 'Recycle Bin', 'delete', 6, 'menu', 'system', 'enable', '', 'system', 'system'),
+('recycle:query', '', '查询回收站', 'Query Recycle Bin', '', 1, 'button', 'recycle', 'enable', '', 'system', 'system'),
+('recycle:purge', '', '清理回收站', 'Purge Recycle Bin', '', 2, 'button', 'recycle', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('user:restore', 'role:restore', 'dept:restore', 'menu:restore', 'recycle', 'recycle:query', 'recycle:purge')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,8 @@
+-- Add migration script here
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('user:import', '', '导入用户', 'Import User', '', 9, 'button', 'user', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id = 'user:import'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,24 @@
+-- Add migration script here
+CREATE TYPE export_status AS ENUM(
+    'pending',
+    'running',
+    'done',
+    'failed'
+);
+
+-- 后台导出任务，生成的文件保存在 base_dir/exports 下
+CREATE TABLE IF NOT EXISTS export_jobs (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    resource VARCHAR(64) NOT NULL,
+    params JSONB NOT NULL DEFAULT '{}',
+    status export_status NOT NULL DEFAULT 'pending',
+    file_name VARCHAR(255) NOT NULL,
+    file_path VARCHAR(512) NOT NULL,
+    row_count BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finish_time TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS export_jobs_user_index ON export_jobs(user_id);
@@ -0,0 +1,10 @@
+-- Add migration script here
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('role:export', '', '导出角色', 'Export Role', '', 7, 'button', 'role', 'enable', '', 'system', 'system'),
+('dept:export', '', '导出部门', 'Export Department', '', 6, 'button', 'dept', 'enable', '', 'system', 'system'),
+('menu:export', '', '导出菜单', 'Export Menu', '', 6, 'button', 'menu', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('role:export', 'dept:export', 'menu:export')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- trigram indexes for case-insensitive substring search on users
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS users_username_trgm_index ON users USING gin (username gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_email_trgm_index ON users USING gin (email gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_create_time_index ON users(create_time);
+CREATE INDEX IF NOT EXISTS users_dept_index ON users(dept_id);
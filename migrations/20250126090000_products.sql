@@ -0,0 +1,62 @@
+-- Add migration script here
+CREATE TYPE product_status AS ENUM(
+    'draft',
+    'on_sale',
+    'off_shelf'
+);
+
+-- 价格均以分为单位保存
+CREATE TABLE IF NOT EXISTS products (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(128) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    status product_status NOT NULL DEFAULT 'draft',
+    price BIGINT NOT NULL CHECK (price >= 0),
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    create_by VARCHAR(64) NOT NULL,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_by VARCHAR(64) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS products_status_index ON products(status);
+
+-- 商品的规格定义，例如 颜色: [红, 蓝]
+CREATE TABLE IF NOT EXISTS product_attributes (
+    id BIGSERIAL PRIMARY KEY,
+    product_id BIGINT NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    options TEXT[] NOT NULL,
+    order_num INT NOT NULL DEFAULT 0
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS product_attribute_name_index ON product_attributes(product_id, name);
+
+-- 每个 SKU 对应一种规格组合
+CREATE TABLE IF NOT EXISTS product_skus (
+    id BIGSERIAL PRIMARY KEY,
+    product_id BIGINT NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    sku_code VARCHAR(64) NOT NULL,
+    price BIGINT NOT NULL CHECK (price >= 0),
+    attributes JSONB NOT NULL DEFAULT '{}',
+    status effect_status NOT NULL DEFAULT 'enable',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS product_sku_code_index ON product_skus(sku_code);
+CREATE UNIQUE INDEX IF NOT EXISTS product_sku_attributes_index ON product_skus(product_id, attributes);
+
+-- seed the mall menus and product permission codes
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('mall', '/mall', '商城管理', 'Mall', 'shop', 2, 'menu', 'root', 'enable', '', 'system', 'system'),
+('product', '/mall/product', '商品管理', 'Product', 'shopping', 1, 'menu', 'mall', 'enable', '', 'system', 'system'),
+('product:query', '', '查询商品', 'Query Product', '', 1, 'button', 'product', 'enable', '', 'system', 'system'),
+('product:create', '', '新增商品', 'Create Product', '', 2, 'button', 'product', 'enable', '', 'system', 'system'),
+('product:update', '', '修改商品', 'Update Product', '', 3, 'button', 'product', 'enable', '', 'system', 'system'),
+('product:delete', '', '删除商品', 'Delete Product', '', 4, 'button', 'product', 'enable', '', 'system', 'system'),
+('product:export', '', '导出商品', 'Export Product', '', 5, 'button', 'product', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('mall', 'product', 'product:query', 'product:create', 'product:update', 'product:delete', 'product:export')
+ON CONFLICT DO NOTHING;
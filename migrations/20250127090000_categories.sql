@@ -0,0 +1,34 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS categories (
+    id BIGSERIAL PRIMARY KEY,
+    parent_id BIGINT REFERENCES categories(id),
+    name VARCHAR(64) NOT NULL,
+    icon VARCHAR(128) NOT NULL DEFAULT '',
+    order_num INT NOT NULL DEFAULT 0,
+    status effect_status NOT NULL DEFAULT 'enable',
+    description TEXT NOT NULL DEFAULT '',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    create_by VARCHAR(64) NOT NULL,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_by VARCHAR(64) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS category_parent_id_index ON categories(parent_id, order_num);
+
+-- 仍有商品的分类不能删除
+ALTER TABLE products ADD COLUMN category_id BIGINT REFERENCES categories(id);
+
+CREATE INDEX IF NOT EXISTS product_category_id_index ON products(category_id);
+
+-- seed the category menu and permission codes
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('category', '/mall/category', '分类管理', 'Category', 'apps', 2, 'menu', 'mall', 'enable', '', 'system', 'system'),
+('category:query', '', '查询分类', 'Query Category', '', 1, 'button', 'category', 'enable', '', 'system', 'system'),
+('category:create', '', '新增分类', 'Create Category', '', 2, 'button', 'category', 'enable', '', 'system', 'system'),
+('category:update', '', '修改分类', 'Update Category', '', 3, 'button', 'category', 'enable', '', 'system', 'system'),
+('category:delete', '', '删除分类', 'Delete Category', '', 4, 'button', 'category', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('category', 'category:query', 'category:create', 'category:update', 'category:delete')
+ON CONFLICT DO NOTHING;
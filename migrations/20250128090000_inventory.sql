@@ -0,0 +1,47 @@
+-- Add migration script here
+CREATE TYPE stock_movement_kind AS ENUM(
+    'inbound',
+    'sale',
+    'return',
+    'adjustment',
+    'reserve',
+    'release'
+);
+
+-- 可售数量为 on_hand - reserved，约束保证不会超卖
+CREATE TABLE IF NOT EXISTS inventories (
+    sku_id BIGINT PRIMARY KEY REFERENCES product_skus(id) ON DELETE CASCADE,
+    on_hand BIGINT NOT NULL DEFAULT 0 CHECK (on_hand >= 0),
+    reserved BIGINT NOT NULL DEFAULT 0 CHECK (reserved >= 0),
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (reserved <= on_hand)
+);
+
+-- 库存流水只追加，删除 SKU 后仍然保留，因此不设置外键
+CREATE TABLE IF NOT EXISTS stock_movements (
+    id BIGSERIAL PRIMARY KEY,
+    sku_id BIGINT NOT NULL,
+    kind stock_movement_kind NOT NULL,
+    quantity BIGINT NOT NULL DEFAULT 0,
+    reserved BIGINT NOT NULL DEFAULT 0,
+    balance BIGINT NOT NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    reference VARCHAR(64) NOT NULL DEFAULT '',
+    create_by VARCHAR(64) NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS stock_movement_sku_id_index ON stock_movements(sku_id, id);
+CREATE INDEX IF NOT EXISTS stock_movement_reference_index ON stock_movements(reference);
+
+-- seed the inventory menu and permission codes
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('inventory', '/mall/inventory', '库存管理', 'Inventory', 'storage', 3, 'menu', 'mall', 'enable', '', 'system', 'system'),
+('inventory:query', '', '查询库存', 'Query Inventory', '', 1, 'button', 'inventory', 'enable', '', 'system', 'system'),
+('inventory:adjust', '', '调整库存', 'Adjust Inventory', '', 2, 'button', 'inventory', 'enable', '', 'system', 'system'),
+('inventory:export', '', '导出库存', 'Export Inventory', '', 3, 'button', 'inventory', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('inventory', 'inventory:query', 'inventory:adjust', 'inventory:export')
+ON CONFLICT DO NOTHING;
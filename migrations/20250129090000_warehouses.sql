@@ -0,0 +1,78 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS warehouses (
+    id BIGSERIAL PRIMARY KEY,
+    code VARCHAR(64) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    address TEXT NOT NULL DEFAULT '',
+    priority INT NOT NULL DEFAULT 0,
+    status effect_status NOT NULL DEFAULT 'enable',
+    description TEXT NOT NULL DEFAULT '',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    create_by VARCHAR(64) NOT NULL,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_by VARCHAR(64) NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS warehouse_code_index ON warehouses(code);
+
+-- 已有库存归入默认仓库
+INSERT INTO warehouses (code, name, priority, create_by, update_by)
+  VALUES ('default', '默认仓库', 1, 'system', 'system')
+ON CONFLICT (code) DO NOTHING;
+
+ALTER TABLE inventories ADD COLUMN warehouse_id BIGINT REFERENCES warehouses(id);
+UPDATE inventories SET warehouse_id = (SELECT id FROM warehouses WHERE code = 'default');
+ALTER TABLE inventories ALTER COLUMN warehouse_id SET NOT NULL;
+ALTER TABLE inventories DROP CONSTRAINT inventories_pkey;
+ALTER TABLE inventories ADD PRIMARY KEY (warehouse_id, sku_id);
+CREATE INDEX IF NOT EXISTS inventory_sku_id_index ON inventories(sku_id);
+
+ALTER TABLE stock_movements ADD COLUMN warehouse_id BIGINT;
+UPDATE stock_movements SET warehouse_id = (SELECT id FROM warehouses WHERE code = 'default');
+ALTER TABLE stock_movements ALTER COLUMN warehouse_id SET NOT NULL;
+
+ALTER TYPE stock_movement_kind ADD VALUE IF NOT EXISTS 'transfer_out';
+ALTER TYPE stock_movement_kind ADD VALUE IF NOT EXISTS 'transfer_in';
+
+CREATE TYPE transfer_status AS ENUM(
+    'pending',
+    'completed',
+    'cancelled'
+);
+
+CREATE TABLE IF NOT EXISTS stock_transfers (
+    id BIGSERIAL PRIMARY KEY,
+    from_warehouse_id BIGINT NOT NULL REFERENCES warehouses(id),
+    to_warehouse_id BIGINT NOT NULL REFERENCES warehouses(id),
+    status transfer_status NOT NULL DEFAULT 'pending',
+    remark TEXT NOT NULL DEFAULT '',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    create_by VARCHAR(64) NOT NULL,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_by VARCHAR(64) NOT NULL,
+    CHECK (from_warehouse_id <> to_warehouse_id)
+);
+
+CREATE TABLE IF NOT EXISTS stock_transfer_items (
+    transfer_id BIGINT NOT NULL REFERENCES stock_transfers(id) ON DELETE CASCADE,
+    sku_id BIGINT NOT NULL REFERENCES product_skus(id),
+    quantity BIGINT NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (transfer_id, sku_id)
+);
+
+-- seed the warehouse and transfer menus and permission codes
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('warehouse', '/mall/warehouse', '仓库管理', 'Warehouse', 'home', 4, 'menu', 'mall', 'enable', '', 'system', 'system'),
+('warehouse:query', '', '查询仓库', 'Query Warehouse', '', 1, 'button', 'warehouse', 'enable', '', 'system', 'system'),
+('warehouse:create', '', '新增仓库', 'Create Warehouse', '', 2, 'button', 'warehouse', 'enable', '', 'system', 'system'),
+('warehouse:update', '', '修改仓库', 'Update Warehouse', '', 3, 'button', 'warehouse', 'enable', '', 'system', 'system'),
+('warehouse:delete', '', '删除仓库', 'Delete Warehouse', '', 4, 'button', 'warehouse', 'enable', '', 'system', 'system'),
+('transfer', '/mall/transfer', '库存调拨', 'Stock Transfer', 'swap', 5, 'menu', 'mall', 'enable', '', 'system', 'system'),
+('transfer:query', '', '查询调拨单', 'Query Transfer', '', 1, 'button', 'transfer', 'enable', '', 'system', 'system'),
+('transfer:create', '', '新增调拨单', 'Create Transfer', '', 2, 'button', 'transfer', 'enable', '', 'system', 'system'),
+('transfer:update', '', '完成或取消调拨', 'Complete Transfer', '', 3, 'button', 'transfer', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('warehouse', 'warehouse:query', 'warehouse:create', 'warehouse:update', 'warehouse:delete', 'transfer', 'transfer:query', 'transfer:create', 'transfer:update')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,21 @@
+-- Add migration script here
+-- 登录用户每人一个购物车，游客购物车只保存令牌的哈希
+CREATE TABLE IF NOT EXISTS carts (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    guest_token VARCHAR(64) UNIQUE,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((user_id IS NULL) <> (guest_token IS NULL))
+);
+
+-- added_price 为加入购物车时的价格，用于提示价格变动
+CREATE TABLE IF NOT EXISTS cart_items (
+    cart_id BIGINT NOT NULL REFERENCES carts(id) ON DELETE CASCADE,
+    sku_id BIGINT NOT NULL REFERENCES product_skus(id) ON DELETE CASCADE,
+    quantity BIGINT NOT NULL CHECK (quantity > 0),
+    added_price BIGINT NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (cart_id, sku_id)
+);
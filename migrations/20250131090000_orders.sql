@@ -0,0 +1,57 @@
+-- Add migration script here
+CREATE TYPE order_status AS ENUM(
+    'pending_payment',
+    'paid',
+    'shipped',
+    'delivered',
+    'completed',
+    'cancelled',
+    'closed'
+);
+
+-- 金额以分为单位
+CREATE TABLE IF NOT EXISTS orders (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id),
+    status order_status NOT NULL DEFAULT 'pending_payment',
+    total_amount BIGINT NOT NULL CHECK (total_amount >= 0),
+    remark TEXT NOT NULL DEFAULT '',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS order_user_index ON orders(user_id, create_time);
+
+-- 下单时的商品快照，删除 SKU 后仍然保留，因此不设置外键
+CREATE TABLE IF NOT EXISTS order_items (
+    order_id BIGINT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    sku_id BIGINT NOT NULL,
+    sku_code VARCHAR(64) NOT NULL,
+    product_id BIGINT NOT NULL,
+    product_name VARCHAR(128) NOT NULL,
+    attributes JSONB NOT NULL DEFAULT '{}',
+    price BIGINT NOT NULL,
+    quantity BIGINT NOT NULL CHECK (quantity > 0),
+    line_total BIGINT NOT NULL,
+    PRIMARY KEY (order_id, sku_id)
+);
+
+-- 订单在各仓库预留的库存，发货时出库，取消或关闭时释放
+CREATE TABLE IF NOT EXISTS order_allocations (
+    order_id BIGINT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    warehouse_id BIGINT NOT NULL REFERENCES warehouses(id),
+    sku_id BIGINT NOT NULL,
+    quantity BIGINT NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (order_id, warehouse_id, sku_id)
+);
+
+-- seed the order menus and permission codes
+INSERT INTO menus(menu_id, path, chinese_name, english_name, icon, order_num, type, parent_menu_id, status, description, create_by, update_by)
+  VALUES ('order', '/mall/order', '订单管理', 'Order', 'file-text', 6, 'menu', 'mall', 'enable', '', 'system', 'system'),
+('order:query', '', '查询订单', 'Query Order', '', 1, 'button', 'order', 'enable', '', 'system', 'system'),
+('order:update', '', '修改订单状态', 'Update Order Status', '', 2, 'button', 'order', 'enable', '', 'system', 'system')
+ON CONFLICT (menu_id) DO NOTHING;
+
+INSERT INTO role_menus(role_id, menu_id)
+  SELECT r.id, m.id FROM roles r CROSS JOIN menus m WHERE r.code = 'admin' AND m.menu_id IN ('order', 'order:query', 'order:update')
+ON CONFLICT DO NOTHING;
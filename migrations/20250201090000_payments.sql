@@ -0,0 +1,36 @@
+-- Add migration script here
+CREATE TYPE payment_status AS ENUM(
+    'pending',
+    'succeeded',
+    'failed',
+    'refunding',
+    'refunded'
+);
+
+-- 每次发起支付都记录一条，同一订单可以有多次尝试
+CREATE TABLE IF NOT EXISTS payments (
+    id BIGSERIAL PRIMARY KEY,
+    order_id BIGINT NOT NULL REFERENCES orders(id),
+    gateway VARCHAR(32) NOT NULL,
+    external_id VARCHAR(128),
+    amount BIGINT NOT NULL CHECK (amount >= 0),
+    status payment_status NOT NULL DEFAULT 'pending',
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    update_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS payment_external_index ON payments(gateway, external_id);
+CREATE INDEX IF NOT EXISTS payment_order_index ON payments(order_id);
+CREATE INDEX IF NOT EXISTS payment_refunding_index ON payments(id) WHERE status = 'refunding';
+
+-- 支付回调按渠道的事件 id 去重，重复通知只处理一次
+CREATE TABLE IF NOT EXISTS payment_events (
+    id BIGSERIAL PRIMARY KEY,
+    gateway VARCHAR(32) NOT NULL,
+    event_id VARCHAR(128) NOT NULL,
+    external_id VARCHAR(128) NOT NULL,
+    status payment_status NOT NULL,
+    payload JSONB NOT NULL,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (gateway, event_id)
+);